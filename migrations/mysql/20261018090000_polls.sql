@@ -0,0 +1,22 @@
+CREATE TABLE polls (
+	id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+	title TEXT NOT NULL,
+	description TEXT NOT NULL
+);
+
+-- Existing items and ballots belong to the first poll
+INSERT INTO polls(id, title, description) VALUES (1, 'Poll', '');
+
+ALTER TABLE items ADD COLUMN poll_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE items ALTER COLUMN poll_id DROP DEFAULT;
+ALTER TABLE items ADD FOREIGN KEY (poll_id) REFERENCES polls(id);
+CREATE INDEX items_by_poll ON items(poll_id);
+
+ALTER TABLE ballots ADD COLUMN poll_id INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE ballots ALTER COLUMN poll_id DROP DEFAULT;
+ALTER TABLE ballots ADD FOREIGN KEY (poll_id) REFERENCES polls(id);
+
+-- A voter has one ballot per poll
+DROP INDEX unique_ballot ON ballots;
+DROP INDEX uuid ON ballots;
+CREATE UNIQUE INDEX unique_ballot ON ballots(poll_id, uuid);
@@ -0,0 +1,5 @@
+ALTER TABLE items ADD COLUMN position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE items ADD COLUMN retired BOOLEAN NOT NULL DEFAULT 0;
+
+-- Keep the current order of the items
+UPDATE items SET position = id;
@@ -0,0 +1,14 @@
+CREATE TABLE ballot_revisions (
+	id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+	poll_id INTEGER NOT NULL,
+	ballot_id INTEGER NOT NULL,
+	items TEXT NOT NULL,
+	created_at DATETIME(6) NOT NULL,
+	previous_hash CHAR(64) NOT NULL,
+	hash CHAR(64) NOT NULL,
+
+	FOREIGN KEY (poll_id) REFERENCES polls(id),
+	FOREIGN KEY (ballot_id) REFERENCES ballots(id)
+);
+-- Every revision follows exactly one revision of its poll
+CREATE UNIQUE INDEX unique_revision_link ON ballot_revisions(poll_id, previous_hash);
@@ -0,0 +1,14 @@
+-- Existing polls stay open
+ALTER TABLE polls ADD COLUMN status VARCHAR(16) NOT NULL DEFAULT 'open';
+ALTER TABLE polls ADD COLUMN opens_at DATETIME(6) NULL;
+ALTER TABLE polls ADD COLUMN closes_at DATETIME(6) NULL;
+
+-- The final tally of a closed poll
+CREATE TABLE poll_results (
+	poll_id INTEGER NOT NULL PRIMARY KEY,
+	result JSON NOT NULL,
+	trace JSON NOT NULL,
+	created_at DATETIME(6) NOT NULL,
+
+	FOREIGN KEY (poll_id) REFERENCES polls(id)
+);
@@ -0,0 +1,10 @@
+-- One-time tokens for registering in polls with a voter roll, only their hash is kept
+CREATE TABLE voter_tokens (
+	id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+	poll_id INTEGER NOT NULL,
+	token_hash CHAR(64) NOT NULL UNIQUE,
+	used_at DATETIME(6) NULL,
+
+	FOREIGN KEY (poll_id) REFERENCES polls(id)
+);
+CREATE INDEX voter_tokens_by_poll ON voter_tokens(poll_id);
@@ -0,0 +1,17 @@
+-- Existing polls keep ranked ballots
+ALTER TABLE polls ADD COLUMN ballot_type VARCHAR(16) NOT NULL DEFAULT 'ranked';
+
+-- Approvals are stored as a score of 1
+CREATE TABLE scores (
+	id INTEGER NOT NULL AUTO_INCREMENT PRIMARY KEY,
+	ballot_id INTEGER NOT NULL,
+	item_id INTEGER NOT NULL,
+	score INTEGER NOT NULL,
+
+	FOREIGN KEY (ballot_id) REFERENCES ballots(id),
+	FOREIGN KEY (item_id) REFERENCES items(id)
+);
+CREATE UNIQUE INDEX unique_score ON scores(ballot_id, item_id);
+
+-- The totals of a closed poll with scored ballots
+ALTER TABLE poll_results ADD COLUMN scores JSON NOT NULL DEFAULT ('null');
@@ -0,0 +1,11 @@
+CREATE TABLE ballot_revisions (
+	id SERIAL PRIMARY KEY,
+	poll_id INTEGER NOT NULL REFERENCES polls(id),
+	ballot_id INTEGER NOT NULL REFERENCES ballots(id),
+	items TEXT NOT NULL,
+	created_at TIMESTAMPTZ NOT NULL,
+	previous_hash CHAR(64) NOT NULL,
+	hash CHAR(64) NOT NULL
+);
+-- Every revision follows exactly one revision of its poll
+CREATE UNIQUE INDEX unique_revision_link ON ballot_revisions(poll_id, previous_hash);
@@ -0,0 +1,12 @@
+-- Existing polls stay open
+ALTER TABLE polls ADD COLUMN status TEXT NOT NULL DEFAULT 'open';
+ALTER TABLE polls ADD COLUMN opens_at TIMESTAMPTZ;
+ALTER TABLE polls ADD COLUMN closes_at TIMESTAMPTZ;
+
+-- The final tally of a closed poll
+CREATE TABLE poll_results (
+	poll_id INTEGER PRIMARY KEY REFERENCES polls(id),
+	result JSONB NOT NULL,
+	trace JSONB NOT NULL,
+	created_at TIMESTAMPTZ NOT NULL
+);
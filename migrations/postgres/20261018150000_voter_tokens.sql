@@ -0,0 +1,8 @@
+-- One-time tokens for registering in polls with a voter roll, only their hash is kept
+CREATE TABLE voter_tokens (
+	id SERIAL PRIMARY KEY,
+	poll_id INTEGER NOT NULL REFERENCES polls(id),
+	token_hash CHAR(64) NOT NULL UNIQUE,
+	used_at TIMESTAMPTZ
+);
+CREATE INDEX voter_tokens_by_poll ON voter_tokens(poll_id);
@@ -0,0 +1,36 @@
+CREATE TABLE polls (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	title TEXT NOT NULL,
+	description TEXT NOT NULL
+);
+
+-- Items and ballots belong to a poll, start with one
+INSERT INTO polls(title, description) VALUES ('Poll', '');
+
+CREATE TABLE ballots (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	poll_id INTEGER NOT NULL REFERENCES polls(id),
+	uuid BLOB NOT NULL
+);
+-- A voter has one ballot per poll
+CREATE UNIQUE INDEX unique_ballot ON ballots(poll_id, uuid);
+
+CREATE TABLE items (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	poll_id INTEGER NOT NULL REFERENCES polls(id),
+	title TEXT NOT NULL,
+	content TEXT NOT NULL,
+	done BOOLEAN NOT NULL DEFAULT 0,
+	position INTEGER NOT NULL DEFAULT 0,
+	retired BOOLEAN NOT NULL DEFAULT 0
+);
+CREATE INDEX items_by_poll ON items(poll_id);
+
+CREATE TABLE rankings (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	ballot_id INTEGER NOT NULL REFERENCES ballots(id),
+	item_id INTEGER NOT NULL REFERENCES items(id),
+	ord INTEGER NOT NULL
+);
+CREATE INDEX ordering_by_ballot_item ON rankings(ballot_id ASC, item_id ASC);
+CREATE UNIQUE INDEX unique_ranking ON rankings(ballot_id, item_id);
@@ -0,0 +1,11 @@
+CREATE TABLE ballot_revisions (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	poll_id INTEGER NOT NULL REFERENCES polls(id),
+	ballot_id INTEGER NOT NULL REFERENCES ballots(id),
+	items TEXT NOT NULL,
+	created_at TEXT NOT NULL,
+	previous_hash TEXT NOT NULL,
+	hash TEXT NOT NULL
+);
+-- Every revision follows exactly one revision of its poll
+CREATE UNIQUE INDEX unique_revision_link ON ballot_revisions(poll_id, previous_hash);
@@ -0,0 +1,12 @@
+-- Existing polls stay open
+ALTER TABLE polls ADD COLUMN status TEXT NOT NULL DEFAULT 'open';
+ALTER TABLE polls ADD COLUMN opens_at TEXT;
+ALTER TABLE polls ADD COLUMN closes_at TEXT;
+
+-- The final tally of a closed poll
+CREATE TABLE poll_results (
+	poll_id INTEGER PRIMARY KEY REFERENCES polls(id),
+	result TEXT NOT NULL,
+	trace TEXT NOT NULL,
+	created_at TEXT NOT NULL
+);
@@ -0,0 +1,8 @@
+-- One-time tokens for registering in polls with a voter roll, only their hash is kept
+CREATE TABLE voter_tokens (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	poll_id INTEGER NOT NULL REFERENCES polls(id),
+	token_hash TEXT NOT NULL UNIQUE,
+	used_at TEXT
+);
+CREATE INDEX voter_tokens_by_poll ON voter_tokens(poll_id);
@@ -0,0 +1,5 @@
+-- Existing polls elect a single item
+ALTER TABLE polls ADD COLUMN seats INTEGER NOT NULL DEFAULT 1;
+
+-- The elected items of a closed poll with more than one seat
+ALTER TABLE poll_results ADD COLUMN election TEXT NOT NULL DEFAULT 'null';
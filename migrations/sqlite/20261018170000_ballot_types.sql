@@ -0,0 +1,14 @@
+-- Existing polls keep ranked ballots
+ALTER TABLE polls ADD COLUMN ballot_type TEXT NOT NULL DEFAULT 'ranked';
+
+-- Approvals are stored as a score of 1
+CREATE TABLE scores (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	ballot_id INTEGER NOT NULL REFERENCES ballots(id),
+	item_id INTEGER NOT NULL REFERENCES items(id),
+	score INTEGER NOT NULL
+);
+CREATE UNIQUE INDEX unique_score ON scores(ballot_id, item_id);
+
+-- The totals of a closed poll with scored ballots
+ALTER TABLE poll_results ADD COLUMN scores TEXT NOT NULL DEFAULT 'null';
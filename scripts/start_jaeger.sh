@@ -18,6 +18,8 @@ if [[ -z "${SKIP_DOCKER}" ]]; then
 fi
 
 docker run \
+-e COLLECTOR_OTLP_ENABLED=true \
+-p "4317:4317/tcp" \
 -p "6831:6831/udp" \
 -p "6832:6832/udp" \
 -p "16686:16686/tcp" \
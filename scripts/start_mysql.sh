@@ -55,6 +55,6 @@ done
 # Run migrations
 export DATABASE_URL="mysql://${DB_USER}:${DB_PASS}@${DB_HOST}:${DB_PORT}/${DB_NAME}"
 sqlx database create
-sqlx migrate run
+sqlx migrate run --source migrations/mysql
 
 >&2 echo "MySQL has been migrated, ready to go!"
@@ -53,6 +53,6 @@ done
 # Run migrations
 export DATABASE_URL="postgresql://${DB_USER}:${DB_PASS}@${DB_HOST}:${DB_PORT}/${DB_NAME}"
 sqlx database create
-sqlx migrate run
+sqlx migrate run --source migrations/postgres
 
 >&2 echo "Postgres has been migrated, ready to go!"
@@ -1,51 +1,230 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::hash_map::RandomState,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    net::TcpListener,
+    path::{Path, PathBuf},
+};
 
 use crate::{
-    conf::{Configuration, DatabaseConfiguration},
-    repository::{ballot::BallotRepository, item::ItemRepository, ranking::RankingRepository},
+    conf::{Configuration, DatabaseConfiguration, DatabaseKind},
+    metrics::metrics,
+    model::RegistrationMode,
+    repository::{
+        memory, mysql, postgres, sqlite, BallotRepository, ItemRepository, PollRepository,
+        TransactableRankingRepository,
+    },
     route,
-    service::{ballot::BallotService, item::ItemService, ranking::RankingService},
+    service::{
+        ballot::BallotService, item::ItemService, poll::PollService, ranking::RankingService,
+        tally::TallyCache,
+    },
 };
 
+pub use self::tally::{tally, TallyArgs};
+
 use actix_web::dev::Server;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use sqlx::{
     migrate::Migrator,
     mysql::{MySqlConnectOptions, MySqlPoolOptions, MySqlSslMode},
+    postgres::{PgConnectOptions, PgPoolOptions, PgSslMode},
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    ConnectOptions,
 };
 
 pub async fn migrate<P>(directory: P, configuration: &Configuration) -> Result<(), anyhow::Error>
 where
     P: AsRef<Path>,
 {
-    let db_pool = db_pool(configuration.database());
     let migrator = Migrator::new(directory.as_ref()).await?;
-    migrator.run(&db_pool).await?;
+    match configuration.database().kind() {
+        DatabaseKind::Mysql => migrator.run(&mysql_pool(configuration.database())?).await?,
+        DatabaseKind::Postgres => {
+            migrator
+                .run(&postgres_pool(configuration.database())?)
+                .await?
+        }
+        DatabaseKind::Sqlite => {
+            migrator
+                .run(&sqlite_pool(configuration.database())?)
+                .await?
+        }
+        // Nothing is kept between runs, so there is nothing to migrate.
+        DatabaseKind::Memory => {}
+    }
     Ok(())
 }
 
 pub struct Application {
+    port: u16,
     server: Server,
+    db_pool: Option<DatabasePool>,
 }
 
 impl Application {
     pub fn new(configuration: &Configuration) -> Result<Self, anyhow::Error> {
-        let db_pool = db_pool(configuration.database());
-        let item_repository = ItemRepository::new(db_pool.clone());
-        let ballot_repository = BallotRepository::new(db_pool.clone());
-        let ranking_repository = RankingRepository::new(db_pool);
-
-        let item_service = ItemService::new(item_repository);
-        let ballot_service = BallotService::new(ballot_repository);
-        let ranking_service = RankingService::new(ranking_repository);
+        let listener = TcpListener::bind(configuration.application().address())?;
+        let port = listener.local_addr()?.port();
+        let (server, db_pool) = match configuration.database().kind() {
+            DatabaseKind::Mysql => {
+                let db_pool = mysql_pool(configuration.database())?;
+                if configuration.metrics().enabled() {
+                    metrics().register_pool(db_pool.clone());
+                }
+                let server = serve(
+                    listener,
+                    configuration,
+                    mysql::PollRepository::new(db_pool.clone()),
+                    mysql::ItemRepository::new(db_pool.clone()),
+                    mysql::BallotRepository::new(db_pool.clone()),
+                    mysql::RankingRepository::new(db_pool.clone()),
+                    shutdown_signal(),
+                )?;
+                (server, Some(DatabasePool::Mysql(db_pool)))
+            }
+            DatabaseKind::Postgres => {
+                let db_pool = postgres_pool(configuration.database())?;
+                if configuration.metrics().enabled() {
+                    metrics().register_pool(db_pool.clone());
+                }
+                let server = serve(
+                    listener,
+                    configuration,
+                    postgres::PollRepository::new(db_pool.clone()),
+                    postgres::ItemRepository::new(db_pool.clone()),
+                    postgres::BallotRepository::new(db_pool.clone()),
+                    postgres::RankingRepository::new(db_pool.clone()),
+                    shutdown_signal(),
+                )?;
+                (server, Some(DatabasePool::Postgres(db_pool)))
+            }
+            DatabaseKind::Sqlite => {
+                let db_pool = sqlite_pool(configuration.database())?;
+                if configuration.metrics().enabled() {
+                    metrics().register_pool(db_pool.clone());
+                }
+                let server = serve(
+                    listener,
+                    configuration,
+                    sqlite::PollRepository::new(db_pool.clone()),
+                    sqlite::ItemRepository::new(db_pool.clone()),
+                    sqlite::BallotRepository::new(db_pool.clone()),
+                    sqlite::RankingRepository::new(db_pool.clone()),
+                    shutdown_signal(),
+                )?;
+                (server, Some(DatabasePool::Sqlite(db_pool)))
+            }
+            DatabaseKind::Memory => {
+                let store = memory::Store::default();
+                let server = serve(
+                    listener,
+                    configuration,
+                    memory::poll::PollRepository::new(store.clone()),
+                    memory::item::ItemRepository::new(store.clone()),
+                    memory::ballot::BallotRepository::new(store.clone()),
+                    memory::ranking::RankingRepository::new(store),
+                    shutdown_signal(),
+                )?;
+                (server, None)
+            }
+        };
+        Ok(Application {
+            port,
+            server,
+            db_pool,
+        })
+    }
 
-        let server = route::serve(configuration, item_service, ballot_service, ranking_service)?;
-        Ok(Application { server })
+    /// The port that the application listens on, which is chosen by the system when the
+    /// configured port is 0.
+    pub fn port(&self) -> u16 {
+        self.port
     }
 
+    /// Serve until the server is stopped. On `SIGTERM` or `SIGINT` it stops accepting connections,
+    /// ends the streams of events, and waits for the requests being handled up to the configured
+    /// shutdown timeout, then the connections of the database are closed.
     pub async fn run(self) -> Result<(), std::io::Error> {
-        self.server.await
+        let result = self.server.await;
+        if let Some(db_pool) = self.db_pool {
+            tracing::info!("Closing the database pool");
+            db_pool.close().await;
+        }
+        result
+    }
+}
+
+/// The pool of the configured database, kept so that it can be closed on shutdown.
+enum DatabasePool {
+    Mysql(sqlx::Pool<sqlx::MySql>),
+    Postgres(sqlx::Pool<sqlx::Postgres>),
+    Sqlite(sqlx::Pool<sqlx::Sqlite>),
+}
+
+impl DatabasePool {
+    async fn close(&self) {
+        match self {
+            Self::Mysql(pool) => pool.close().await,
+            Self::Postgres(pool) => pool.close().await,
+            Self::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
+
+/// Build the services on top of the repositories of the configured database and start serving.
+pub(crate) fn serve<P, I, B, R>(
+    listener: TcpListener,
+    configuration: &Configuration,
+    poll_repository: P,
+    item_repository: I,
+    ballot_repository: B,
+    ranking_repository: R,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<Server, std::io::Error>
+where
+    P: 'static + PollRepository,
+    I: 'static + ItemRepository,
+    B: 'static + BallotRepository,
+    R: 'static + TransactableRankingRepository,
+{
+    let tally_cache = TallyCache::default();
+    let poll_service = PollService::new(poll_repository.clone());
+    let item_service = ItemService::new(
+        poll_repository.clone(),
+        item_repository.clone(),
+        tally_cache.clone(),
+    );
+    let registration = configuration.registration();
+    if registration.mode() == RegistrationMode::InviteCode && registration.invite_code().is_none() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "An invite code must be configured for registering with invite codes",
+        ));
     }
+    let ballot_service = BallotService::new(
+        ballot_repository,
+        registration.mode(),
+        registration.invite_code().cloned(),
+    );
+    let ranking_service = RankingService::new(
+        ranking_repository,
+        item_repository,
+        poll_repository,
+        tally_cache,
+        configuration.voting().method(),
+        configuration.voting().tie_break(),
+        configuration.voting().seed().unwrap_or_else(random_seed),
+    );
+    route::serve(
+        listener,
+        configuration,
+        poll_service,
+        item_service,
+        ballot_service,
+        ranking_service,
+        shutdown,
+    )
 }
 
 #[derive(Parser)]
@@ -56,6 +235,15 @@ pub struct Cli {
 
     #[arg(short, long = "migrate")]
     migrate: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Tally ballots from a CSV, JSON or BLT file and print the result
+    Tally(TallyArgs),
 }
 
 impl Cli {
@@ -66,22 +254,103 @@ impl Cli {
     pub fn migrate(&self) -> &Option<PathBuf> {
         &self.migrate
     }
+
+    pub fn command(&self) -> &Option<Command> {
+        &self.command
+    }
+}
+
+/// Wait for `SIGTERM` or `SIGINT`.
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "Failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+/// Get a seed from the randomly keyed hasher of the standard library.
+fn random_seed() -> u64 {
+    let seed = RandomState::new().build_hasher().finish();
+    tracing::info!(%seed, "Using random seed for breaking ties");
+    seed
+}
+
+fn mysql_pool(
+    configuration: &DatabaseConfiguration,
+) -> Result<sqlx::Pool<sqlx::MySql>, sqlx::Error> {
+    let options = match configuration.url() {
+        Some(url) => MySqlConnectOptions::from_url(&url.parse().map_err(sqlx::Error::config)?)?,
+        None => MySqlConnectOptions::new()
+            .ssl_mode(if configuration.require_ssl() {
+                MySqlSslMode::Required
+            } else {
+                MySqlSslMode::Preferred
+            })
+            .host(configuration.host())
+            .port(configuration.port())
+            .username(configuration.username())
+            .password(configuration.password())
+            .database(configuration.database()),
+    };
+    Ok(MySqlPoolOptions::new()
+        .acquire_timeout(std::time::Duration::from_secs(2))
+        .connect_lazy_with(options))
 }
 
-fn db_pool(configuration: &DatabaseConfiguration) -> sqlx::Pool<sqlx::MySql> {
-    MySqlPoolOptions::new()
+fn postgres_pool(
+    configuration: &DatabaseConfiguration,
+) -> Result<sqlx::Pool<sqlx::Postgres>, sqlx::Error> {
+    let options = match configuration.url() {
+        Some(url) => PgConnectOptions::from_url(&url.parse().map_err(sqlx::Error::config)?)?,
+        None => PgConnectOptions::new()
+            .ssl_mode(if configuration.require_ssl() {
+                PgSslMode::Require
+            } else {
+                PgSslMode::Prefer
+            })
+            .host(configuration.host())
+            .port(configuration.port())
+            .username(configuration.username())
+            .password(configuration.password())
+            .database(configuration.database()),
+    };
+    Ok(PgPoolOptions::new()
         .acquire_timeout(std::time::Duration::from_secs(2))
-        .connect_lazy_with(
-            MySqlConnectOptions::new()
-                .ssl_mode(if configuration.require_ssl() {
-                    MySqlSslMode::Required
-                } else {
-                    MySqlSslMode::Preferred
-                })
-                .host(configuration.host())
-                .port(configuration.port())
-                .username(configuration.username())
-                .password(configuration.password())
-                .database(configuration.database()),
-        )
+        .connect_lazy_with(options))
 }
+
+fn sqlite_pool(
+    configuration: &DatabaseConfiguration,
+) -> Result<sqlx::Pool<sqlx::Sqlite>, sqlx::Error> {
+    let options = match configuration.url() {
+        Some(url) => url.parse::<SqliteConnectOptions>()?,
+        None => SqliteConnectOptions::new().filename(configuration.database()),
+    };
+    Ok(SqlitePoolOptions::new()
+        .acquire_timeout(std::time::Duration::from_secs(2))
+        .connect_lazy_with(options.create_if_missing(true)))
+}
+
+mod tally;
+#[cfg(test)]
+mod tests;
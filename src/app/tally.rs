@@ -0,0 +1,667 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    irv::instant_runoff_trace,
+    model::{ElectionTrace, Item, TallyResult, TallyTrace},
+    stv::single_transferable_vote_trace,
+    view::display_votes,
+    voting::{
+        tie_break::{TieBreak, TieBreaker},
+        Method, VotingMethod,
+    },
+};
+
+use super::random_seed;
+
+/// Tally ballots that were exported to a file, without a database or a running server.
+#[derive(Args, Debug)]
+pub struct TallyArgs {
+    /// File containing the ballots
+    file: PathBuf,
+
+    /// Format of the file, which is guessed from its extension by default
+    #[arg(short, long, value_enum)]
+    format: Option<BallotFormat>,
+
+    /// Voting method for electing a single item
+    #[arg(short, long, value_enum, default_value = "instant_runoff")]
+    method: Method,
+
+    /// Number of items to elect, several seats are filled with the single transferable vote.
+    /// Defaults to the seats of a BLT file, or 1
+    #[arg(short, long)]
+    seats: Option<usize>,
+
+    /// Rule for breaking ties
+    #[arg(short, long, value_enum, default_value = "backward")]
+    tie_break: TieBreak,
+
+    /// Seed of the random draws when ties can not be broken otherwise, which is chosen randomly
+    /// by default
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// How the result is printed
+    #[arg(short, long, value_enum, default_value = "text")]
+    output: OutputFormat,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum BallotFormat {
+    /// One ballot per line, each column is a rank and items sharing a rank are joined by `=`
+    Csv,
+    /// A list of ballots, each rank is an item or a list of items sharing the rank
+    Json,
+    /// The ballot format of OpenSTV and other tally programs
+    Blt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BallotFileError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid BLT on line {line}: {message}")]
+    Blt { line: usize, message: String },
+
+    #[error("Ballot {ballot} ranks {item} more than once")]
+    DuplicateItem { ballot: usize, item: String },
+
+    #[error("Format of {0} is unknown, it can be given with --format")]
+    UnknownFormat(PathBuf),
+}
+
+/// Weighted ballots of a BLT file are tallied one by one, so their total weight is bounded to keep
+/// a small file from taking all the memory.
+const MAX_BLT_BALLOTS: usize = 1_000_000;
+
+/// Ballots read from a file, every item is given by its index in `items`.
+#[derive(Debug, Default, PartialEq)]
+struct BallotFile {
+    items: Vec<String>,
+    ballots: Vec<Vec<Vec<usize>>>,
+    /// Number of seats given by the file.
+    seats: Option<usize>,
+}
+
+impl BallotFile {
+    fn read(path: &Path, format: Option<BallotFormat>) -> Result<Self, BallotFileError> {
+        let format = match format {
+            Some(v) => v,
+            None => path
+                .extension()
+                .and_then(|ext| BallotFormat::from_str(&ext.to_string_lossy(), true).ok())
+                .ok_or_else(|| BallotFileError::UnknownFormat(path.to_path_buf()))?,
+        };
+        let content = fs::read_to_string(path)?;
+        let file = match format {
+            BallotFormat::Csv => Self::parse_csv(&content)?,
+            BallotFormat::Json => Self::parse_json(&content)?,
+            BallotFormat::Blt => Self::parse_blt(&content)?,
+        };
+        file.check_duplicates()?;
+        Ok(file)
+    }
+
+    /// The index of an item given by its name, items are added in the order they appear.
+    fn item_index(&mut self, indices: &mut HashMap<String, usize>, name: &str) -> usize {
+        *indices.entry(name.to_string()).or_insert_with(|| {
+            self.items.push(name.to_string());
+            self.items.len() - 1
+        })
+    }
+
+    fn parse_csv(content: &str) -> Result<Self, BallotFileError> {
+        let mut file = Self::default();
+        let mut indices = HashMap::new();
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .comment(Some(b'#'))
+            .from_reader(content.as_bytes());
+        for record in reader.records() {
+            let mut ballot = Vec::new();
+            for field in record?.iter() {
+                let rank: Vec<usize> = field
+                    .split('=')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(|name| file.item_index(&mut indices, name))
+                    .collect();
+                if !rank.is_empty() {
+                    ballot.push(rank);
+                }
+            }
+            file.ballots.push(ballot);
+        }
+        Ok(file)
+    }
+
+    fn parse_json(content: &str) -> Result<Self, BallotFileError> {
+        /// Same as the ranks of the JSON API, with items given by their names.
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RankData {
+            Item(String),
+            Tied(Vec<String>),
+        }
+
+        let ballots: Vec<Vec<RankData>> = serde_json::from_str(content)?;
+        let mut file = Self::default();
+        let mut indices = HashMap::new();
+        for ballot in ballots {
+            let ballot = ballot
+                .into_iter()
+                .map(|rank| match rank {
+                    RankData::Item(name) => vec![name],
+                    RankData::Tied(names) => names,
+                })
+                .filter(|names| !names.is_empty())
+                .map(|names| {
+                    names
+                        .iter()
+                        .map(|name| file.item_index(&mut indices, name))
+                        .collect()
+                })
+                .collect();
+            file.ballots.push(ballot);
+        }
+        Ok(file)
+    }
+
+    /// Read a BLT file: the number of items and seats, the withdrawn items as negative numbers,
+    /// then the ballots each starting with its weight and ending with 0, a 0 after the last
+    /// ballot, the names of the items and the title of the election. Items sharing a rank are
+    /// joined by `=`.
+    fn parse_blt(content: &str) -> Result<Self, BallotFileError> {
+        let mut tokens = blt_tokens(content).into_iter();
+        let mut last_line = 1;
+        let mut next = |expected: &str| -> Result<(usize, String), BallotFileError> {
+            match tokens.next() {
+                Some((line, token)) => {
+                    last_line = line;
+                    Ok((line, token))
+                }
+                None => Err(BallotFileError::Blt {
+                    line: last_line,
+                    message: format!("Expected {expected} but the file ended"),
+                }),
+            }
+        };
+        let number = |(line, token): (usize, String)| -> Result<usize, BallotFileError> {
+            token.parse().map_err(|_| BallotFileError::Blt {
+                line,
+                message: format!("Expected a number but found {token}"),
+            })
+        };
+
+        let item_count = number(next("the number of items")?)?;
+        let seats = number(next("the number of seats")?)?;
+        let item = |(line, token): (usize, String)| -> Result<usize, BallotFileError> {
+            match token.parse::<usize>() {
+                Ok(n) if (1..=item_count).contains(&n) => Ok(n - 1),
+                _ => Err(BallotFileError::Blt {
+                    line,
+                    message: format!(
+                        "Expected an item between 1 and {item_count} but found {token}"
+                    ),
+                }),
+            }
+        };
+
+        let mut withdrawn = Vec::new();
+        let mut ballots = Vec::new();
+        let mut token = next("a ballot")?;
+        while let Some(n) = token.1.strip_prefix('-') {
+            withdrawn.push(item((token.0, n.to_string()))?);
+            token = next("a ballot")?;
+        }
+        while token.1 != "0" {
+            // Some programs give every ballot an id in parentheses.
+            if token.1.starts_with('(') {
+                token = next("the weight of a ballot")?;
+            }
+            let weight_line = token.0;
+            let weight = number(token)?;
+            if weight > MAX_BLT_BALLOTS - ballots.len() {
+                return Err(BallotFileError::Blt {
+                    line: weight_line,
+                    message: format!("Ballots weigh more than {MAX_BLT_BALLOTS} in total"),
+                });
+            }
+            let mut ballot = Vec::new();
+            loop {
+                let (line, prefs) = next("the end of a ballot")?;
+                if prefs == "0" {
+                    break;
+                }
+                let rank = prefs
+                    .split('=')
+                    .map(|n| item((line, n.to_string())))
+                    .filter(|n| !matches!(n, Ok(n) if withdrawn.contains(n)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if !rank.is_empty() {
+                    ballot.push(rank);
+                }
+            }
+            ballots.extend(std::iter::repeat_n(ballot, weight));
+            token = next("a ballot")?;
+        }
+
+        let items = (0..item_count)
+            .map(|_| next("the name of an item").map(|(_, name)| name))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            items,
+            ballots,
+            seats: Some(seats),
+        })
+    }
+
+    fn check_duplicates(&self) -> Result<(), BallotFileError> {
+        for (n, ballot) in self.ballots.iter().enumerate() {
+            let mut seen = vec![false; self.items.len()];
+            for &index in ballot.iter().flatten() {
+                if std::mem::replace(&mut seen[index], true) {
+                    return Err(BallotFileError::DuplicateItem {
+                        ballot: n + 1,
+                        item: self.items[index].clone(),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Split a BLT file into its tokens along with their line, quoted names are kept whole and
+/// comments starting with `#` are skipped.
+fn blt_tokens(content: &str) -> Vec<(usize, String)> {
+    let mut tokens = Vec::new();
+    for (n, line) in content.lines().enumerate() {
+        let mut chars = line.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+            } else if c == '#' {
+                break;
+            } else if c == '"' {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|&c| c != '"').collect();
+                tokens.push((n + 1, name));
+            } else {
+                let mut token = String::new();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '#') {
+                    token.push(c);
+                }
+                tokens.push((n + 1, token));
+            }
+        }
+    }
+    tokens
+}
+
+/// What is printed after tallying the ballots, which holds the same traces as the final tally
+/// of a poll.
+#[derive(Serialize)]
+struct TallyOutput {
+    /// Name of the voting method in configuration.
+    method: &'static str,
+    ballots: usize,
+    seats: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<TallyResult>,
+    /// Every round of instant-runoff voting.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    trace: Option<TallyTrace>,
+    /// Every round of the single transferable vote for several seats.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    election: Option<ElectionTrace>,
+    tie_break: TieBreak,
+    seed: u64,
+}
+
+impl TallyOutput {
+    fn new(file: &BallotFile, args: &TallyArgs) -> Self {
+        let items: Vec<Item> = file
+            .items
+            .iter()
+            .enumerate()
+            .map(|(n, name)| Item {
+                id: n as i32 + 1,
+                title: name.clone(),
+                content: String::new(),
+                done: false,
+                position: n as i32,
+                retired: false,
+            })
+            .collect();
+        let ballots: Vec<Vec<Vec<&Item>>> = file
+            .ballots
+            .iter()
+            .map(|ballot| {
+                ballot
+                    .iter()
+                    .map(|rank| rank.iter().map(|&index| &items[index]).collect())
+                    .collect()
+            })
+            .collect();
+        let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+        let seats = args.seats.or(file.seats).unwrap_or(1);
+        let seed = args.seed.unwrap_or_else(random_seed);
+        let mut tie_breaker = TieBreaker::new(args.tie_break, seed);
+
+        let mut output = Self {
+            method: args.method.as_str(),
+            ballots: ballots.len(),
+            seats,
+            result: None,
+            trace: None,
+            election: None,
+            tie_break: args.tie_break,
+            seed,
+        };
+        if seats > 1 {
+            output.method = "single_transferable_vote";
+            let trace = single_transferable_vote_trace(&ballots, seats, &mut tie_breaker);
+            output.election = Some(ElectionTrace::new(trace, &tie_breaker));
+        } else if args.method == Method::InstantRunoff {
+            let trace = TallyTrace::new(
+                instant_runoff_trace(&ballots, &mut tie_breaker),
+                &tie_breaker,
+            );
+            output.result = Some(trace.result.clone());
+            output.trace = Some(trace);
+        } else {
+            output.result = Some(args.method.tally(&ballots, &mut tie_breaker).into());
+        }
+        output
+    }
+
+    fn write_text(&self, out: &mut impl Write, method: Method) -> io::Result<()> {
+        let titles = |items: &[Item]| -> String {
+            items
+                .iter()
+                .map(|it| it.title.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match &self.election {
+            Some(election) => writeln!(
+                out,
+                "Single transferable vote for {} seats, {} ballots, quota of {} ballots",
+                election.seats,
+                self.ballots,
+                display_votes(election.quota)
+            )?,
+            None => writeln!(out, "{method}, {} ballots", self.ballots)?,
+        }
+        match self.tie_break {
+            TieBreak::None => writeln!(out, "Ties are not broken")?,
+            tie_break => writeln!(
+                out,
+                "Ties are decided by {tie_break} with seed {}",
+                self.seed
+            )?,
+        }
+
+        if let Some(trace) = &self.trace {
+            for (n, round) in trace.rounds.iter().enumerate() {
+                writeln!(out, "\nRound {}", n + 1)?;
+                for (it, count) in round.counts.iter() {
+                    writeln!(out, "  {}: {}", it.title, display_votes(*count))?;
+                }
+                writeln!(
+                    out,
+                    "  Exhausted ballots: {}",
+                    display_votes(round.exhausted)
+                )?;
+                if !round.eliminated.is_empty() {
+                    write!(out, "  Eliminated: {}", titles(&round.eliminated))?;
+                    match round.tie_break {
+                        Some(tie_break) => writeln!(out, " (tie decided by {tie_break})")?,
+                        None => writeln!(out)?,
+                    }
+                }
+                for transfer in round.transfers.iter() {
+                    write!(
+                        out,
+                        "    {} ballot(s) from {} ",
+                        display_votes(transfer.ballots),
+                        transfer.from.title
+                    )?;
+                    match &transfer.to {
+                        Some(to) => writeln!(out, "transferred to {}", to.title)?,
+                        None => writeln!(out, "became exhausted")?,
+                    }
+                }
+            }
+        }
+        if let Some(election) = &self.election {
+            for (n, round) in election.rounds.iter().enumerate() {
+                writeln!(out, "\nRound {}", n + 1)?;
+                for (it, votes) in round.counts.iter() {
+                    writeln!(out, "  {}: {}", it.title, display_votes(*votes))?;
+                }
+                writeln!(
+                    out,
+                    "  Exhausted ballots: {}",
+                    display_votes(round.exhausted)
+                )?;
+                if !round.elected.is_empty() {
+                    writeln!(out, "  Elected: {}", titles(&round.elected))?;
+                }
+                if !round.eliminated.is_empty() {
+                    write!(out, "  Eliminated: {}", titles(&round.eliminated))?;
+                    match round.tie_break {
+                        Some(tie_break) => writeln!(out, " (tie decided by {tie_break})")?,
+                        None => writeln!(out)?,
+                    }
+                }
+                for transfer in round.transfers.iter() {
+                    write!(
+                        out,
+                        "    {} vote(s) from {} ",
+                        display_votes(transfer.votes),
+                        transfer.from.title
+                    )?;
+                    match &transfer.to {
+                        Some(to) => writeln!(out, "transferred to {}", to.title)?,
+                        None => writeln!(out, "became exhausted")?,
+                    }
+                }
+            }
+            writeln!(out)?;
+            writeln!(out, "Elected: {}", titles(&election.elected))?;
+            if !election.tied.is_empty() {
+                writeln!(
+                    out,
+                    "Tied for the remaining seats: {}",
+                    titles(&election.tied)
+                )?;
+            }
+        }
+
+        match &self.result {
+            Some(TallyResult::Winner(it)) => writeln!(out, "\nWinner: {}", it.title)?,
+            Some(TallyResult::Tied(items)) => writeln!(out, "\nTied between: {}", titles(items))?,
+            Some(TallyResult::NoWinner) => writeln!(out, "\nNo winner")?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Tally the ballots of a file and print the result along with every round of the tally.
+pub fn tally(args: &TallyArgs) -> Result<(), anyhow::Error> {
+    let file = BallotFile::read(&args.file, args.format)?;
+    let output = TallyOutput::new(&file, args);
+    let mut stdout = io::stdout().lock();
+    match args.output {
+        OutputFormat::Text => output.write_text(&mut stdout, args.method)?,
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut stdout, &output)?;
+            writeln!(stdout)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: TallyArgs,
+    }
+
+    fn args(argv: &[&str]) -> TallyArgs {
+        Cli::parse_from(["tally", "ballots"].iter().chain(argv)).args
+    }
+
+    fn names(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn csv_ballots_share_ranks_with_equals() {
+        let file = BallotFile::parse_csv("Alice, Bob = Carol\n# comment\nCarol,,Alice\n\"Bob\"\n")
+            .unwrap();
+        assert_eq!(file.items, names(&["Alice", "Bob", "Carol"]));
+        assert_eq!(
+            file.ballots,
+            vec![
+                vec![vec![0], vec![1, 2]],
+                vec![vec![2], vec![0]],
+                vec![vec![1]],
+            ]
+        );
+        assert_eq!(file.seats, None);
+    }
+
+    #[test]
+    fn json_ballots_are_lists_of_ranks() {
+        let file = BallotFile::parse_json(r#"[["Alice", ["Bob", "Carol"]], [["Carol"], []], []]"#)
+            .unwrap();
+        assert_eq!(file.items, names(&["Alice", "Bob", "Carol"]));
+        assert_eq!(
+            file.ballots,
+            vec![vec![vec![0], vec![1, 2]], vec![vec![2]], vec![]]
+        );
+    }
+
+    #[test]
+    fn blt_ballots_are_weighted_without_withdrawn_items() {
+        let content = "4 2\n\
+                       -4\n\
+                       3 1 2 0\n\
+                       (b2) 1 3=4 1 0 # comment\n\
+                       1 4 0\n\
+                       0\n\
+                       \"Alice\" \"Bob\"\n\
+                       \"Carol Jones\"\n\
+                       \"Dan\"\n\
+                       \"Election\"\n";
+        let file = BallotFile::parse_blt(content).unwrap();
+        assert_eq!(file.items, names(&["Alice", "Bob", "Carol Jones", "Dan"]));
+        assert_eq!(
+            file.ballots,
+            vec![
+                vec![vec![0], vec![1]],
+                vec![vec![0], vec![1]],
+                vec![vec![0], vec![1]],
+                vec![vec![2], vec![0]],
+                vec![],
+            ]
+        );
+        assert_eq!(file.seats, Some(2));
+    }
+
+    #[test]
+    fn blt_errors_give_the_line() {
+        let err = BallotFile::parse_blt("2 1\n1 1 3 0\n0\n\"A\" \"B\"\n").unwrap_err();
+        assert!(matches!(err, BallotFileError::Blt { line: 2, .. }), "{err}");
+        let err = BallotFile::parse_blt("2 1\n1 1 2 0\n").unwrap_err();
+        assert!(matches!(err, BallotFileError::Blt { line: 2, .. }), "{err}");
+    }
+
+    #[test]
+    fn blt_weights_are_bounded() {
+        let content = format!("2 1\n1 1 0\n{MAX_BLT_BALLOTS} 2 0\n0\n\"A\" \"B\"\n");
+        let err = BallotFile::parse_blt(&content).unwrap_err();
+        assert!(matches!(err, BallotFileError::Blt { line: 3, .. }), "{err}");
+        let err =
+            BallotFile::parse_blt("2 1\n18446744073709551615 1 0\n0\n\"A\" \"B\"\n").unwrap_err();
+        assert!(matches!(err, BallotFileError::Blt { line: 2, .. }), "{err}");
+    }
+
+    #[test]
+    fn ballots_can_not_rank_an_item_twice() {
+        let file = BallotFile::parse_csv("Alice,Bob\nBob,Alice=Bob\n").unwrap();
+        let err = file.check_duplicates().unwrap_err();
+        assert!(matches!(
+            err,
+            BallotFileError::DuplicateItem { ballot: 2, ref item } if item == "Bob"
+        ));
+    }
+
+    #[test]
+    fn tally_runs_the_chosen_method() {
+        // Carol is eliminated first and her ballots elect Bob, who is the Condorcet winner
+        let file = BallotFile::parse_csv(
+            "Alice,Bob\nAlice,Bob\nAlice,Bob\nBob,Alice\nBob,Alice\nBob,Alice\nCarol,Bob\n\
+             Carol,Bob\n",
+        )
+        .unwrap();
+
+        let output = TallyOutput::new(&file, &args(&["--seed", "1"]));
+        assert_eq!(output.method, "instant_runoff");
+        assert_eq!(output.ballots, 8);
+        assert_eq!(output.trace.as_ref().unwrap().rounds.len(), 2);
+        assert!(matches!(output.result, Some(TallyResult::Winner(ref it)) if it.title == "Bob"));
+        let mut text = Vec::new();
+        output.write_text(&mut text, Method::InstantRunoff).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("Eliminated: Carol"), "{text}");
+        assert!(text.ends_with("Winner: Bob\n"), "{text}");
+
+        let output = TallyOutput::new(&file, &args(&["-m", "schulze"]));
+        assert!(output.trace.is_none());
+        assert!(matches!(output.result, Some(TallyResult::Winner(ref it)) if it.title == "Bob"));
+
+        let output = TallyOutput::new(&file, &args(&["--seats", "2", "-o", "json"]));
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["method"], "single_transferable_vote");
+        assert!(json.get("result").is_none());
+        let elected = output.election.unwrap().elected;
+        // Both reach the quota in the first round
+        let mut elected: Vec<_> = elected.iter().map(|it| it.title.as_str()).collect();
+        elected.sort();
+        assert_eq!(elected, ["Alice", "Bob"]);
+    }
+}
@@ -0,0 +1,874 @@
+use std::{future::Future, net::TcpListener, time::Duration};
+
+use hyper::{body, client::HttpConnector, header, Body, Client, Method, Request, StatusCode};
+use serde_json::{json, Value};
+
+use crate::{
+    conf::ConfigurationBuilder,
+    model::NewItem,
+    repository::{memory, ItemRepository},
+};
+
+use super::serve;
+
+const ITEMS: [&str; 3] = ["Ada Lovelace", "Alan Turing", "Grace Hopper"];
+
+/// An application that is served on a random port and stores its data in memory.
+struct TestApp {
+    address: String,
+    client: Client<HttpConnector>,
+}
+
+/// Start the application with a poll whose items are `ITEMS`, in that order with ids starting
+/// from 1.
+async fn spawn_app() -> TestApp {
+    spawn_app_until(std::future::pending()).await
+}
+
+/// Start the application like `spawn_app`, it shuts down when the future completes.
+async fn spawn_app_until(shutdown: impl Future<Output = ()> + Send + 'static) -> TestApp {
+    let configuration = ConfigurationBuilder::default()
+        .build()
+        .expect("Failed to read configuration");
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
+    let port = listener.local_addr().unwrap().port();
+
+    let store = memory::Store::default();
+    let item_repository = memory::item::ItemRepository::new(store.clone());
+    for title in ITEMS {
+        item_repository
+            .create(NewItem {
+                poll_id: 1,
+                title: title.to_string(),
+                content: String::new(),
+            })
+            .await
+            .expect("Failed to create item");
+    }
+
+    let server = serve(
+        listener,
+        &configuration,
+        memory::poll::PollRepository::new(store.clone()),
+        item_repository,
+        memory::ballot::BallotRepository::new(store.clone()),
+        memory::ranking::RankingRepository::new(store),
+        shutdown,
+    )
+    .expect("Failed to start server");
+    tokio::spawn(server);
+
+    TestApp {
+        address: format!("http://127.0.0.1:{port}"),
+        client: Client::new(),
+    }
+}
+
+struct TestResponse {
+    status: StatusCode,
+    headers: header::HeaderMap,
+    body: String,
+}
+
+impl TestResponse {
+    fn json(&self) -> Value {
+        serde_json::from_str(&self.body).expect("Response is not JSON")
+    }
+
+    /// The cookies that were set, in a form that can be sent back. Removed cookies are left out.
+    fn cookies(&self) -> String {
+        self.headers
+            .get_all(header::SET_COOKIE)
+            .iter()
+            .filter_map(|v| v.to_str().ok()?.split(';').next())
+            .filter(|c| !c.ends_with('='))
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+}
+
+impl TestApp {
+    async fn send(
+        &self,
+        method: Method,
+        path: &str,
+        headers: &[(header::HeaderName, &str)],
+        body: String,
+    ) -> TestResponse {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(format!("{}{}", self.address, path));
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        let response = self
+            .client
+            .request(request.body(Body::from(body)).unwrap())
+            .await
+            .expect("Failed to send request");
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = body::to_bytes(response.into_body()).await.unwrap();
+        TestResponse {
+            status,
+            headers,
+            body: String::from_utf8(body.to_vec()).unwrap(),
+        }
+    }
+
+    async fn register(&self, poll_id: i32, uuid: &str) -> TestResponse {
+        self.send(
+            Method::POST,
+            "/register",
+            &[(header::CONTENT_TYPE, "application/x-www-form-urlencoded")],
+            format!("poll_id={poll_id}&uuid={uuid}"),
+        )
+        .await
+    }
+
+    async fn put_rankings(&self, poll_id: i32, uuid: &str, items: Value) -> TestResponse {
+        let authorization = format!("Bearer {uuid}");
+        self.send(
+            Method::PUT,
+            &format!("/api/v1/polls/{poll_id}/ballot"),
+            &[
+                (header::AUTHORIZATION, &authorization),
+                (header::CONTENT_TYPE, "application/json"),
+            ],
+            json!({ "items": items }).to_string(),
+        )
+        .await
+    }
+
+    async fn get(&self, path: &str) -> TestResponse {
+        self.send(Method::GET, path, &[], String::new()).await
+    }
+
+    /// Log in as an administrator and return the cookies of the session.
+    async fn login_admin(&self) -> String {
+        let response = self
+            .send(
+                Method::POST,
+                "/admin/login",
+                &[(header::CONTENT_TYPE, "application/x-www-form-urlencoded")],
+                "password=admin".to_string(),
+            )
+            .await;
+        assert_eq!(response.headers[header::LOCATION], "/admin");
+        response.cookies()
+    }
+
+    async fn post_admin_form(&self, cookies: &str, path: &str, form: &str) -> TestResponse {
+        self.send(
+            Method::POST,
+            path,
+            &[
+                (header::COOKIE, cookies),
+                (header::CONTENT_TYPE, "application/x-www-form-urlencoded"),
+            ],
+            form.to_string(),
+        )
+        .await
+    }
+}
+
+fn uuid() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+fn titles(items: &Value) -> Vec<&str> {
+    items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["title"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn register_rank_and_get_result() {
+    let app = spawn_app().await;
+    let voters: Vec<_> = (0..5).map(|_| uuid()).collect();
+
+    for voter in &voters {
+        let response = app.register(1, voter).await;
+        assert_eq!(response.status, StatusCode::SEE_OTHER);
+        assert_eq!(response.headers[header::LOCATION], "/polls/1/ballot");
+    }
+    app.put_rankings(1, &voters[0], json!([1])).await;
+    app.put_rankings(1, &voters[1], json!([1, 2])).await;
+    app.put_rankings(1, &voters[2], json!([2])).await;
+    app.put_rankings(1, &voters[3], json!([2, 3])).await;
+    let response = app.put_rankings(1, &voters[4], json!([3, 1])).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let ranks = &response.json()["ranks"];
+    assert_eq!(titles(&ranks[0]), ["Grace Hopper"]);
+    assert_eq!(titles(&ranks[1]), ["Ada Lovelace"]);
+    assert_eq!(titles(&response.json()["unranked"]), ["Alan Turing"]);
+
+    // Grace Hopper is eliminated first and her ballot goes to Ada Lovelace
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(result["status"], "winner");
+    assert_eq!(titles(&result["items"]), ["Ada Lovelace"]);
+}
+
+#[tokio::test]
+async fn equal_rankings_split_the_ballot() {
+    let app = spawn_app().await;
+    let voters: Vec<_> = (0..3).map(|_| uuid()).collect();
+    for voter in &voters {
+        app.register(1, voter).await;
+    }
+    let response = app.put_rankings(1, &voters[0], json!([[1, 2], 3])).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let ranks = &response.json()["ranks"];
+    assert_eq!(titles(&ranks[0]), ["Ada Lovelace", "Alan Turing"]);
+    assert_eq!(titles(&ranks[1]), ["Grace Hopper"]);
+    app.put_rankings(1, &voters[1], json!([3])).await;
+    app.put_rankings(1, &voters[2], json!([[1], 2])).await;
+
+    // Ada Lovelace gets one and a half ballots, Alan Turing only half of one
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(titles(&result["items"]), ["Ada Lovelace"]);
+}
+
+#[tokio::test]
+async fn malformed_rankings_are_rejected() {
+    let app = spawn_app().await;
+    let cookies = app.login_admin().await;
+    app.post_admin_form(&cookies, "/admin/polls/1/items/3/done", "value=true")
+        .await;
+    let voter = uuid();
+    app.register(1, &voter).await;
+    app.put_rankings(1, &voter, json!([1])).await;
+
+    let response = app.put_rankings(1, &voter, json!([[2, 1], 3, 2, 42])).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response.json();
+    assert_eq!(body["code"], "invalid_ballot");
+    let problems: Vec<_> = body["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| (p["code"].as_str().unwrap(), p["item_id"].as_i64().unwrap()))
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            ("inactive_item", 3),
+            ("duplicate_item", 2),
+            ("unknown_item", 42)
+        ]
+    );
+
+    // Ids that are not numbers are reported the same way
+    let response = app.put_rankings(1, &voter, json!(["abc", [2, 1.5]])).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    let body = response.json();
+    assert_eq!(body["code"], "invalid_ballot");
+    let problems: Vec<_> = body["problems"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| (p["code"].as_str().unwrap(), p["message"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            ("invalid_item_id", "\"abc\" is not an item id"),
+            ("invalid_item_id", "\"1.5\" is not an item id")
+        ]
+    );
+
+    // The ballot is left as it was
+    let authorization = format!("Bearer {voter}");
+    let ballot = app
+        .send(
+            Method::GET,
+            "/api/v1/polls/1/ballot",
+            &[(header::AUTHORIZATION, &authorization)],
+            String::new(),
+        )
+        .await;
+    assert_eq!(titles(&ballot.json()["ranks"][0]), ["Ada Lovelace"]);
+}
+
+#[tokio::test]
+async fn health_checks_report_dependencies() {
+    let app = spawn_app().await;
+
+    let response = app.get("/health/live").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.json()["status"], "up");
+
+    // The exporter of the local configuration is only up when a collector is running, which
+    // does not keep the application from serving requests
+    let response = app.get("/health/ready").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let body = response.json();
+    assert_eq!(body["status"], "up");
+    assert_eq!(body["checks"][0]["name"], "database");
+    assert_eq!(body["checks"][0]["status"], "up");
+    assert!(body["checks"][0]["latency_ms"].is_f64());
+    assert_eq!(body["checks"][1]["name"], "otlp_exporter");
+    assert!(body["checks"][1]["latency_ms"].is_f64());
+}
+
+#[tokio::test]
+async fn metrics_count_requests_ballots_and_tallies() {
+    let app = spawn_app().await;
+    let voter = uuid();
+    app.register(1, &voter).await;
+    app.put_rankings(1, &voter, json!([2, 1])).await;
+    app.get("/api/v1/polls/1/result").await;
+
+    // Metrics are shared by every test of the process, so only their presence is checked
+    let response = app.get("/metrics").await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.headers[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    for line in [
+        r#"poll_http_requests_total{method="GET",route="/api/v1/polls/{id}/result",status="200"}"#,
+        r#"poll_http_request_duration_seconds_count{method="GET",route="/api/v1/polls/{id}/result"}"#,
+        r#"poll_tally_duration_seconds_count{method="instant_runoff"}"#,
+        r#"poll_ballots_registered_total{poll_id="1"}"#,
+        r#"poll_ballot_updates_total{poll_id="1"}"#,
+    ] {
+        assert!(response.body.contains(line), "{line} is missing");
+    }
+}
+
+#[tokio::test]
+async fn result_without_ballots_has_no_winner() {
+    let app = spawn_app().await;
+
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(result["status"], "no_winner");
+    assert_eq!(result["items"], json!([]));
+}
+
+#[tokio::test]
+async fn replacing_rankings_changes_result() {
+    let app = spawn_app().await;
+    let voter = uuid();
+    app.register(1, &voter).await;
+
+    app.put_rankings(1, &voter, json!([1])).await;
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(titles(&result["items"]), ["Ada Lovelace"]);
+
+    app.put_rankings(1, &voter, json!([3, 1])).await;
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(titles(&result["items"]), ["Grace Hopper"]);
+}
+
+#[tokio::test]
+async fn ballot_page_updates_best_item() {
+    let app = spawn_app().await;
+    let response = app.register(1, &uuid()).await;
+    let cookies = response.cookies();
+
+    let response = app
+        .send(
+            Method::POST,
+            "/polls/1/ballot",
+            &[
+                (header::COOKIE, &cookies),
+                (header::CONTENT_TYPE, "application/json"),
+            ],
+            json!({ "items": ["2", "<DELIMITER>", "1", "3"] }).to_string(),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.contains("Alan Turing"));
+}
+
+#[tokio::test]
+async fn ballot_requires_registration() {
+    let app = spawn_app().await;
+
+    let response = app.get("/api/v1/polls/1/ballot").await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.json()["code"], "unauthorized");
+
+    let response = app.put_rankings(1, &uuid(), json!([1])).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["code"], "ballot_not_found");
+
+    let response = app.get("/api/v1/polls/2/result").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["code"], "poll_not_found");
+}
+
+#[tokio::test]
+async fn errors_carry_the_request_id() {
+    let app = spawn_app().await;
+
+    // Browsers get an error page
+    let response = app.get("/nowhere").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let request_id = response.headers["x-request-id"].to_str().unwrap();
+    assert!(response.headers[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(response.body.contains("Page not found"));
+    assert!(response.body.contains(request_id));
+
+    // Clients asking for JSON get the error as JSON
+    let response = app
+        .send(
+            Method::GET,
+            "/nowhere",
+            &[(header::ACCEPT, "application/json")],
+            String::new(),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.json()["code"], "not_found");
+    assert_eq!(
+        response.json()["request_id"],
+        response.headers["x-request-id"].to_str().unwrap()
+    );
+
+    let response = app.get("/api/v1/polls/1/ballot").await;
+    assert_eq!(
+        response.json()["request_id"],
+        response.headers["x-request-id"].to_str().unwrap()
+    );
+
+    // Successful responses carry it too
+    let response = app.get("/").await;
+    assert!(response.headers.contains_key("x-request-id"));
+}
+
+#[tokio::test]
+async fn audit_verifies_revisions_and_replays_result() {
+    let app = spawn_app().await;
+    let voter = uuid();
+    app.register(1, &voter).await;
+    app.put_rankings(1, &voter, json!([1])).await;
+    let before = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    app.put_rankings(1, &voter, json!([3, 1])).await;
+
+    let response = app.get("/admin/polls/1/%61udit").await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+    assert_eq!(response.headers[header::LOCATION], "/admin/login");
+
+    let cookies = app.login_admin().await;
+
+    let response = app
+        .send(
+            Method::GET,
+            &format!("/admin/polls/1/audit?at={}", before.replace('+', "%2B")),
+            &[(header::COOKIE, &cookies)],
+            String::new(),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let audit = response.json();
+    assert_eq!(audit["report"]["valid"], true);
+    assert_eq!(audit["report"]["revisions"], 2);
+    // The ballot only ranked Ada Lovelace at that time
+    assert_eq!(audit["result"]["status"], "winner");
+    assert_eq!(titles(&audit["result"]["items"]), ["Ada Lovelace"]);
+}
+
+#[tokio::test]
+async fn closed_poll_rejects_ballots_and_keeps_final_result() {
+    let app = spawn_app().await;
+    let voter = uuid();
+    app.register(1, &voter).await;
+    app.put_rankings(1, &voter, json!([2])).await;
+
+    let cookies = app.login_admin().await;
+    let response = app
+        .post_admin_form(
+            &cookies,
+            "/admin/polls/1/lifecycle",
+            "status=closed&opens_at=&closes_at=",
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+
+    let response = app.put_rankings(1, &voter, json!([3])).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(response.json()["code"], "poll_closed");
+
+    // Retiring the winner afterwards does not change the final result
+    app.post_admin_form(&cookies, "/admin/polls/1/items/2/retire", "value=true")
+        .await;
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(result["poll_status"], "closed");
+    assert_eq!(titles(&result["items"]), ["Alan Turing"]);
+
+    // A closed poll can not be opened again
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/1/lifecycle",
+        "status=open&opens_at=&closes_at=",
+    )
+    .await;
+    let response = app.put_rankings(1, &voter, json!([3])).await;
+    assert_eq!(response.json()["code"], "poll_closed");
+}
+
+#[tokio::test]
+async fn items_of_closed_poll_can_not_be_changed() {
+    let app = spawn_app().await;
+    let cookies = app.login_admin().await;
+    // The poll is closed by its schedule rather than by the administrator
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/1/lifecycle",
+        "status=open&opens_at=1999-01-01T00%3A00&closes_at=2000-01-01T00%3A00",
+    )
+    .await;
+    let items = app.get("/api/v1/polls/1/items").await.json();
+
+    app.post_admin_form(&cookies, "/admin/polls/1/items", "title=Linus&content=")
+        .await;
+    app.post_admin_form(&cookies, "/admin/polls/1/items/1", "title=Ada&content=")
+        .await;
+    app.post_admin_form(&cookies, "/admin/polls/1/items/2/done", "value=true")
+        .await;
+    app.post_admin_form(&cookies, "/admin/polls/1/items/3/retire", "value=true")
+        .await;
+    let response = app
+        .send(
+            Method::POST,
+            "/admin/polls/1/items/order",
+            &[
+                (header::COOKIE, &cookies),
+                (header::CONTENT_TYPE, "application/json"),
+            ],
+            json!({ "items": ["3", "2", "1"] }).to_string(),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+
+    assert_eq!(app.get("/api/v1/polls/1/items").await.json(), items);
+}
+
+#[tokio::test]
+async fn scheduled_times_are_entered_with_the_browser_offset() {
+    let app = spawn_app().await;
+    let cookies = app.login_admin().await;
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/1/lifecycle",
+        "status=draft&opens_at=2999-01-01T10%3A00&opens_at_offset=120\
+         &closes_at=2999-07-01T10%3A00&closes_at_offset=-330",
+    )
+    .await;
+    let response = app
+        .send(
+            Method::GET,
+            "/admin/polls/1/items",
+            &[(header::COOKIE, &cookies)],
+            String::new(),
+        )
+        .await;
+    assert!(response.body.contains("value=\"2999-01-01T08:00\""));
+    assert!(response.body.contains("value=\"2999-07-01T15:30\""));
+
+    let response = app.get("/polls/1").await;
+    assert!(response.body.contains("2999-01-01 08:00 UTC"));
+}
+
+#[tokio::test]
+async fn draft_poll_opens_at_scheduled_time() {
+    let app = spawn_app().await;
+    let voter = uuid();
+    app.register(1, &voter).await;
+
+    let cookies = app.login_admin().await;
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/1/lifecycle",
+        "status=draft&opens_at=2999-01-01T00%3A00&closes_at=",
+    )
+    .await;
+    let response = app.put_rankings(1, &voter, json!([1])).await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+    assert_eq!(response.json()["code"], "poll_not_open");
+
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/1/lifecycle",
+        "status=draft&opens_at=2000-01-01T00%3A00&closes_at=",
+    )
+    .await;
+    let response = app.put_rankings(1, &voter, json!([1])).await;
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn registration_is_rate_limited() {
+    let app = spawn_app().await;
+
+    // The default configuration allows 10 registrations per minute from an address
+    for _ in 0..10 {
+        let response = app.register(1, &uuid()).await;
+        assert_eq!(response.headers[header::LOCATION], "/polls/1/ballot");
+    }
+    let response = app.register(1, &uuid()).await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+    assert_eq!(response.headers[header::LOCATION], "/polls/1");
+}
+
+#[tokio::test]
+async fn admin_login_is_rate_limited() {
+    let app = spawn_app().await;
+    let login = |password: &'static str| {
+        app.send(
+            Method::POST,
+            "/admin/login",
+            &[(header::CONTENT_TYPE, "application/x-www-form-urlencoded")],
+            format!("password={password}"),
+        )
+    };
+
+    // The default configuration allows 10 login attempts per minute from an address
+    for _ in 0..9 {
+        let response = login("guess").await;
+        assert_eq!(response.headers[header::LOCATION], "/admin/login");
+    }
+    let response = login("admin").await;
+    assert_eq!(response.headers[header::LOCATION], "/admin");
+    let response = login("admin").await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+    assert_eq!(response.headers[header::LOCATION], "/admin/login");
+}
+
+#[tokio::test]
+async fn admin_creates_a_poll() {
+    let app = spawn_app().await;
+    let form = "title=Best+language&description=Which+one%3F";
+    let response = app.post_admin_form("", "/admin/polls", form).await;
+    assert_eq!(response.headers[header::LOCATION], "/admin/login");
+
+    let cookies = app.login_admin().await;
+    let response = app
+        .post_admin_form(&cookies, "/admin/polls", "title=+&description=")
+        .await;
+    assert_eq!(response.headers[header::LOCATION], "/admin");
+    let response = app.post_admin_form(&cookies, "/admin/polls", form).await;
+    assert_eq!(response.headers[header::LOCATION], "/admin/polls/2/items");
+
+    // New polls are drafts until they are set up
+    let result = app.get("/api/v1/polls/2/result").await.json();
+    assert_eq!(result["poll_status"], "draft");
+    for title in ["Rust", "OCaml"] {
+        app.post_admin_form(
+            &cookies,
+            "/admin/polls/2/items",
+            &format!("title={title}&content="),
+        )
+        .await;
+    }
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/2/lifecycle",
+        "status=open&opens_at=&closes_at=",
+    )
+    .await;
+
+    let voter = uuid();
+    app.register(2, &voter).await;
+    let response = app.put_rankings(2, &voter, json!([4])).await;
+    assert_eq!(response.status, StatusCode::OK);
+    let result = app.get("/api/v1/polls/2/result").await.json();
+    assert_eq!(titles(&result["items"]), ["Rust"]);
+
+    let response = app.get("/").await;
+    assert!(response.body.contains("Best language"));
+}
+
+#[tokio::test]
+async fn admin_pages_require_a_login_whatever_the_path_encoding() {
+    let app = spawn_app().await;
+    let form = "title=Best+language&description=";
+    let response = app.post_admin_form("", "/%61dmin/polls", form).await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+    assert_eq!(response.headers[header::LOCATION], "/admin/login");
+    let response = app.get("/%61dmin").await;
+    assert_eq!(response.headers[header::LOCATION], "/admin/login");
+
+    let response = app.get("/api/v1/polls/2/result").await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn ballot_without_a_session_redirects_to_the_poll() {
+    let app = spawn_app().await;
+    for path in ["/polls/1/ballot", "/polls/1/%62allot"] {
+        let response = app.get(path).await;
+        assert_eq!(response.status, StatusCode::SEE_OTHER);
+        assert_eq!(response.headers[header::LOCATION], "/polls/1");
+    }
+}
+
+#[tokio::test]
+async fn admin_issues_voter_tokens() {
+    let app = spawn_app().await;
+    let response = app
+        .post_admin_form("", "/admin/polls/1/%74okens", "count=3")
+        .await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+    assert_eq!(response.headers[header::LOCATION], "/admin/login");
+    let response = app.get("/admin/polls/1/tokens").await;
+    assert_eq!(response.headers[header::LOCATION], "/admin/login");
+
+    let cookies = app.login_admin().await;
+    let response = app
+        .post_admin_form(&cookies, "/admin/polls/1/tokens", "count=2")
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.body.contains("2 tokens issued, 0 of them used"));
+}
+
+#[tokio::test]
+async fn poll_with_several_seats_elects_items() {
+    let app = spawn_app().await;
+    let cookies = app.login_admin().await;
+    let response = app
+        .post_admin_form(&cookies, "/admin/polls/1/seats", "seats=2")
+        .await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+
+    let voters: Vec<_> = (0..5).map(|_| uuid()).collect();
+    for voter in &voters {
+        app.register(1, voter).await;
+    }
+    for voter in &voters[..3] {
+        app.put_rankings(1, voter, json!([1, 3])).await;
+    }
+    app.put_rankings(1, &voters[3], json!([2])).await;
+    app.put_rankings(1, &voters[4], json!([3])).await;
+
+    // Ada Lovelace reaches the quota of 2 ballots and her surplus goes to Grace Hopper
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(result["seats"], 2);
+    assert_eq!(result["method"], "single_transferable_vote");
+    assert_eq!(titles(&result["items"]), ["Ada Lovelace"]);
+    assert_eq!(titles(&result["elected"]), ["Ada Lovelace", "Grace Hopper"]);
+
+    let response = app.get("/polls/1/results").await;
+    assert!(response.body.contains("Elected for 2 seats"));
+}
+
+#[tokio::test]
+async fn leaderboard_places_every_item() {
+    let app = spawn_app().await;
+    let voters: Vec<_> = (0..3).map(|_| uuid()).collect();
+    for voter in &voters {
+        app.register(1, voter).await;
+    }
+    app.put_rankings(1, &voters[0], json!([2, 1])).await;
+    app.put_rankings(1, &voters[1], json!([2, 1])).await;
+    app.put_rankings(1, &voters[2], json!([1])).await;
+
+    let response = app.get("/polls/1/leaderboard").await;
+    assert_eq!(response.status, StatusCode::OK);
+    let alan = response.body.find("Alan Turing").unwrap();
+    let ada = response.body.find("Ada Lovelace").unwrap();
+    let grace = response.body.find("Grace Hopper").unwrap();
+    assert!(alan < ada && ada < grace);
+    assert!(response.body.contains("Not ranked"));
+}
+
+#[tokio::test]
+async fn star_poll_counts_scored_ballots() {
+    let app = spawn_app().await;
+    let cookies = app.login_admin().await;
+
+    // The ballot type can only be changed before the poll opens
+    let response = app
+        .post_admin_form(&cookies, "/admin/polls/1/ballot_type", "ballot_type=star")
+        .await;
+    assert_eq!(response.status, StatusCode::SEE_OTHER);
+    let poll = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(poll["ballot_type"], "ranked");
+
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/1/lifecycle",
+        "status=draft&opens_at=&closes_at=",
+    )
+    .await;
+    app.post_admin_form(&cookies, "/admin/polls/1/ballot_type", "ballot_type=star")
+        .await;
+    app.post_admin_form(
+        &cookies,
+        "/admin/polls/1/lifecycle",
+        "status=open&opens_at=&closes_at=",
+    )
+    .await;
+
+    // Ada Lovelace has the highest total but Alan Turing is scored higher on more ballots
+    let ballots = [
+        json!({ "1": "5", "2": "0", "3": "1" }),
+        json!({ "1": "3", "2": "4", "3": "1" }),
+        json!({ "1": "3", "2": "4", "3": "1" }),
+    ];
+    for scores in ballots {
+        let voter = uuid();
+        let cookies = app.register(1, &voter).await.cookies();
+        let response = app
+            .send(
+                Method::POST,
+                "/polls/1/ballot/scores",
+                &[
+                    (header::COOKIE, &cookies),
+                    (header::CONTENT_TYPE, "application/json"),
+                ],
+                scores.to_string(),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+
+        let response = app.put_rankings(1, &voter, json!([1])).await;
+        assert_eq!(response.status, StatusCode::CONFLICT);
+    }
+
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(result["ballot_type"], "star");
+    assert_eq!(result["method"], "star");
+    assert_eq!(titles(&result["items"]), ["Alan Turing"]);
+
+    // Polls with scored ballots elect a single item
+    app.post_admin_form(&cookies, "/admin/polls/1/seats", "seats=2")
+        .await;
+    let result = app.get("/api/v1/polls/1/result").await.json();
+    assert_eq!(result["seats"], 1);
+
+    let response = app.get("/polls/1/results").await;
+    assert!(response.body.contains("Automatic runoff"));
+}
+
+#[tokio::test]
+async fn event_streams_end_when_shutting_down() {
+    let (shutdown, stopped) = tokio::sync::oneshot::channel::<()>();
+    let app = spawn_app_until(async {
+        let _ = stopped.await;
+    })
+    .await;
+    let request = Request::builder()
+        .uri(format!("{}/polls/1/events", app.address))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.client.request(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    shutdown.send(()).unwrap();
+    // The shutdown timeout is much longer, so the stream must end on its own
+    let body = tokio::time::timeout(Duration::from_secs(5), body::to_bytes(response.into_body()))
+        .await
+        .expect("Stream was not ended");
+    assert!(body.is_ok());
+}
@@ -0,0 +1,303 @@
+//! Hash chain of ballot revisions. Every revision of a poll commits to the revision before it, so
+//! changing, inserting or removing a stored revision breaks the chain from that point on.
+
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+use crate::model::{BallotRevision, Ranking, Score};
+
+/// The previous hash of the first revision of a poll.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ChainError {
+    #[error("Revision {0} does not follow the revision before it")]
+    BrokenLink(i32),
+
+    #[error("Revision {0} does not match its hash")]
+    HashMismatch(i32),
+}
+
+impl ChainError {
+    /// The id of the first revision that could not be verified.
+    pub fn revision_id(&self) -> i32 {
+        match self {
+            ChainError::BrokenLink(id) | ChainError::HashMismatch(id) => *id,
+        }
+    }
+}
+
+/// Encode the ranks of item ids, from the most preferred, the way they are stored in a revision.
+/// Ranks are separated by `,` and items that share a rank are joined with `=`.
+pub fn encode_items(ranks: &[Vec<i32>]) -> String {
+    ranks
+        .iter()
+        .map(|rank| {
+            rank.iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join("=")
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Encode the scored item ids with their scores the way they are stored in a revision, each item
+/// is written as `id:score`.
+pub fn encode_scores(scores: &[(i32, u32)]) -> String {
+    scores
+        .iter()
+        .map(|(id, score)| format!("{id}:{score}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Decode the scored item ids of a revision, items without a score are skipped.
+pub fn decode_scores(scores: &str) -> Vec<(i32, u32)> {
+    scores
+        .split(',')
+        .filter_map(|entry| {
+            let (id, score) = entry.split_once(':')?;
+            Some((id.trim().parse().ok()?, score.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+/// Decode the ranks of item ids of a revision, ids that can not be read are skipped along with
+/// the ranks that are left empty.
+pub fn decode_items(items: &str) -> Vec<Vec<i32>> {
+    items
+        .split(',')
+        .map(|rank| {
+            rank.split('=')
+                .filter_map(|id| id.trim().parse().ok())
+                .collect::<Vec<_>>()
+        })
+        .filter(|rank| !rank.is_empty())
+        .collect()
+}
+
+/// Timestamps are kept with the precision that every database can store.
+pub fn revision_timestamp(timestamp: OffsetDateTime) -> OffsetDateTime {
+    timestamp
+        .replace_nanosecond(timestamp.microsecond() * 1_000)
+        .expect("microseconds are valid nanoseconds")
+}
+
+/// Hash the content of a revision together with the hash of the revision before it.
+pub fn revision_hash(
+    previous_hash: &str,
+    poll_id: i32,
+    ballot_id: i32,
+    items: &str,
+    created_at: OffsetDateTime,
+) -> String {
+    let created_at = created_at.unix_timestamp_nanos() / 1_000;
+    let content = format!("{previous_hash}\n{poll_id}\n{ballot_id}\n{created_at}\n{items}");
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// Check that the revisions of a poll, sorted by id, form an unbroken chain.
+pub fn verify_chain<'a, I>(revisions: I) -> Result<(), ChainError>
+where
+    I: IntoIterator<Item = &'a BallotRevision>,
+{
+    let mut previous_hash = GENESIS_HASH;
+    for revision in revisions {
+        if revision.previous_hash != previous_hash {
+            return Err(ChainError::BrokenLink(revision.id));
+        }
+        let hash = revision_hash(
+            &revision.previous_hash,
+            revision.poll_id,
+            revision.ballot_id,
+            &revision.items,
+            revision.created_at,
+        );
+        if revision.hash != hash {
+            return Err(ChainError::HashMismatch(revision.id));
+        }
+        previous_hash = &revision.hash;
+    }
+    Ok(())
+}
+
+/// The latest revision of every ballot, sorted by ballot id. Only revisions that were created at
+/// or before the given time are considered when there is one.
+pub fn latest_revisions<'a, I>(
+    revisions: I,
+    at: Option<OffsetDateTime>,
+) -> BTreeMap<i32, &'a BallotRevision>
+where
+    I: IntoIterator<Item = &'a BallotRevision>,
+{
+    let mut latest = BTreeMap::new();
+    for revision in revisions {
+        if at.is_none_or(|at| revision.created_at <= at) {
+            latest.insert(revision.ballot_id, revision);
+        }
+    }
+    latest
+}
+
+/// The rankings that the ballots had at the given time, sorted by ballot id and ranking order.
+pub fn rankings_at<'a, I>(revisions: I, at: OffsetDateTime) -> Vec<Ranking>
+where
+    I: IntoIterator<Item = &'a BallotRevision>,
+{
+    latest_revisions(revisions, Some(at))
+        .into_values()
+        .flat_map(|revision| {
+            decode_items(&revision.items)
+                .into_iter()
+                .enumerate()
+                .flat_map(move |(ord, rank)| {
+                    rank.into_iter().map(move |item_id| Ranking {
+                        ballot_id: revision.ballot_id,
+                        item_id,
+                        ord: ord as i32,
+                    })
+                })
+        })
+        .collect()
+}
+
+/// The scores that the ballots had at the given time, sorted by ballot id.
+pub fn scores_at<'a, I>(revisions: I, at: OffsetDateTime) -> Vec<Score>
+where
+    I: IntoIterator<Item = &'a BallotRevision>,
+{
+    latest_revisions(revisions, Some(at))
+        .into_values()
+        .flat_map(|revision| {
+            decode_scores(&revision.items)
+                .into_iter()
+                .map(|(item_id, score)| Score {
+                    ballot_id: revision.ballot_id,
+                    item_id,
+                    score: score as i32,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn strict(item_ids: &[i32]) -> Vec<Vec<i32>> {
+        item_ids.iter().map(|id| vec![*id]).collect()
+    }
+
+    fn chain(submissions: &[(i32, &[i32], OffsetDateTime)]) -> Vec<BallotRevision> {
+        let mut revisions: Vec<BallotRevision> = Vec::new();
+        for (id, (ballot_id, item_ids, created_at)) in submissions.iter().enumerate() {
+            let previous_hash = revisions
+                .last()
+                .map_or(GENESIS_HASH.to_string(), |r| r.hash.clone());
+            let items = encode_items(&strict(item_ids));
+            let hash = revision_hash(&previous_hash, 1, *ballot_id, &items, *created_at);
+            revisions.push(BallotRevision {
+                id: id as i32 + 1,
+                poll_id: 1,
+                ballot_id: *ballot_id,
+                items,
+                created_at: *created_at,
+                previous_hash,
+                hash,
+            });
+        }
+        revisions
+    }
+
+    fn submissions() -> Vec<(i32, &'static [i32], OffsetDateTime)> {
+        vec![
+            (1, &[1, 2], datetime!(2026-10-01 10:00 UTC)),
+            (2, &[2], datetime!(2026-10-01 11:00 UTC)),
+            (1, &[3, 1], datetime!(2026-10-01 12:00 UTC)),
+        ]
+    }
+
+    #[test]
+    fn verify_untouched_chain() {
+        assert_eq!(verify_chain(&chain(&submissions())), Ok(()));
+        assert_eq!(verify_chain(&[]), Ok(()));
+    }
+
+    #[test]
+    fn verify_detects_tampering() {
+        let mut revisions = chain(&submissions());
+        revisions[1].items = encode_items(&strict(&[1]));
+        assert_eq!(verify_chain(&revisions), Err(ChainError::HashMismatch(2)));
+
+        let mut revisions = chain(&submissions());
+        revisions.remove(1);
+        assert_eq!(verify_chain(&revisions), Err(ChainError::BrokenLink(3)));
+
+        let mut revisions = chain(&submissions());
+        let forged = &chain(&[(1, &[2], datetime!(2026-10-01 10:00 UTC))])[0];
+        revisions[0].items = forged.items.clone();
+        revisions[0].hash = forged.hash.clone();
+        assert_eq!(verify_chain(&revisions), Err(ChainError::BrokenLink(2)));
+    }
+
+    #[test]
+    fn rankings_at_replays_latest_revisions() {
+        let revisions = chain(&submissions());
+        let item_ids = |at| -> Vec<(i32, i32)> {
+            rankings_at(&revisions, at)
+                .into_iter()
+                .map(|r| (r.ballot_id, r.item_id))
+                .collect()
+        };
+        assert!(item_ids(datetime!(2026-10-01 09:00 UTC)).is_empty());
+        assert_eq!(
+            item_ids(datetime!(2026-10-01 11:30 UTC)),
+            vec![(1, 1), (1, 2), (2, 2)]
+        );
+        assert_eq!(
+            item_ids(datetime!(2026-10-01 12:00 UTC)),
+            vec![(1, 3), (1, 1), (2, 2)]
+        );
+    }
+
+    #[test]
+    fn scores_and_rankings_are_kept_apart() {
+        let scores = encode_scores(&[(3, 5), (1, 0)]);
+        assert_eq!(scores, "3:5,1:0");
+        assert_eq!(decode_scores(&scores), vec![(3, 5), (1, 0)]);
+        assert!(decode_items(&scores).is_empty());
+        assert!(decode_scores(&encode_items(&strict(&[1, 2]))).is_empty());
+    }
+
+    #[test]
+    fn encode_items_keeps_equal_ranks() {
+        let items = encode_items(&[vec![3], vec![1, 2], vec![4]]);
+        assert_eq!(items, "3,1=2,4");
+        assert_eq!(decode_items(&items), vec![vec![3], vec![1, 2], vec![4]]);
+        assert_eq!(decode_items("1,2"), strict(&[1, 2]));
+        assert!(decode_items("").is_empty());
+
+        let revisions = vec![BallotRevision {
+            id: 1,
+            poll_id: 1,
+            ballot_id: 1,
+            items,
+            created_at: datetime!(2026-10-01 10:00 UTC),
+            previous_hash: GENESIS_HASH.to_string(),
+            hash: String::new(),
+        }];
+        let ords: Vec<_> = rankings_at(&revisions, datetime!(2026-10-01 10:00 UTC))
+            .into_iter()
+            .map(|r| (r.item_id, r.ord))
+            .collect();
+        assert_eq!(ords, vec![(3, 0), (1, 1), (2, 1), (4, 2)]);
+    }
+}
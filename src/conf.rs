@@ -1,10 +1,16 @@
-use std::{io, path::Path};
+use std::{io, path::Path, time::Duration};
 
 use secrecy::{ExposeSecret, Secret};
-use serde_aux::field_attributes::deserialize_number_from_string;
+use serde_aux::field_attributes::{
+    deserialize_number_from_string, deserialize_option_number_from_string,
+};
 use tracing_subscriber::EnvFilter;
 
-use crate::{CONFIG_BASE_NAME, CONFIG_DIRECTORY, ENV_PREFIX, ENV_RUN_MODE};
+use crate::{
+    model::RegistrationMode,
+    voting::{tie_break::TieBreak, Method},
+    CONFIG_BASE_NAME, CONFIG_DIRECTORY, ENV_PREFIX, ENV_RUN_MODE,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum ConfigurationError {
@@ -66,13 +72,25 @@ impl<'a> ConfigurationBuilder<'a> {
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct Configuration {
+    admin: AdminConfiguration,
     application: ApplicationConfiguration,
     cookie: CookieConfiguration,
     database: DatabaseConfiguration,
+    #[serde(default)]
+    health: HealthConfiguration,
+    #[serde(default)]
+    metrics: MetricsConfiguration,
+    #[serde(default)]
+    registration: RegistrationConfiguration,
     tracing: TracingConfiguration,
+    voting: VotingConfiguration,
 }
 
 impl Configuration {
+    pub fn admin(&self) -> &AdminConfiguration {
+        &self.admin
+    }
+
     pub fn application(&self) -> &ApplicationConfiguration {
         &self.application
     }
@@ -85,9 +103,43 @@ impl Configuration {
         &self.database
     }
 
+    pub fn health(&self) -> &HealthConfiguration {
+        &self.health
+    }
+
+    pub fn metrics(&self) -> &MetricsConfiguration {
+        &self.metrics
+    }
+
+    pub fn registration(&self) -> &RegistrationConfiguration {
+        &self.registration
+    }
+
     pub fn tracing(&self) -> &TracingConfiguration {
         &self.tracing
     }
+
+    pub fn voting(&self) -> &VotingConfiguration {
+        &self.voting
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct AdminConfiguration {
+    password: Secret<String>,
+    /// Number of login attempts that are allowed from an IP address.
+    #[serde(default)]
+    rate_limit: RateLimitConfiguration,
+}
+
+impl AdminConfiguration {
+    pub fn password(&self) -> &Secret<String> {
+        &self.password
+    }
+
+    pub fn rate_limit(&self) -> &RateLimitConfiguration {
+        &self.rate_limit
+    }
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -97,6 +149,9 @@ pub struct ApplicationConfiguration {
     port: u16,
     url: String,
     flash_message_minimum_level: actix_web_flash_messages::Level,
+    /// How long requests that are being handled may take to finish once shutdown has started.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    shutdown_timeout_seconds: u64,
 }
 
 impl ApplicationConfiguration {
@@ -111,6 +166,10 @@ impl ApplicationConfiguration {
     pub fn flash_message_minimum_level(&self) -> actix_web_flash_messages::Level {
         self.flash_message_minimum_level
     }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_seconds)
+    }
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -134,18 +193,62 @@ impl CookieConfiguration {
     }
 }
 
+/// The database server that stores the data.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseKind {
+    #[default]
+    Mysql,
+    Postgres,
+    Sqlite,
+    /// Keep everything in memory, the data is lost when the application stops.
+    Memory,
+}
+
+/// Connection settings of the database. A URL can be given instead of the separate fields, in
+/// which case its scheme decides the kind of database. The database of SQLite is a file name.
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct DatabaseConfiguration {
+    #[serde(default)]
+    kind: DatabaseKind,
+    url: Option<Secret<String>>,
+    #[serde(default)]
     require_ssl: bool,
+    #[serde(default)]
     host: String,
-    #[serde(deserialize_with = "deserialize_number_from_string")]
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
     port: u16,
+    #[serde(default)]
     username: String,
+    #[serde(default = "empty_secret")]
     password: Secret<String>,
+    #[serde(default)]
     database: String,
 }
 
+fn empty_secret() -> Secret<String> {
+    Secret::new(String::new())
+}
+
 impl DatabaseConfiguration {
+    pub fn kind(&self) -> DatabaseKind {
+        let scheme = self
+            .url
+            .as_ref()
+            .and_then(|url| url.expose_secret().split_once(':'))
+            .map(|(scheme, _)| scheme);
+        match scheme {
+            Some("mysql" | "mariadb") => DatabaseKind::Mysql,
+            Some("postgres" | "postgresql") => DatabaseKind::Postgres,
+            Some("sqlite") => DatabaseKind::Sqlite,
+            _ => self.kind,
+        }
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_ref().map(|url| url.expose_secret().as_str())
+    }
+
     pub fn require_ssl(&self) -> bool {
         self.require_ssl
     }
@@ -171,6 +274,124 @@ impl DatabaseConfiguration {
     }
 }
 
+/// How long the readiness check waits for each dependency before reporting it as down.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct HealthConfiguration {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    database_timeout_ms: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    otlp_timeout_ms: u64,
+}
+
+impl Default for HealthConfiguration {
+    fn default() -> Self {
+        Self {
+            database_timeout_ms: 1000,
+            otlp_timeout_ms: 1000,
+        }
+    }
+}
+
+impl HealthConfiguration {
+    pub fn database_timeout(&self) -> Duration {
+        Duration::from_millis(self.database_timeout_ms)
+    }
+
+    pub fn otlp_timeout(&self) -> Duration {
+        Duration::from_millis(self.otlp_timeout_ms)
+    }
+}
+
+/// Whether metrics are collected and the path where they are exposed for Prometheus. They are off
+/// unless enabled, since anyone who can reach the application can read them.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct MetricsConfiguration {
+    enabled: bool,
+    path: String,
+}
+
+impl Default for MetricsConfiguration {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "/metrics".to_string(),
+        }
+    }
+}
+
+impl MetricsConfiguration {
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// How voters get their ballots. Voters who already have a ballot can always use it again.
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct RegistrationConfiguration {
+    #[serde(default)]
+    mode: RegistrationMode,
+    /// The code that voters must give when registering with invite codes.
+    invite_code: Option<Secret<String>>,
+    #[serde(default)]
+    rate_limit: RateLimitConfiguration,
+}
+
+impl RegistrationConfiguration {
+    pub fn mode(&self) -> RegistrationMode {
+        self.mode
+    }
+
+    pub fn invite_code(&self) -> Option<&Secret<String>> {
+        self.invite_code.as_ref()
+    }
+
+    pub fn rate_limit(&self) -> &RateLimitConfiguration {
+        &self.rate_limit
+    }
+}
+
+/// Number of requests that are allowed from an IP address in a period of time.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct RateLimitConfiguration {
+    /// No limit is applied when this is 0.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    requests: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    period_seconds: u64,
+    /// Take the address from the `Forwarded` or `X-Forwarded-For` headers, which should only be
+    /// done behind a proxy that sets them.
+    #[serde(default)]
+    use_forwarded_for: bool,
+}
+
+impl Default for RateLimitConfiguration {
+    fn default() -> Self {
+        Self {
+            requests: 10,
+            period_seconds: 60,
+            use_forwarded_for: false,
+        }
+    }
+}
+
+impl RateLimitConfiguration {
+    pub fn requests(&self) -> u32 {
+        self.requests
+    }
+
+    pub fn period(&self) -> Duration {
+        Duration::from_secs(self.period_seconds)
+    }
+
+    pub fn use_forwarded_for(&self) -> bool {
+        self.use_forwarded_for
+    }
+}
+
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct TracingConfiguration {
     service_name: String,
@@ -192,6 +413,31 @@ impl TracingConfiguration {
     }
 }
 
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct VotingConfiguration {
+    method: Method,
+    #[serde(default)]
+    tie_break: TieBreak,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    seed: Option<u64>,
+}
+
+impl VotingConfiguration {
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
+
+    /// Seed for drawing between tied items. A random seed is chosen on startup if it is not
+    /// configured, and is reported along with the results so that they can be reproduced.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+}
+
 /// The possible runtime environment for our application.
 pub enum RunMode {
     Local,
@@ -3,6 +3,12 @@ use std::{
     hash::Hash,
 };
 
+use crate::voting::{
+    shares,
+    tie_break::{TieBreak, TieBreaker},
+    transfers, BALLOT_VALUE,
+};
+
 /// Result of a poll
 pub enum InstantRunoffVotingResult<'a, T> {
     /// Could not finish the poll
@@ -15,78 +21,180 @@ pub enum InstantRunoffVotingResult<'a, T> {
     Winner(&'a T),
 }
 
+/// Value that moved from an eliminated item to the next preference of its ballots.
+pub struct InstantRunoffVotingTransfer<'a, T> {
+    /// The eliminated item that the ballots were counted for
+    pub from: &'a T,
+
+    /// The next remaining preference on the ballots, `None` if the ballots are exhausted
+    pub to: Option<&'a T>,
+
+    /// Value that was transferred, in fractions of `BALLOT_VALUE`
+    pub value: u64,
+}
+
+/// A single counting round of a poll
+pub struct InstantRunoffVotingRound<'a, T> {
+    /// Value of the first preferences received by each remaining item, sorted by descending
+    /// value
+    pub counts: Vec<(&'a T, u64)>,
+
+    /// Items that were eliminated at the end of the round
+    pub eliminated: Vec<&'a T>,
+
+    /// The rule that decided which item to eliminate when several items had the fewest ballots
+    pub tie_break: Option<TieBreak>,
+
+    /// Where the ballots of the eliminated items went to
+    pub transfers: Vec<InstantRunoffVotingTransfer<'a, T>>,
+
+    /// Value of the ballots without any remaining preference during the round
+    pub exhausted: u64,
+}
+
+/// The full record of a poll, containing every counting round and the final result
+pub struct InstantRunoffVotingTrace<'a, T> {
+    pub rounds: Vec<InstantRunoffVotingRound<'a, T>>,
+    pub result: InstantRunoffVotingResult<'a, T>,
+}
+
 /// Determine the best item(s) using the instant-runoff voting system. This function does not
 /// guarantee the winner to be the one receives the majority votes.
-pub fn instant_runoff_vote<'a, T>(ballots: &'a [&'a [T]]) -> InstantRunoffVotingResult<'a, T>
+///
+/// A ballot counts for its most preferred item that is not eliminated. When it ranks several
+/// remaining items first, its value is split equally between them, and a truncated ballot is
+/// exhausted once every item it lists is eliminated.
+///
+/// When several items have the fewest ballots, the tie breaker chooses the only one that gets
+/// eliminated. If the tie breaker does not break ties, all of them are eliminated at once.
+pub fn instant_runoff_vote<'a, T>(
+    ballots: &'a [&'a [Vec<T>]],
+    tie_breaker: &mut TieBreaker,
+) -> InstantRunoffVotingResult<'a, T>
+where
+    T: 'a + Ord + Hash,
+{
+    instant_runoff_trace(ballots, tie_breaker).result
+}
+
+/// Run the instant-runoff voting system while recording what happened in every round.
+pub fn instant_runoff_trace<'a, T>(
+    ballots: &'a [&'a [Vec<T>]],
+    tie_breaker: &mut TieBreaker,
+) -> InstantRunoffVotingTrace<'a, T>
 where
-    T: 'a + Eq + Hash,
+    T: 'a + Ord + Hash,
 {
+    let mut rounds = Vec::new();
+    let mut history = Vec::new();
     let mut eliminated_items: HashSet<&T> = HashSet::new();
-    loop {
+    let result = loop {
         // Count ballots
-        let mut ballots_count: HashMap<&T, u32> = HashMap::new();
+        let mut exhausted = 0;
+        let mut ballots_value: HashMap<&T, u64> = HashMap::new();
         for &vote in ballots {
-            for opt in vote {
-                if !eliminated_items.contains(opt) {
-                    let count = ballots_count.entry(opt).or_insert(0);
-                    *count += 1;
-                    break;
-                }
+            let shares = shares(vote, &eliminated_items, BALLOT_VALUE);
+            if shares.is_empty() {
+                exhausted += BALLOT_VALUE;
+            }
+            for (opt, value) in shares {
+                *ballots_value.entry(opt).or_insert(0) += value;
             }
         }
+        let mut counts: Vec<_> = ballots_value.into_iter().collect();
+        counts.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+        history.push(counts.clone());
+        let mut round = InstantRunoffVotingRound {
+            counts,
+            eliminated: Vec::new(),
+            tie_break: None,
+            transfers: Vec::new(),
+            exhausted,
+        };
+
         // There is no vote
-        if ballots_count.is_empty() {
-            break InstantRunoffVotingResult::NoWinner;
-        }
+        let (max_count, min_count) = match (round.counts.first(), round.counts.last()) {
+            (Some(&(_, max)), Some(&(_, min))) => (max, min),
+            _ => {
+                rounds.push(round);
+                break InstantRunoffVotingResult::NoWinner;
+            }
+        };
 
         // Get items with most number of ballots and items with least number of ballots
-        let mut max_count = u32::MIN;
-        let mut min_count = u32::MAX;
-        let mut best_items = Vec::new();
-        let mut worst_items = Vec::new();
-        for (&k, &v) in ballots_count.iter() {
-            if v > max_count {
-                best_items.clear();
-                max_count = v;
-            }
-            if v >= max_count {
-                best_items.push(k);
-            }
-            if v < min_count {
-                worst_items.clear();
-                min_count = v;
-            }
-            if v <= min_count {
-                worst_items.push(k);
-            }
-        }
+        let best_items: Vec<_> = round
+            .counts
+            .iter()
+            .filter(|&&(_, v)| v == max_count)
+            .map(|&(k, _)| k)
+            .collect();
+        let worst_items: Vec<_> = round
+            .counts
+            .iter()
+            .filter(|&&(_, v)| v == min_count)
+            .map(|&(k, _)| k)
+            .collect();
 
         // Only one item received the majority of ballots
         if best_items.len() == 1 {
+            rounds.push(round);
             break InstantRunoffVotingResult::Winner(best_items[0]);
         }
-        // Tied when ballots are evenly distributed
-        if max_count == min_count {
-            break InstantRunoffVotingResult::Tied(best_items);
-        }
 
-        for opt in worst_items {
-            eliminated_items.insert(opt);
+        let eliminated = match tie_breaker.weakest(&worst_items, &history, ballots) {
+            Some((opt, rule)) => {
+                if worst_items.len() > 1 {
+                    round.tie_break = Some(rule);
+                }
+                vec![opt]
+            }
+            // Tied when ballots are evenly distributed
+            None if max_count == min_count => {
+                rounds.push(round);
+                break InstantRunoffVotingResult::Tied(best_items);
+            }
+            None => worst_items,
+        };
+
+        // Record where the ballots of the eliminated items go to
+        let current_shares: Vec<_> = ballots
+            .iter()
+            .map(|&vote| shares(vote, &eliminated_items, BALLOT_VALUE))
+            .collect();
+        eliminated_items.extend(eliminated.iter().copied());
+        for (&vote, before) in ballots.iter().zip(current_shares) {
+            let after = shares(vote, &eliminated_items, BALLOT_VALUE);
+            for (from, to, value) in transfers(&before, &after, &eliminated_items, BALLOT_VALUE) {
+                match round
+                    .transfers
+                    .iter_mut()
+                    .find(|t| t.from == from && t.to == to)
+                {
+                    Some(transfer) => transfer.value += value,
+                    None => round
+                        .transfers
+                        .push(InstantRunoffVotingTransfer { from, to, value }),
+                }
+            }
         }
-    }
+        round.eliminated = eliminated;
+        rounds.push(round);
+    };
+    InstantRunoffVotingTrace { rounds, result }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::voting::strict;
 
     #[test]
     fn irv_basic_poll() {
-        let vote_a = vec!["bob", "bill", "sue"];
-        let vote_b = vec!["sue", "bob", "bill"];
-        let vote_c = vec!["bill", "sue", "bob"];
-        let vote_d = vec!["bob", "bill", "sue"];
-        let vote_e = vec!["sue", "bob", "bill"];
+        let vote_a = strict(&["bob", "bill", "sue"]);
+        let vote_b = strict(&["sue", "bob", "bill"]);
+        let vote_c = strict(&["bill", "sue", "bob"]);
+        let vote_d = strict(&["bob", "bill", "sue"]);
+        let vote_e = strict(&["sue", "bob", "bill"]);
 
         let votes = vec![
             vote_a.as_slice(),
@@ -95,7 +203,7 @@ mod tests {
             vote_d.as_slice(),
             vote_e.as_slice(),
         ];
-        match instant_runoff_vote(&votes) {
+        match instant_runoff_vote(&votes, &mut TieBreaker::default()) {
             InstantRunoffVotingResult::NoWinner => unreachable!(),
             InstantRunoffVotingResult::Tied(_) => unreachable!(),
             InstantRunoffVotingResult::Winner(winner) => assert_eq!(winner, &"sue"),
@@ -104,11 +212,11 @@ mod tests {
 
     #[test]
     fn irv_tied_01() {
-        let vote_a = vec!["bob"];
-        let vote_b = vec!["sue"];
+        let vote_a = strict(&["bob"]);
+        let vote_b = strict(&["sue"]);
 
         let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
-        match instant_runoff_vote(&votes) {
+        match instant_runoff_vote(&votes, &mut TieBreaker::default()) {
             InstantRunoffVotingResult::NoWinner => unreachable!(),
             InstantRunoffVotingResult::Winner(_) => unreachable!(),
             InstantRunoffVotingResult::Tied(options) => {
@@ -120,11 +228,11 @@ mod tests {
 
     #[test]
     fn irv_tied_02() {
-        let vote_a = vec!["bob", "sue"];
-        let vote_b = vec!["sue", "bob"];
+        let vote_a = strict(&["bob", "sue"]);
+        let vote_b = strict(&["sue", "bob"]);
 
         let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
-        match instant_runoff_vote(&votes) {
+        match instant_runoff_vote(&votes, &mut TieBreaker::default()) {
             InstantRunoffVotingResult::NoWinner => unreachable!(),
             InstantRunoffVotingResult::Winner(_) => unreachable!(),
             InstantRunoffVotingResult::Tied(options) => {
@@ -136,11 +244,147 @@ mod tests {
 
     #[test]
     fn irv_no_vote() {
-        let votes: Vec<&[&str]> = vec![];
-        match instant_runoff_vote(&votes) {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        match instant_runoff_vote(&votes, &mut TieBreaker::default()) {
             InstantRunoffVotingResult::Tied(_) => unreachable!(),
             InstantRunoffVotingResult::Winner(_) => unreachable!(),
             InstantRunoffVotingResult::NoWinner => {}
         };
     }
+
+    #[test]
+    fn irv_trace_rounds() {
+        let vote_a = strict(&["bob", "bill", "sue"]);
+        let vote_b = strict(&["sue", "bob", "bill"]);
+        let vote_c = strict(&["bill", "sue"]);
+        let vote_d = strict(&["bill"]);
+
+        let votes = vec![
+            vote_a.as_slice(),
+            vote_a.as_slice(),
+            vote_a.as_slice(),
+            vote_b.as_slice(),
+            vote_b.as_slice(),
+            vote_b.as_slice(),
+            vote_c.as_slice(),
+            vote_d.as_slice(),
+        ];
+        let trace = instant_runoff_trace(&votes, &mut TieBreaker::default());
+        assert_eq!(trace.rounds.len(), 2);
+
+        let first = &trace.rounds[0];
+        assert_eq!(first.counts.len(), 3);
+        assert_eq!(first.counts[2], (&"bill", 2 * BALLOT_VALUE));
+        assert_eq!(first.eliminated, vec![&"bill"]);
+        assert_eq!(first.exhausted, 0);
+        assert_eq!(first.transfers.len(), 2);
+        assert!(first
+            .transfers
+            .iter()
+            .any(|t| t.from == &"bill" && t.to == Some(&"sue") && t.value == BALLOT_VALUE));
+        assert!(first
+            .transfers
+            .iter()
+            .any(|t| t.from == &"bill" && t.to.is_none() && t.value == BALLOT_VALUE));
+
+        let second = &trace.rounds[1];
+        assert_eq!(
+            second.counts,
+            vec![(&"sue", 4 * BALLOT_VALUE), (&"bob", 3 * BALLOT_VALUE)]
+        );
+        assert_eq!(second.exhausted, BALLOT_VALUE);
+        assert!(second.eliminated.is_empty());
+        match trace.result {
+            InstantRunoffVotingResult::NoWinner => unreachable!(),
+            InstantRunoffVotingResult::Tied(_) => unreachable!(),
+            InstantRunoffVotingResult::Winner(winner) => assert_eq!(winner, &"sue"),
+        };
+    }
+
+    #[test]
+    fn irv_splits_equal_ranks() {
+        let vote_a = vec![vec!["bob", "sue"]];
+        let vote_b = strict(&["bob"]);
+        let vote_c = strict(&["sue"]);
+        let vote_d = vec![vec!["bill"], vec!["bob", "sue"]];
+
+        let votes = vec![
+            vote_a.as_slice(),
+            vote_b.as_slice(),
+            vote_c.as_slice(),
+            vote_d.as_slice(),
+        ];
+        let trace = instant_runoff_trace(&votes, &mut TieBreaker::default());
+
+        let first = &trace.rounds[0];
+        assert_eq!(
+            first.counts,
+            vec![
+                (&"bob", 3 * BALLOT_VALUE / 2),
+                (&"sue", 3 * BALLOT_VALUE / 2),
+                (&"bill", BALLOT_VALUE)
+            ]
+        );
+        assert_eq!(first.eliminated, vec![&"bill"]);
+        assert_eq!(first.transfers.len(), 2);
+        assert!(first
+            .transfers
+            .iter()
+            .all(|t| t.from == &"bill" && t.to.is_some() && t.value == BALLOT_VALUE / 2));
+        assert_eq!(
+            trace.rounds[1].counts,
+            vec![(&"bob", 2 * BALLOT_VALUE), (&"sue", 2 * BALLOT_VALUE)]
+        );
+    }
+
+    #[test]
+    fn irv_tie_break_eliminates_one_item() {
+        let vote_a = strict(&["bob", "sue"]);
+        let vote_b = strict(&["sue", "bill"]);
+        let vote_c = strict(&["bill", "bob"]);
+        let vote_d = strict(&["ann", "bob"]);
+
+        let votes = vec![
+            vote_a.as_slice(),
+            vote_a.as_slice(),
+            vote_a.as_slice(),
+            vote_b.as_slice(),
+            vote_b.as_slice(),
+            vote_b.as_slice(),
+            vote_c.as_slice(),
+            vote_d.as_slice(),
+        ];
+        let mut tie_breaker = TieBreaker::new(TieBreak::Borda, 0);
+        let trace = instant_runoff_trace(&votes, &mut tie_breaker);
+
+        // "ann" has a lower Borda score than "bill" so only "ann" is eliminated
+        let first = &trace.rounds[0];
+        assert_eq!(first.eliminated, vec![&"ann"]);
+        assert_eq!(first.tie_break, Some(TieBreak::Borda));
+        match trace.result {
+            InstantRunoffVotingResult::NoWinner => unreachable!(),
+            InstantRunoffVotingResult::Tied(_) => unreachable!(),
+            InstantRunoffVotingResult::Winner(winner) => assert_eq!(winner, &"bob"),
+        };
+    }
+
+    #[test]
+    fn irv_tie_break_resolves_tied_result() {
+        let vote_a = strict(&["bob", "sue"]);
+        let vote_b = strict(&["sue", "bob"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        let mut tie_breaker = TieBreaker::new(TieBreak::Random, 7);
+        let first = match instant_runoff_vote(&votes, &mut tie_breaker) {
+            InstantRunoffVotingResult::Winner(winner) => winner,
+            _ => unreachable!(),
+        };
+
+        let mut tie_breaker = TieBreaker::new(TieBreak::Random, 7);
+        let second = match instant_runoff_vote(&votes, &mut tie_breaker) {
+            InstantRunoffVotingResult::Winner(winner) => winner,
+            _ => unreachable!(),
+        };
+        assert_eq!(first, second);
+    }
 }
@@ -2,13 +2,20 @@ pub mod app;
 pub mod conf;
 pub mod telemetry;
 
+pub(crate) mod audit;
 pub(crate) mod irv;
+pub(crate) mod metrics;
 pub(crate) mod middleware;
 pub(crate) mod model;
+pub(crate) mod pairwise;
+pub(crate) mod rate_limit;
 pub(crate) mod repository;
 pub(crate) mod route;
+pub(crate) mod score;
 pub(crate) mod service;
+pub(crate) mod stv;
 pub(crate) mod view;
+pub(crate) mod voting;
 
 pub(crate) const ENV_PREFIX: &str = "POLL";
 pub(crate) const ENV_RUN_MODE: &str = "POLL__RUN_MODE";
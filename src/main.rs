@@ -1,6 +1,6 @@
 use clap::Parser;
 use poll::{
-    app::{Application, Cli},
+    app::{Application, Cli, Command},
     conf::ConfigurationBuilder,
     telemetry,
 };
@@ -10,6 +10,10 @@ async fn main() -> Result<(), anyhow::Error> {
     let mut config_builder = ConfigurationBuilder::default();
 
     let cli = Cli::parse();
+    // Tallying a file does not need the configuration of the server.
+    if let Some(Command::Tally(args)) = cli.command() {
+        return poll::app::tally(args);
+    }
     if let Some(config) = cli.config() {
         config_builder.config_directory(config);
     }
@@ -22,7 +26,9 @@ async fn main() -> Result<(), anyhow::Error> {
         None => {
             telemetry::setup_tracing(&configuration)?;
             let app = Application::new(&configuration)?;
-            app.run().await?;
+            let result = app.run().await;
+            telemetry::shutdown_tracing().await;
+            result?;
         }
     }
     Ok(())
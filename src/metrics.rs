@@ -0,0 +1,266 @@
+//! Metrics of the application, exposed in the Prometheus text format. They are kept in a single
+//! registry for the whole process, so that every part of the application can update them.
+
+use std::{sync::OnceLock, time::Instant};
+
+use prometheus::{
+    core::{Collector, Desc},
+    proto::MetricFamily,
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+    TextEncoder,
+};
+use tracing::{span, Level, Subscriber};
+use tracing_subscriber::{filter::Targets, layer::Context, registry::LookupSpan, Layer};
+
+/// Prefix of the name of every metric.
+const NAMESPACE: &str = "poll";
+
+/// Module of the repositories, whose instrumented methods are timed as database queries.
+const REPOSITORY_TARGET: &str = concat!(env!("CARGO_CRATE_NAME"), "::repository::");
+
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    db_query_duration: HistogramVec,
+    tally_duration: HistogramVec,
+    ballots_registered: IntCounterVec,
+    ballot_updates: IntCounterVec,
+}
+
+/// The metrics of the process, they are created when first used.
+pub fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Number of handled HTTP requests")
+                .namespace(NAMESPACE),
+            &["method", "route", "status"],
+        )
+        .expect("Failed to create metric");
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time taken to handle HTTP requests",
+            )
+            .namespace(NAMESPACE),
+            &["method", "route"],
+        )
+        .expect("Failed to create metric");
+        let db_query_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "db_query_duration_seconds",
+                "Time taken by the methods of the repositories",
+            )
+            .namespace(NAMESPACE)
+            .buckets(vec![
+                0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5,
+            ]),
+            &["repository", "method"],
+        )
+        .expect("Failed to create metric");
+        let tally_duration = HistogramVec::new(
+            HistogramOpts::new("tally_duration_seconds", "Time taken to tally the ballots")
+                .namespace(NAMESPACE)
+                .buckets(vec![
+                    0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0,
+                ]),
+            &["method"],
+        )
+        .expect("Failed to create metric");
+        let ballots_registered = IntCounterVec::new(
+            Opts::new(
+                "ballots_registered_total",
+                "Number of ballots given to voters",
+            )
+            .namespace(NAMESPACE),
+            &["poll_id"],
+        )
+        .expect("Failed to create metric");
+        let ballot_updates = IntCounterVec::new(
+            Opts::new(
+                "ballot_updates_total",
+                "Number of changes of the rankings or scores of ballots",
+            )
+            .namespace(NAMESPACE),
+            &["poll_id"],
+        )
+        .expect("Failed to create metric");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn Collector>,
+            Box::new(http_request_duration.clone()),
+            Box::new(db_query_duration.clone()),
+            Box::new(tally_duration.clone()),
+            Box::new(ballots_registered.clone()),
+            Box::new(ballot_updates.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Failed to register metric");
+        }
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_query_duration,
+            tally_duration,
+            ballots_registered,
+            ballot_updates,
+        }
+    }
+
+    /// Render every metric in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!(error = %e, "Failed to encode metrics");
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+
+    /// Report the connections of a database pool along with the other metrics. Only the first
+    /// pool that is registered is reported.
+    pub fn register_pool<DB>(&self, pool: sqlx::Pool<DB>)
+    where
+        DB: sqlx::Database,
+    {
+        if let Err(e) = self.registry.register(Box::new(PoolCollector::new(pool))) {
+            tracing::warn!(error = %e, "Failed to register the metrics of the database pool");
+        }
+    }
+
+    /// Count a handled request, `route` is the pattern of the matched route.
+    pub fn observe_http_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[method, route])
+            .observe(seconds);
+    }
+
+    /// Time a tally of the ballots using the given method.
+    pub fn time_tally<T>(&self, method: &str, f: impl FnOnce() -> T) -> T {
+        let _timer = self
+            .tally_duration
+            .with_label_values(&[method])
+            .start_timer();
+        f()
+    }
+
+    pub fn ballot_registered(&self, poll_id: i32) {
+        self.ballots_registered
+            .with_label_values(&[&poll_id.to_string()])
+            .inc();
+    }
+
+    pub fn ballot_updated(&self, poll_id: i32) {
+        self.ballot_updates
+            .with_label_values(&[&poll_id.to_string()])
+            .inc();
+    }
+}
+
+/// Reports how many connections of a database pool are in use when the metrics are gathered.
+struct PoolCollector<DB: sqlx::Database> {
+    pool: sqlx::Pool<DB>,
+    connections: IntGaugeVec,
+    max_connections: IntGauge,
+}
+
+impl<DB: sqlx::Database> PoolCollector<DB> {
+    fn new(pool: sqlx::Pool<DB>) -> Self {
+        let connections = IntGaugeVec::new(
+            Opts::new(
+                "db_pool_connections",
+                "Number of connections of the database pool",
+            )
+            .namespace(NAMESPACE),
+            &["state"],
+        )
+        .expect("Failed to create metric");
+        let max_connections = IntGauge::with_opts(
+            Opts::new(
+                "db_pool_max_connections",
+                "Maximum number of connections of the database pool",
+            )
+            .namespace(NAMESPACE),
+        )
+        .expect("Failed to create metric");
+        Self {
+            pool,
+            connections,
+            max_connections,
+        }
+    }
+}
+
+impl<DB: sqlx::Database> Collector for PoolCollector<DB> {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut descs = self.connections.desc();
+        descs.extend(self.max_connections.desc());
+        descs
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let size = self.pool.size() as i64;
+        let idle = self.pool.num_idle() as i64;
+        self.connections.with_label_values(&["idle"]).set(idle);
+        self.connections
+            .with_label_values(&["in_use"])
+            .set(size - idle);
+        self.max_connections
+            .set(self.pool.options().get_max_connections() as i64);
+        let mut families = self.connections.collect();
+        families.extend(self.max_connections.collect());
+        families
+    }
+}
+
+/// Times the instrumented methods of the repositories, from the creation of their span until it
+/// is closed.
+pub struct QueryMetricsLayer;
+
+impl QueryMetricsLayer {
+    /// Only the spans of the repositories are timed, whatever their level.
+    pub fn filter() -> Targets {
+        Targets::new().with_target(REPOSITORY_TARGET.trim_end_matches("::"), Level::TRACE)
+    }
+}
+
+struct QueryStart(Instant);
+
+impl<S> Layer<S> for QueryMetricsLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if !attrs.metadata().target().starts_with(REPOSITORY_TARGET) {
+            return;
+        }
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(QueryStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let Some(start) = span.extensions().get::<QueryStart>().map(|s| s.0) else {
+            return;
+        };
+        // Targets look like `poll::repository::sql::ranking`, the last part names the
+        // repository.
+        let target = span.metadata().target();
+        let repository = target.rsplit("::").next().unwrap_or(target);
+        metrics()
+            .db_query_duration
+            .with_label_values(&[repository, span.name()])
+            .observe(start.elapsed().as_secs_f64());
+    }
+}
@@ -1,11 +1,167 @@
-use std::rc::Rc;
+use std::{rc::Rc, time::Instant};
 
 use actix_web::{
     body::EitherBody,
-    dev::{ResourceDef, Service, ServiceRequest, ServiceResponse, Transform},
-    http, HttpResponse,
+    dev::{Path, ResourceDef, Service, ServiceRequest, ServiceResponse, Transform},
+    http::{
+        self,
+        header::{HeaderMap, HeaderName, HeaderValue},
+    },
+    HttpMessage, HttpResponse,
 };
 use futures::future::{ready, LocalBoxFuture, Ready};
+use tracing_actix_web::RequestId;
+
+use crate::metrics::metrics;
+
+/// Header carrying the id of the request, which is the `request_id` of its tracing span.
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_CONTEXT: RequestContext;
+}
+
+/// What is known about the request that is being handled when one of its errors is rendered.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RequestContext {
+    pub request_id: Option<RequestId>,
+    /// Whether the client asked for JSON rather than HTML.
+    pub prefers_json: bool,
+}
+
+impl RequestContext {
+    /// The context of the request being handled by the current task, requests that are not
+    /// handled through `CorrelationMiddleware` have no request id and prefer HTML.
+    pub fn current() -> Self {
+        REQUEST_CONTEXT.try_with(|c| *c).unwrap_or_default()
+    }
+
+    fn new(request: &ServiceRequest) -> Self {
+        Self {
+            request_id: request.extensions().get::<RequestId>().copied(),
+            prefers_json: prefers_json(request.headers()),
+        }
+    }
+}
+
+fn prefers_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(http::header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json") && !accept.contains("text/html"))
+}
+
+/// Make the request id given by the tracing logger available while the request is handled, and
+/// send it back in the `X-Request-Id` header so that a response can be matched with its logs.
+/// It must be wrapped inside the tracing logger.
+#[derive(Clone, Default)]
+pub struct CorrelationMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for CorrelationMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = CorrelationMiddlewareInner<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(CorrelationMiddlewareInner { service }))
+    }
+}
+
+pub struct CorrelationMiddlewareInner<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for CorrelationMiddlewareInner<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let context = RequestContext::new(&request);
+        let res = REQUEST_CONTEXT.sync_scope(context, || self.service.call(request));
+        Box::pin(REQUEST_CONTEXT.scope(context, async move {
+            let mut res = res.await?;
+            if let Some(value) = context
+                .request_id
+                .and_then(|id| HeaderValue::from_str(&id.to_string()).ok())
+            {
+                res.headers_mut().insert(REQUEST_ID_HEADER, value);
+            }
+            Ok(res)
+        }))
+    }
+}
+
+/// Count the handled requests and measure how long they take, labelled by the pattern of the
+/// route that matched so that the paths of different polls are counted together.
+#[derive(Clone, Default)]
+pub struct MetricsMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for MetricsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type InitError = ();
+    type Transform = MetricsMiddlewareInner<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddlewareInner { service }))
+    }
+}
+
+pub struct MetricsMiddlewareInner<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddlewareInner<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_web::dev::forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = request.method().clone();
+        let res = self.service.call(request);
+        Box::pin(async move {
+            let res = res.await?;
+            // The pattern is only known once the request has been routed.
+            let route = res.request().match_pattern();
+            metrics().observe_http_request(
+                method.as_str(),
+                route.as_deref().unwrap_or("unmatched"),
+                res.status().as_u16(),
+                started.elapsed().as_secs_f64(),
+            );
+            Ok(res)
+        })
+    }
+}
 
 #[derive(Clone)]
 pub struct RedirectMiddleware<F> {
@@ -15,6 +171,8 @@ pub struct RedirectMiddleware<F> {
 }
 
 impl<F> RedirectMiddleware<F> {
+    /// Redirect requests matching any of the resources and the predicate. Dynamic segments of the
+    /// matched resource, e.g. `{id}`, are substituted into the redirection target.
     pub fn new(redirected_to: &'static str, predicate: F, resources: Vec<ResourceDef>) -> Self {
         Self {
             redirected_to,
@@ -68,18 +226,26 @@ where
     actix_web::dev::forward_ready!(service);
 
     fn call(&self, request: ServiceRequest) -> Self::Future {
+        // The resources are matched against the decoded path like the routes of the app, so that
+        // percent-encoding a segment does not get around the redirection.
+        let mut path = Path::new(request.match_info().get_ref().clone());
         let match_resource = self
             .resources
             .iter()
-            .any(|res| res.is_match(request.path()));
+            .any(|res| res.capture_match_info(&mut path));
         let match_predicate = (self.predicate)(&request);
 
         tracing::debug!(%match_resource, %match_predicate, path = %request.path(), "calling redirect middleware");
 
         if match_resource && match_predicate {
+            let location = path
+                .iter()
+                .fold(self.redirected_to.to_string(), |location, (name, value)| {
+                    location.replace(&format!("{{{name}}}"), value)
+                });
             let (request, _pl) = request.into_parts();
             let response = HttpResponse::SeeOther()
-                .insert_header((http::header::LOCATION, self.redirected_to))
+                .insert_header((http::header::LOCATION, location))
                 .finish()
                 .map_into_right_body();
             return Box::pin(async { Ok(ServiceResponse::new(request, response)) });
@@ -1,58 +1,335 @@
-use serde::Serialize;
+use std::{borrow::Borrow, fmt, hash::Hash};
+
+use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
-#[derive(Clone, Debug, Serialize, PartialEq, Eq, Hash, FromRow)]
+use crate::{
+    irv::InstantRunoffVotingTrace,
+    pairwise::PairwiseMatrix,
+    score::{ScoreVotingTrace, MAX_SCORE},
+    stv::SingleTransferableVoteTrace,
+    voting::{
+        tie_break::{TieBreak, TieBreaker},
+        Method, VotingMethod, VotingResult, BALLOT_VALUE,
+    },
+};
+
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct Poll {
+    pub id: i32,
+    pub title: String,
+    pub description: String,
+    #[sqlx(try_from = "String")]
+    pub status: PollStatus,
+    /// When a draft poll opens by itself.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub opens_at: Option<OffsetDateTime>,
+    /// When the poll closes by itself.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub closes_at: Option<OffsetDateTime>,
+    /// Number of items that are elected, polls with more than one seat use the single
+    /// transferable vote.
+    pub seats: i32,
+    #[sqlx(try_from = "String")]
+    pub ballot_type: BallotType,
+}
+
+impl Poll {
+    /// The status of the poll at the given time, taking the scheduled times into account.
+    pub fn status_at(&self, now: OffsetDateTime) -> PollStatus {
+        if self.status == PollStatus::Closed || self.closes_at.is_some_and(|t| t <= now) {
+            PollStatus::Closed
+        } else if self.status == PollStatus::Open || self.opens_at.is_some_and(|t| t <= now) {
+            PollStatus::Open
+        } else {
+            PollStatus::Draft
+        }
+    }
+
+    /// The status of the poll right now.
+    pub fn current_status(&self) -> PollStatus {
+        self.status_at(OffsetDateTime::now_utc())
+    }
+
+    /// Number of items that the poll elects, polls with scored ballots elect a single item.
+    pub fn elected_seats(&self) -> usize {
+        if self.ballot_type.is_scored() {
+            1
+        } else {
+            self.seats.max(1) as usize
+        }
+    }
+
+    /// The method that decides the poll, given the method configured for ranked ballots, along
+    /// with its name in configuration.
+    pub fn method(&self, ranked: Method) -> (&'static str, &'static str) {
+        match self.ballot_type {
+            BallotType::Ranked if self.elected_seats() > 1 => {
+                ("Single transferable vote", "single_transferable_vote")
+            }
+            BallotType::Ranked => (ranked.name(), ranked.as_str()),
+            BallotType::Approval => ("Approval voting", "approval"),
+            BallotType::Score => ("Score voting", "score"),
+            BallotType::Star => ("STAR voting", "star"),
+        }
+    }
+}
+
+/// Ballots can only be changed while their poll is open. A closed poll can not be opened again.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PollStatus {
+    Draft,
+    #[default]
+    Open,
+    Closed,
+}
+
+impl PollStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Open => "open",
+            Self::Closed => "closed",
+        }
+    }
+}
+
+impl TryFrom<String> for PollStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "draft" => Ok(Self::Draft),
+            "open" => Ok(Self::Open),
+            "closed" => Ok(Self::Closed),
+            _ => Err(format!("Unknown poll status: {value}")),
+        }
+    }
+}
+
+/// How voters fill in the ballots of a poll.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BallotType {
+    /// Items are ranked from the most preferred.
+    #[default]
+    Ranked,
+    /// Items are approved or not.
+    Approval,
+    /// Items are scored from 0 to 5.
+    Score,
+    /// Items are scored from 0 to 5, then the two best items are compared head to head.
+    Star,
+}
+
+impl BallotType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ranked => "ranked",
+            Self::Approval => "approval",
+            Self::Score => "score",
+            Self::Star => "star",
+        }
+    }
+
+    /// Whether items are given a score instead of being ranked.
+    pub fn is_scored(&self) -> bool {
+        *self != Self::Ranked
+    }
+
+    /// Highest score that can be given to an item, approving an item gives it a score of 1.
+    pub fn max_score(&self) -> u32 {
+        match self {
+            Self::Ranked => 0,
+            Self::Approval => 1,
+            Self::Score | Self::Star => MAX_SCORE,
+        }
+    }
+}
+
+impl fmt::Display for BallotType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ranked => "Ranked",
+            Self::Approval => "Approval",
+            Self::Score => "Score",
+            Self::Star => "STAR",
+        })
+    }
+}
+
+impl TryFrom<String> for BallotType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "ranked" => Ok(Self::Ranked),
+            "approval" => Ok(Self::Approval),
+            "score" => Ok(Self::Score),
+            "star" => Ok(Self::Star),
+            _ => Err(format!("Unknown ballot type: {value}")),
+        }
+    }
+}
+
+/// The final tally of a closed poll, which is kept as it was when the poll was closed.
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct PollSnapshot {
+    pub poll_id: i32,
+    #[sqlx(json)]
+    pub result: TallyResult,
+    #[sqlx(json)]
+    pub trace: TallyTrace,
+    /// The elected items of a poll with more than one seat.
+    #[sqlx(json)]
+    pub election: Option<ElectionTrace>,
+    /// The totals of a poll with scored ballots.
+    #[sqlx(json)]
+    pub scores: Option<ScoreTally>,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, FromRow)]
 pub struct Item {
     pub id: i32,
     pub title: String,
     pub content: String,
     pub done: bool,
+    pub position: i32,
+    pub retired: bool,
 }
 
-#[derive(Debug, FromRow)]
+#[derive(Debug)]
+pub struct NewPoll {
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Debug)]
+pub struct NewItem {
+    pub poll_id: i32,
+    pub title: String,
+    pub content: String,
+}
+
+#[derive(Clone, Debug, FromRow)]
 pub struct Ballot {
     pub id: i32,
+    pub poll_id: i32,
     pub uuid: Uuid,
 }
 
+/// What a voter must give for getting a new ballot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    /// Anyone can get a ballot.
+    #[default]
+    Open,
+    /// The configured invite code is needed.
+    InviteCode,
+    /// A one-time token that was issued by an administrator is needed.
+    VoterRoll,
+}
+
+/// Number of voter tokens of a poll.
+#[derive(Clone, Copy, Debug, Default, Serialize, FromRow)]
+pub struct VoterRoll {
+    pub issued: i64,
+    pub used: i64,
+}
+
+/// A ranking with only the ids that are needed for running a poll. Items of a ballot with the
+/// same order share a rank.
 #[derive(Debug, FromRow)]
-pub struct JoinedRanking {
-    pub id: i32,
-    pub ord: i32,
+pub struct Ranking {
+    pub ballot_id: i32,
     pub item_id: i32,
-    pub item_title: String,
-    pub item_content: String,
-    pub item_done: bool,
+    pub ord: i32,
+}
+
+/// An item along with its order on a ballot.
+#[derive(Debug, FromRow)]
+pub struct RankedItem {
+    pub ord: i32,
+    #[sqlx(flatten)]
+    pub item: Item,
+}
+
+/// A score with only the ids that are needed for running a poll.
+#[derive(Debug, FromRow)]
+pub struct Score {
     pub ballot_id: i32,
-    pub ballot_uuid: Uuid,
+    pub item_id: i32,
+    pub score: i32,
 }
 
 #[derive(Debug)]
-pub struct Ranking {
+pub struct NewScore {
+    pub item_id: i32,
+    pub ballot_id: i32,
+    pub score: i32,
+}
+
+/// A submission of a ballot, which is never changed once it is stored.
+#[derive(Clone, Debug, Serialize, FromRow)]
+pub struct BallotRevision {
     pub id: i32,
-    pub ord: i32,
-    pub item: Item,
-    pub ballot: Ballot,
+    pub poll_id: i32,
+    pub ballot_id: i32,
+    /// Ids of the ranked items from the most preferred, separated by commas.
+    pub items: String,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    pub previous_hash: String,
+    pub hash: String,
 }
 
-impl From<JoinedRanking> for Ranking {
-    fn from(r: JoinedRanking) -> Self {
-        Ranking {
-            id: r.id,
-            ord: r.ord,
-            item: Item {
-                id: r.item_id,
-                title: r.item_title,
-                content: r.item_content,
-                done: r.item_done,
-            },
-            ballot: Ballot {
-                id: r.ballot_id,
-                uuid: r.ballot_uuid,
-            },
-        }
-    }
+#[derive(Debug)]
+pub struct NewBallotRevision {
+    pub poll_id: i32,
+    pub ballot_id: i32,
+    pub items: String,
+    pub created_at: OffsetDateTime,
+    pub previous_hash: String,
+    pub hash: String,
+}
+
+/// Result of checking the ballot revisions of a poll.
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditReport {
+    pub valid: bool,
+    pub revisions: usize,
+    /// Why the chain of revisions could not be verified.
+    pub chain_error: Option<String>,
+    /// The first revision that could not be verified.
+    pub broken_revision: Option<i32>,
+    /// Ballots whose rankings differ from their latest revision.
+    pub mismatched_ballots: Vec<i32>,
+    /// Ballots that have rankings but no revision, e.g. from before revisions were kept.
+    pub unrecorded_ballots: Vec<i32>,
+}
+
+/// The best items of a poll right after one of its ballots was changed.
+#[derive(Clone, Debug)]
+pub struct BestItemUpdate {
+    pub poll_id: i32,
+    /// The winner, or every elected item of a poll with more than one seat.
+    pub best_items: Vec<Item>,
+}
+
+/// Items sharing a place in the complete ranking of a poll.
+#[derive(Clone, Debug, Serialize)]
+pub struct Standing {
+    /// One more than the number of items ranked above, so tied items skip the following places.
+    pub place: usize,
+    pub items: Vec<Item>,
+    /// Whether the items are not on any ballot, they are placed last.
+    pub unranked: bool,
 }
 
 #[derive(Debug)]
@@ -61,3 +338,299 @@ pub struct NewRanking {
     pub item_id: i32,
     pub ballot_id: i32,
 }
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TallyResult {
+    NoWinner,
+    Tied(Vec<Item>),
+    Winner(Item),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TallyTransfer {
+    pub from: Item,
+    pub to: Option<Item>,
+    /// Ballots that were transferred, which can be fractions of ballots that were split between
+    /// equally ranked items.
+    pub ballots: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TallyRound {
+    pub counts: Vec<(Item, f64)>,
+    pub eliminated: Vec<Item>,
+    pub tie_break: Option<TieBreak>,
+    pub transfers: Vec<TallyTransfer>,
+    pub exhausted: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TallyTrace {
+    pub rounds: Vec<TallyRound>,
+    pub result: TallyResult,
+    pub tie_break: TieBreak,
+    pub seed: u64,
+}
+
+impl TallyTrace {
+    /// Create a trace from a poll that was run with the given tie breaker.
+    pub fn new<T>(trace: InstantRunoffVotingTrace<'_, T>, tie_breaker: &TieBreaker) -> Self
+    where
+        T: Borrow<Item>,
+    {
+        let rounds = trace
+            .rounds
+            .into_iter()
+            .map(|round| TallyRound {
+                counts: round
+                    .counts
+                    .into_iter()
+                    .map(|(item, count)| (item.borrow().clone(), ballot_value(count)))
+                    .collect(),
+                eliminated: round
+                    .eliminated
+                    .into_iter()
+                    .map(|item| item.borrow().clone())
+                    .collect(),
+                tie_break: round.tie_break,
+                transfers: round
+                    .transfers
+                    .into_iter()
+                    .map(|t| TallyTransfer {
+                        from: t.from.borrow().clone(),
+                        to: t.to.map(|item| item.borrow().clone()),
+                        ballots: ballot_value(t.value),
+                    })
+                    .collect(),
+                exhausted: ballot_value(round.exhausted),
+            })
+            .collect();
+        TallyTrace {
+            rounds,
+            result: VotingResult::from(trace.result).into(),
+            tie_break: tie_breaker.rule(),
+            seed: tie_breaker.seed(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoreTally {
+    pub totals: Vec<(Item, u32)>,
+    /// Number of ballots preferring each finalist of a STAR poll.
+    pub runoff: Vec<(Item, u32)>,
+    pub tie_break: Option<TieBreak>,
+    pub result: TallyResult,
+}
+
+impl<'a, T> From<ScoreVotingTrace<'a, T>> for ScoreTally
+where
+    T: Borrow<Item>,
+{
+    fn from(trace: ScoreVotingTrace<'a, T>) -> Self {
+        let counts = |counts: Vec<(&T, u32)>| -> Vec<(Item, u32)> {
+            counts
+                .into_iter()
+                .map(|(item, count)| (item.borrow().clone(), count))
+                .collect()
+        };
+        ScoreTally {
+            totals: counts(trace.totals),
+            runoff: counts(trace.runoff),
+            tie_break: trace.tie_break,
+            result: trace.result.into(),
+        }
+    }
+}
+
+/// Value of ballots that can be split or transferred as fractions, a full ballot is worth 1.
+fn ballot_value(value: u64) -> f64 {
+    value as f64 / BALLOT_VALUE as f64
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ElectionTransfer {
+    pub from: Item,
+    pub to: Option<Item>,
+    pub votes: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ElectionRound {
+    pub counts: Vec<(Item, f64)>,
+    pub elected: Vec<Item>,
+    pub eliminated: Vec<Item>,
+    pub tie_break: Option<TieBreak>,
+    pub transfers: Vec<ElectionTransfer>,
+    pub exhausted: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ElectionTrace {
+    pub seats: usize,
+    pub quota: f64,
+    pub rounds: Vec<ElectionRound>,
+    pub elected: Vec<Item>,
+    pub tied: Vec<Item>,
+    pub tie_break: TieBreak,
+    pub seed: u64,
+}
+
+impl ElectionTrace {
+    /// Create a trace from a poll with several seats that was run with the given tie breaker.
+    pub fn new<T>(trace: SingleTransferableVoteTrace<'_, T>, tie_breaker: &TieBreaker) -> Self
+    where
+        T: Borrow<Item>,
+    {
+        let items = |items: Vec<&T>| -> Vec<Item> {
+            items
+                .into_iter()
+                .map(|item| item.borrow().clone())
+                .collect()
+        };
+        let rounds = trace
+            .rounds
+            .into_iter()
+            .map(|round| ElectionRound {
+                counts: round
+                    .counts
+                    .into_iter()
+                    .map(|(item, value)| (item.borrow().clone(), ballot_value(value)))
+                    .collect(),
+                elected: items(round.elected),
+                eliminated: items(round.eliminated),
+                tie_break: round.tie_break,
+                transfers: round
+                    .transfers
+                    .into_iter()
+                    .map(|t| ElectionTransfer {
+                        from: t.from.borrow().clone(),
+                        to: t.to.map(|item| item.borrow().clone()),
+                        votes: ballot_value(t.value),
+                    })
+                    .collect(),
+                exhausted: ballot_value(round.exhausted),
+            })
+            .collect();
+        ElectionTrace {
+            seats: trace.seats,
+            quota: ballot_value(trace.quota),
+            rounds,
+            elected: items(trace.elected),
+            tied: items(trace.tied),
+            tie_break: tie_breaker.rule(),
+            seed: tie_breaker.seed(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PairwiseComparison {
+    pub items: Vec<Item>,
+    pub preferences: Vec<Vec<u32>>,
+    pub condorcet_winner: Option<Item>,
+    pub condorcet_loser: Option<Item>,
+    pub smith_set: Vec<Item>,
+}
+
+impl<'a, T> From<PairwiseMatrix<'a, T>> for PairwiseComparison
+where
+    T: Borrow<Item> + Eq + Hash,
+{
+    fn from(matrix: PairwiseMatrix<'a, T>) -> Self {
+        PairwiseComparison {
+            items: matrix
+                .candidates()
+                .iter()
+                .map(|item| item.borrow().clone())
+                .collect(),
+            preferences: matrix.preferences().to_vec(),
+            condorcet_winner: matrix.condorcet_winner().map(|item| item.borrow().clone()),
+            condorcet_loser: matrix.condorcet_loser().map(|item| item.borrow().clone()),
+            smith_set: matrix
+                .smith_set()
+                .into_iter()
+                .map(|item| item.borrow().clone())
+                .collect(),
+        }
+    }
+}
+
+impl<'a, T> From<VotingResult<'a, T>> for TallyResult
+where
+    T: Borrow<Item>,
+{
+    fn from(result: VotingResult<'a, T>) -> Self {
+        match result {
+            VotingResult::NoWinner => TallyResult::NoWinner,
+            VotingResult::Tied(items) => TallyResult::Tied(
+                items
+                    .into_iter()
+                    .map(|item| item.borrow().clone())
+                    .collect(),
+            ),
+            VotingResult::Winner(item) => TallyResult::Winner(item.borrow().clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    fn poll(
+        status: PollStatus,
+        opens_at: Option<OffsetDateTime>,
+        closes_at: Option<OffsetDateTime>,
+    ) -> Poll {
+        Poll {
+            id: 1,
+            title: String::new(),
+            description: String::new(),
+            status,
+            opens_at,
+            closes_at,
+            seats: 1,
+            ballot_type: BallotType::Ranked,
+        }
+    }
+
+    #[test]
+    fn poll_status_follows_schedule() {
+        let opens_at = Some(datetime!(2026-10-01 09:00 UTC));
+        let closes_at = Some(datetime!(2026-10-31 17:00 UTC));
+        let before = datetime!(2026-09-30 12:00 UTC);
+        let during = datetime!(2026-10-18 12:00 UTC);
+        let after = datetime!(2026-11-01 12:00 UTC);
+
+        let scheduled = poll(PollStatus::Draft, opens_at, closes_at);
+        assert_eq!(scheduled.status_at(before), PollStatus::Draft);
+        assert_eq!(scheduled.status_at(during), PollStatus::Open);
+        assert_eq!(scheduled.status_at(after), PollStatus::Closed);
+
+        // Opening or closing a poll by hand does not wait for its schedule
+        let opened = poll(PollStatus::Open, opens_at, closes_at);
+        assert_eq!(opened.status_at(before), PollStatus::Open);
+        let closed = poll(PollStatus::Closed, opens_at, closes_at);
+        assert_eq!(closed.status_at(during), PollStatus::Closed);
+
+        assert_eq!(
+            poll(PollStatus::Draft, None, None).status_at(after),
+            PollStatus::Draft
+        );
+    }
+
+    #[test]
+    fn poll_method_follows_ballot_type_and_seats() {
+        let mut poll = poll(PollStatus::Open, None, None);
+        assert_eq!(poll.method(Method::Schulze), ("Schulze method", "schulze"));
+        poll.seats = 3;
+        assert_eq!(poll.elected_seats(), 3);
+        assert_eq!(poll.method(Method::Schulze).1, "single_transferable_vote");
+        poll.ballot_type = BallotType::Star;
+        assert_eq!(poll.elected_seats(), 1);
+        assert_eq!(poll.method(Method::Schulze), ("STAR voting", "star"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Every distinct item found on the ballots, in the order they first appear.
+pub struct Candidates<'a, T> {
+    items: Vec<&'a T>,
+    indices: HashMap<&'a T, usize>,
+}
+
+impl<'a, T> Candidates<'a, T>
+where
+    T: Eq + Hash,
+{
+    pub fn new(ballots: &'a [&'a [Vec<T>]]) -> Self {
+        let mut items = Vec::new();
+        let mut indices = HashMap::new();
+        for &ballot in ballots {
+            for item in ballot.iter().flatten() {
+                indices.entry(item).or_insert_with(|| {
+                    items.push(item);
+                    items.len() - 1
+                });
+            }
+        }
+        Self { items, indices }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn get(&self, index: usize) -> &'a T {
+        self.items[index]
+    }
+
+    pub fn index_of(&self, item: &T) -> usize {
+        self.indices[item]
+    }
+
+    /// The index of an item, `None` if it is not on any ballot.
+    pub fn position(&self, item: &T) -> Option<usize> {
+        self.indices.get(item).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a T> + '_ {
+        self.items.iter().copied()
+    }
+}
+
+/// Head-to-head comparison between every pair of items found on the ballots. Items that are
+/// listed on a ballot are preferred to the items that are not listed, and a ballot has no
+/// preference between items that share a rank or that are both not listed.
+pub struct PairwiseMatrix<'a, T> {
+    candidates: Candidates<'a, T>,
+    preferences: Vec<Vec<u32>>,
+}
+
+impl<'a, T> PairwiseMatrix<'a, T>
+where
+    T: Eq + Hash,
+{
+    pub fn new(ballots: &'a [&'a [Vec<T>]]) -> Self {
+        let candidates = Candidates::new(ballots);
+        let n = candidates.len();
+        let mut preferences = vec![vec![0; n]; n];
+        for &ballot in ballots {
+            let mut ranked = vec![false; n];
+            for rank in ballot {
+                // Items of the rank are preferred to every item that is ranked lower
+                let mut indices: Vec<_> = rank
+                    .iter()
+                    .map(|item| candidates.index_of(item))
+                    .filter(|&i| !ranked[i])
+                    .collect();
+                indices.sort_unstable();
+                indices.dedup();
+                for &i in &indices {
+                    ranked[i] = true;
+                }
+                for &i in &indices {
+                    for (j, &was_ranked) in ranked.iter().enumerate() {
+                        if !was_ranked {
+                            preferences[i][j] += 1;
+                        }
+                    }
+                }
+            }
+        }
+        Self {
+            candidates,
+            preferences,
+        }
+    }
+
+    pub fn candidates(&self) -> &Candidates<'a, T> {
+        &self.candidates
+    }
+
+    /// The number of ballots preferring one candidate to another for every pair of candidates.
+    pub fn preferences(&self) -> &[Vec<u32>] {
+        &self.preferences
+    }
+
+    /// Check if the `i`-th candidate is preferred to the `j`-th candidate by more ballots.
+    pub fn beats(&self, i: usize, j: usize) -> bool {
+        self.preferences[i][j] > self.preferences[j][i]
+    }
+
+    /// The item that beats every other item head to head.
+    pub fn condorcet_winner(&self) -> Option<&'a T> {
+        let n = self.candidates.len();
+        (0..n)
+            .find(|&i| (0..n).all(|j| i == j || self.beats(i, j)))
+            .map(|i| self.candidates.get(i))
+    }
+
+    /// The item that is beaten by every other item head to head.
+    pub fn condorcet_loser(&self) -> Option<&'a T> {
+        let n = self.candidates.len();
+        (0..n)
+            .find(|&i| (0..n).all(|j| i == j || self.beats(j, i)))
+            .map(|i| self.candidates.get(i))
+    }
+
+    /// The smallest non-empty set of items where every member beats every item outside of the set.
+    pub fn smith_set(&self) -> Vec<&'a T> {
+        let n = self.candidates.len();
+        // The Smith set contains the items that can reach every other item through a chain of
+        // wins or ties.
+        let mut reach: Vec<Vec<bool>> = (0..n)
+            .map(|i| (0..n).map(|j| i == j || !self.beats(j, i)).collect())
+            .collect();
+        for k in 0..n {
+            let through_k = reach[k].clone();
+            for row in reach.iter_mut().filter(|row| row[k]) {
+                for (r, &t) in row.iter_mut().zip(through_k.iter()) {
+                    *r |= t;
+                }
+            }
+        }
+        (0..n)
+            .filter(|&i| reach[i].iter().all(|&r| r))
+            .map(|i| self.candidates.get(i))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::strict;
+
+    #[test]
+    fn pairwise_condorcet_winner_and_loser() {
+        let vote_a = strict(&["bob", "bill", "sue"]);
+        let vote_b = strict(&["sue", "bob", "bill"]);
+        let vote_c = strict(&["bill", "bob"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice(), vote_c.as_slice()];
+        let matrix = PairwiseMatrix::new(&votes);
+        let bob = matrix.candidates().index_of(&"bob");
+        let sue = matrix.candidates().index_of(&"sue");
+        assert_eq!(matrix.preferences()[bob][sue], 2);
+        assert_eq!(matrix.preferences()[sue][bob], 1);
+        assert_eq!(matrix.condorcet_winner(), Some(&"bob"));
+        assert_eq!(matrix.condorcet_loser(), Some(&"sue"));
+        assert_eq!(matrix.smith_set(), vec![&"bob"]);
+    }
+
+    #[test]
+    fn pairwise_cycle() {
+        let vote_a = strict(&["a", "b", "c"]);
+        let vote_b = strict(&["b", "c", "a"]);
+        let vote_c = strict(&["c", "a", "b"]);
+        let vote_d = strict(&["d"]);
+
+        let votes = vec![
+            vote_a.as_slice(),
+            vote_b.as_slice(),
+            vote_c.as_slice(),
+            vote_d.as_slice(),
+        ];
+        let matrix = PairwiseMatrix::new(&votes);
+        assert_eq!(matrix.condorcet_winner(), None);
+        assert_eq!(matrix.condorcet_loser(), Some(&"d"));
+
+        let smith_set = matrix.smith_set();
+        assert_eq!(smith_set.len(), 3);
+        assert!(!smith_set.contains(&&"d"));
+    }
+
+    #[test]
+    fn pairwise_no_vote() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        let matrix = PairwiseMatrix::new(&votes);
+        assert_eq!(matrix.condorcet_winner(), None);
+        assert_eq!(matrix.condorcet_loser(), None);
+        assert!(matrix.smith_set().is_empty());
+    }
+
+    #[test]
+    fn pairwise_equal_ranks_and_unranked_items() {
+        let vote_a = vec![vec!["bob", "sue"], vec!["bill"]];
+        let vote_b = strict(&["bill", "bob"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        let matrix = PairwiseMatrix::new(&votes);
+        let bob = matrix.candidates().index_of(&"bob");
+        let sue = matrix.candidates().index_of(&"sue");
+        let bill = matrix.candidates().index_of(&"bill");
+        // Items sharing a rank are not preferred to each other, unranked items are last.
+        assert_eq!(matrix.preferences()[bob][sue], 1);
+        assert_eq!(matrix.preferences()[sue][bob], 0);
+        assert_eq!(matrix.preferences()[bob][bill], 1);
+        assert_eq!(matrix.preferences()[bill][bob], 1);
+        assert_eq!(matrix.preferences()[sue][bill], 1);
+        assert_eq!(matrix.preferences()[bill][sue], 1);
+    }
+}
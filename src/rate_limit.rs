@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::HttpRequest;
+
+use crate::conf::RateLimitConfiguration;
+
+/// Number of tracked addresses above which the expired ones are dropped.
+const PRUNE_THRESHOLD: usize = 1024;
+
+/// Allow a number of requests from every IP address in each fixed window of time.
+pub struct RateLimiter {
+    requests: u32,
+    period: Duration,
+    windows: Mutex<HashMap<IpAddr, Window>>,
+}
+
+struct Window {
+    started_at: Instant,
+    requests: u32,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing the given number of requests per period, there is no limit when
+    /// the number is 0.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        Self {
+            requests,
+            period,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether requests are let through without counting them.
+    pub fn is_unlimited(&self) -> bool {
+        self.requests == 0
+    }
+
+    /// Count a request from the address, returns `false` if it goes over the limit.
+    pub fn check(&self, address: IpAddr, now: Instant) -> bool {
+        if self.requests == 0 {
+            return true;
+        }
+        let mut windows = self.windows.lock().expect("rate limiter lock is poisoned");
+        if windows.len() >= PRUNE_THRESHOLD {
+            windows.retain(|_, w| now.duration_since(w.started_at) < self.period);
+        }
+        let window = windows.entry(address).or_insert(Window {
+            started_at: now,
+            requests: 0,
+        });
+        if now.duration_since(window.started_at) >= self.period {
+            window.started_at = now;
+            window.requests = 0;
+        }
+        if window.requests >= self.requests {
+            return false;
+        }
+        window.requests += 1;
+        true
+    }
+}
+
+/// Limit on the number of requests from the address of their client.
+pub struct AddressLimit {
+    limiter: RateLimiter,
+    use_forwarded_for: bool,
+}
+
+impl AddressLimit {
+    pub fn new(config: &RateLimitConfiguration) -> Self {
+        Self {
+            limiter: RateLimiter::new(config.requests(), config.period()),
+            use_forwarded_for: config.use_forwarded_for(),
+        }
+    }
+
+    /// Count the request, returns `false` if there were too many from its address.
+    pub fn check(&self, request: &HttpRequest) -> bool {
+        let forwarded = if self.use_forwarded_for {
+            request
+                .connection_info()
+                .realip_remote_addr()
+                .and_then(parse_address)
+        } else {
+            None
+        };
+        match forwarded.or_else(|| request.peer_addr().map(|a| a.ip())) {
+            Some(address) => self.limiter.check(address, Instant::now()),
+            // Requests without a known address can not be told apart, so they are refused
+            // rather than let through without a limit.
+            None => self.limiter.is_unlimited(),
+        }
+    }
+}
+
+/// Parse the address of a client given by a proxy, which may come with a port and with the
+/// brackets around an IPv6 address, such as `203.0.113.7:4711` or `[2001:db8::1]:4711`.
+fn parse_address(address: &str) -> Option<IpAddr> {
+    address
+        .parse::<IpAddr>()
+        .or_else(|_| address.parse::<SocketAddr>().map(|a| a.ip()))
+        .ok()
+        .or_else(|| {
+            address
+                .strip_prefix('[')?
+                .strip_suffix(']')?
+                .parse::<IpAddr>()
+                .ok()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::header, test::TestRequest};
+
+    use super::*;
+
+    fn limit(requests: u32) -> AddressLimit {
+        AddressLimit {
+            limiter: RateLimiter::new(requests, Duration::from_secs(60)),
+            use_forwarded_for: true,
+        }
+    }
+
+    #[test]
+    fn rate_limiter_resets_after_period() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let alice: IpAddr = "10.0.0.1".parse().unwrap();
+        let bob: IpAddr = "10.0.0.2".parse().unwrap();
+        let start = Instant::now();
+
+        assert!(limiter.check(alice, start));
+        assert!(limiter.check(alice, start + Duration::from_secs(1)));
+        assert!(!limiter.check(alice, start + Duration::from_secs(2)));
+        assert!(limiter.check(bob, start + Duration::from_secs(2)));
+        assert!(limiter.check(alice, start + Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn rate_limiter_without_limit() {
+        let limiter = RateLimiter::new(0, Duration::from_secs(60));
+        let alice: IpAddr = "10.0.0.1".parse().unwrap();
+        let now = Instant::now();
+        assert!((0..100).all(|_| limiter.check(alice, now)));
+    }
+
+    #[test]
+    fn forwarded_addresses_are_parsed_with_ports_and_brackets() {
+        let expected: IpAddr = "203.0.113.7".parse().unwrap();
+        assert_eq!(parse_address("203.0.113.7"), Some(expected));
+        assert_eq!(parse_address("203.0.113.7:4711"), Some(expected));
+        let expected: IpAddr = "2001:db8::1".parse().unwrap();
+        assert_eq!(parse_address("2001:db8::1"), Some(expected));
+        assert_eq!(parse_address("[2001:db8::1]"), Some(expected));
+        assert_eq!(parse_address("[2001:db8::1]:4711"), Some(expected));
+        assert_eq!(parse_address("unknown"), None);
+        assert_eq!(parse_address("_hidden"), None);
+    }
+
+    #[test]
+    fn forwarded_addresses_with_ports_share_a_limit() {
+        let limit = limit(1);
+        let request = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=\"[2001:db8::1]:4711\""))
+            .to_http_request();
+        assert!(limit.check(&request));
+        let request = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=\"[2001:db8::1]:4712\""))
+            .to_http_request();
+        assert!(!limit.check(&request));
+    }
+
+    #[test]
+    fn malformed_forwarded_header_falls_back_to_the_peer_address() {
+        let limit = limit(1);
+        let peer: SocketAddr = "198.51.100.1:4711".parse().unwrap();
+        let request = |forwarded| {
+            TestRequest::default()
+                .insert_header((header::FORWARDED, forwarded))
+                .peer_addr(peer)
+                .to_http_request()
+        };
+        assert!(limit.check(&request("for=unknown")));
+        // Both requests are counted against the peer, so a malformed header does not bypass it
+        assert!(!limit.check(&request("for=_hidden")));
+    }
+
+    #[test]
+    fn requests_without_an_address_are_refused() {
+        let request = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=unknown"))
+            .to_http_request();
+        assert!(!limit(10).check(&request));
+        assert!(limit(0).check(&request));
+    }
+}
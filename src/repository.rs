@@ -1,42 +1,163 @@
-pub mod ballot;
-pub mod item;
-pub mod ranking;
+pub mod memory;
+pub mod mysql;
+pub mod postgres;
+mod sql;
+pub mod sqlite;
+#[cfg(test)]
+mod tests;
 
 use async_trait::async_trait;
+use time::OffsetDateTime;
 use uuid::Uuid;
 
-use crate::model::{Ballot, Item, NewRanking, Ranking};
-
-const BIND_LIMIT: usize = 1 << 16;
+use crate::model::{
+    Ballot, BallotRevision, BallotType, Item, NewBallotRevision, NewItem, NewPoll, NewRanking,
+    NewScore, Poll, PollSnapshot, PollStatus, RankedItem, Ranking, Score, VoterRoll,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum RepositoryError {
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
+
+    #[error("Constraint violated: {0}")]
+    Constraint(&'static str),
 }
 
 #[async_trait]
 pub trait Transact {
-    type Txn<'a>: Send + Sync;
+    type Txn<'a>: Send;
 
-    async fn begin(&self) -> Result<Self::Txn<'_>, RepositoryError>;
+    async fn begin<'a>(&'a self) -> Result<Self::Txn<'a>, RepositoryError>;
     async fn end(&self, txn: Self::Txn<'_>) -> Result<(), RepositoryError>;
 }
 
+#[async_trait]
+pub trait PollRepository: Clone + Send + Sync {
+    /// Check that the database can be reached.
+    async fn ping(&self) -> Result<(), RepositoryError>;
+
+    /// Get all polls ordered by their creation.
+    async fn find_all(&self) -> Result<Vec<Poll>, RepositoryError>;
+
+    /// Find a poll with the given id.
+    async fn find_by_id(&self, id: i32) -> Result<Option<Poll>, RepositoryError>;
+
+    /// Create a new draft poll that elects a single item with ranked ballots, returns its id.
+    async fn create(&self, poll: NewPoll) -> Result<i32, RepositoryError>;
+
+    /// Change the status and the scheduled times of a poll, returns `false` if the poll was not
+    /// found.
+    async fn update_lifecycle(
+        &self,
+        id: i32,
+        status: PollStatus,
+        opens_at: Option<OffsetDateTime>,
+        closes_at: Option<OffsetDateTime>,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Change the number of items that are elected, returns `false` if the poll was not found.
+    async fn update_seats(&self, id: i32, seats: i32) -> Result<bool, RepositoryError>;
+
+    /// Change how the ballots of a poll are filled in, returns `false` if the poll was not found.
+    async fn update_ballot_type(
+        &self,
+        id: i32,
+        ballot_type: BallotType,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Find the final tally of a closed poll.
+    async fn find_snapshot(&self, poll_id: i32) -> Result<Option<PollSnapshot>, RepositoryError>;
+
+    /// Store the final tally of a closed poll. Nothing is changed if the poll already has one.
+    async fn create_snapshot(&self, snapshot: &PollSnapshot) -> Result<(), RepositoryError>;
+}
+
 #[async_trait]
 pub trait ItemRepository: Clone + Send + Sync {
-    async fn find_ranked_by_ballot(&self, ballot_id: i32) -> Result<Vec<Item>, RepositoryError>;
+    /// Find the active items ranked on a ballot, ordered by their rank and then their position.
+    async fn find_ranked_by_ballot(
+        &self,
+        ballot_id: i32,
+    ) -> Result<Vec<RankedItem>, RepositoryError>;
+
+    async fn find_unranked_by_ballot(
+        &self,
+        poll_id: i32,
+        ballot_id: i32,
+    ) -> Result<Vec<Item>, RepositoryError>;
+
+    /// Get every item of the poll, including the ones that are done or retired, ordered by
+    /// their position.
+    async fn find_by_poll(&self, poll_id: i32) -> Result<Vec<Item>, RepositoryError>;
+
+    /// Create a new item that is placed after every existing item of the poll.
+    async fn create(&self, item: NewItem) -> Result<(), RepositoryError>;
 
-    async fn find_unranked_by_ballot(&self, ballot_id: i32) -> Result<Vec<Item>, RepositoryError>;
+    /// Change the title and the content of an item, returns `false` if the item was not found.
+    async fn update_content(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        title: &str,
+        content: &str,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Place the items of the poll in the given order.
+    ///
+    /// Callers must make sure that the slice is not empty.
+    async fn update_positions(&self, poll_id: i32, item_ids: &[i32])
+        -> Result<(), RepositoryError>;
+
+    /// Mark an item as done or not done, returns `false` if the item was not found.
+    async fn update_done(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        done: bool,
+    ) -> Result<bool, RepositoryError>;
+
+    /// Mark an item as retired or not retired, returns `false` if the item was not found.
+    async fn update_retired(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        retired: bool,
+    ) -> Result<bool, RepositoryError>;
 }
 
 #[async_trait]
 pub trait BallotRepository: Clone + Send + Sync {
-    /// Find a ballot with the given UUID.
-    async fn find_by_uuid(&self, uuid: Uuid) -> Result<Option<Ballot>, RepositoryError>;
+    /// Find a ballot of the poll with the given UUID.
+    async fn find_by_uuid(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+    ) -> Result<Option<Ballot>, RepositoryError>;
+
+    /// Create a new ballot of the poll with the given UUID and do nothing if the UUID already
+    /// exists in the poll.
+    async fn save_ignoring_conflict(&self, poll_id: i32, uuid: Uuid)
+        -> Result<(), RepositoryError>;
+
+    /// Store the hashes of new voter tokens of a poll.
+    async fn create_voter_tokens(
+        &self,
+        poll_id: i32,
+        token_hashes: &[String],
+    ) -> Result<(), RepositoryError>;
+
+    /// Mark an unused voter token of the poll as used and create a ballot with the given UUID,
+    /// as a single change. Returns `false` and changes nothing if there is no such token.
+    async fn save_with_voter_token(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+        token_hash: &str,
+    ) -> Result<bool, RepositoryError>;
 
-    /// Create a new ballot with the given UUID and do nothing if the UUID already exists.
-    async fn save_ignoring_conflict(&self, uuid: Uuid) -> Result<(), RepositoryError>;
+    /// Count the issued and the used voter tokens of a poll.
+    async fn count_voter_tokens(&self, poll_id: i32) -> Result<VoterRoll, RepositoryError>;
 }
 
 #[async_trait]
@@ -57,9 +178,52 @@ pub trait TransactableRankingRepository: Transact + RankingRepository {
         txn: &mut Self::Txn<'_>,
         ballot_id: i32,
     ) -> Result<(), RepositoryError>;
+
+    /// Takes new scores from the iterator and insert them into the repository.
+    ///
+    /// Callers must make sure that the iterator is not empty.
+    async fn txn_create_score_bulk<I>(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        scores: &mut I,
+    ) -> Result<(), RepositoryError>
+    where
+        I: Iterator<Item = NewScore> + Send;
+
+    async fn txn_remove_ballot_scores(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        ballot_id: i32,
+    ) -> Result<(), RepositoryError>;
+
+    /// Get the hash of the latest revision of the poll's ballots.
+    async fn txn_find_last_revision_hash(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        poll_id: i32,
+    ) -> Result<Option<String>, RepositoryError>;
+
+    /// Store a new ballot revision. Revisions are never changed nor removed.
+    async fn txn_create_revision(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        revision: NewBallotRevision,
+    ) -> Result<(), RepositoryError>;
 }
 
 #[async_trait]
 pub trait RankingRepository: Clone + Send + Sync {
-    async fn get_all(&self) -> Result<Vec<Ranking>, RepositoryError>;
+    /// Get the ids of all rankings of the poll sorted by ballot id and ranking order. Rankings of
+    /// items that are done or retired are included.
+    async fn get_by_poll(&self, poll_id: i32) -> Result<Vec<Ranking>, RepositoryError>;
+
+    /// Get all scores of the poll sorted by ballot id and item id. Scores of items that are done
+    /// or retired are included.
+    async fn get_scores_by_poll(&self, poll_id: i32) -> Result<Vec<Score>, RepositoryError>;
+
+    /// Get all ballot revisions of the poll in the order they were created.
+    async fn get_revisions_by_poll(
+        &self,
+        poll_id: i32,
+    ) -> Result<Vec<BallotRevision>, RepositoryError>;
 }
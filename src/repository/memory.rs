@@ -0,0 +1,98 @@
+//! Repositories that keep the data in memory, which is lost when the application stops.
+
+use std::sync::Arc;
+
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::model::{Ballot, BallotRevision, BallotType, Item, Poll, PollSnapshot, PollStatus};
+
+pub mod ballot;
+pub mod item;
+pub mod poll;
+pub mod ranking;
+
+/// The data shared by all in-memory repositories that are created from the same store.
+#[derive(Clone)]
+pub struct Store {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for Store {
+    /// Create a store with one empty poll, like the migrations of the other databases.
+    fn default() -> Self {
+        let state = State {
+            polls: vec![Poll {
+                id: 1,
+                title: "Poll".to_string(),
+                description: String::new(),
+                status: PollStatus::Open,
+                opens_at: None,
+                closes_at: None,
+                seats: 1,
+                ballot_type: BallotType::Ranked,
+            }],
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+}
+
+impl Store {
+    async fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().await
+    }
+}
+
+#[derive(Clone, Default)]
+struct State {
+    polls: Vec<Poll>,
+    snapshots: Vec<PollSnapshot>,
+    items: Vec<StoredItem>,
+    ballots: Vec<Ballot>,
+    rankings: Vec<StoredRanking>,
+    scores: Vec<StoredScore>,
+    revisions: Vec<BallotRevision>,
+    voter_tokens: Vec<StoredVoterToken>,
+}
+
+#[derive(Clone)]
+struct StoredItem {
+    poll_id: i32,
+    item: Item,
+}
+
+#[derive(Clone)]
+struct StoredRanking {
+    ord: i32,
+    item_id: i32,
+    ballot_id: i32,
+}
+
+#[derive(Clone)]
+struct StoredScore {
+    item_id: i32,
+    ballot_id: i32,
+    score: i32,
+}
+
+#[derive(Clone)]
+struct StoredVoterToken {
+    poll_id: i32,
+    token_hash: String,
+    used: bool,
+}
+
+/// A transaction holds the store for itself and works on a copy of its data. The copy replaces
+/// the data of the store when the transaction ends, and is discarded if the transaction is
+/// dropped before that.
+pub struct Transaction<'a> {
+    state: MutexGuard<'a, State>,
+    working: State,
+}
+
+/// Ids are generated like auto-incremented columns, one greater than the current maximum.
+fn next_id(ids: impl Iterator<Item = i32>) -> i32 {
+    ids.max().unwrap_or(0) + 1
+}
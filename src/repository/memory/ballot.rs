@@ -0,0 +1,121 @@
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::{
+    model::{Ballot, VoterRoll},
+    repository::{self, RepositoryError},
+};
+
+use super::{next_id, Store, StoredVoterToken};
+
+#[derive(Clone)]
+pub struct BallotRepository {
+    store: Store,
+}
+
+impl BallotRepository {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl repository::BallotRepository for BallotRepository {
+    #[tracing::instrument(skip(self))]
+    async fn find_by_uuid(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+    ) -> Result<Option<Ballot>, RepositoryError> {
+        let state = self.store.lock().await;
+        Ok(state
+            .ballots
+            .iter()
+            .find(|b| b.poll_id == poll_id && b.uuid == uuid)
+            .cloned())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn save_ignoring_conflict(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.store.lock().await;
+        if !state.polls.iter().any(|p| p.id == poll_id) {
+            return Err(RepositoryError::Constraint("ballot must belong to a poll"));
+        }
+        if !state
+            .ballots
+            .iter()
+            .any(|b| b.poll_id == poll_id && b.uuid == uuid)
+        {
+            let id = next_id(state.ballots.iter().map(|b| b.id));
+            state.ballots.push(Ballot { id, poll_id, uuid });
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, token_hashes), fields(tokens = token_hashes.len()))]
+    async fn create_voter_tokens(
+        &self,
+        poll_id: i32,
+        token_hashes: &[String],
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.store.lock().await;
+        if token_hashes
+            .iter()
+            .any(|h| state.voter_tokens.iter().any(|t| &t.token_hash == h))
+        {
+            return Err(RepositoryError::Constraint("voter tokens must be unique"));
+        }
+        state
+            .voter_tokens
+            .extend(token_hashes.iter().map(|token_hash| StoredVoterToken {
+                poll_id,
+                token_hash: token_hash.clone(),
+                used: false,
+            }));
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, token_hash))]
+    async fn save_with_voter_token(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+        token_hash: &str,
+    ) -> Result<bool, RepositoryError> {
+        let mut state = self.store.lock().await;
+        if !state.polls.iter().any(|p| p.id == poll_id) {
+            return Err(RepositoryError::Constraint("ballot must belong to a poll"));
+        }
+        let Some(token) = state
+            .voter_tokens
+            .iter_mut()
+            .find(|t| t.poll_id == poll_id && t.token_hash == token_hash && !t.used)
+        else {
+            return Ok(false);
+        };
+        token.used = true;
+        if !state
+            .ballots
+            .iter()
+            .any(|b| b.poll_id == poll_id && b.uuid == uuid)
+        {
+            let id = next_id(state.ballots.iter().map(|b| b.id));
+            state.ballots.push(Ballot { id, poll_id, uuid });
+        }
+        Ok(true)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn count_voter_tokens(&self, poll_id: i32) -> Result<VoterRoll, RepositoryError> {
+        let state = self.store.lock().await;
+        let tokens = state.voter_tokens.iter().filter(|t| t.poll_id == poll_id);
+        Ok(VoterRoll {
+            issued: tokens.clone().count() as i64,
+            used: tokens.filter(|t| t.used).count() as i64,
+        })
+    }
+}
@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+
+use crate::{
+    model::{Item, NewItem, RankedItem},
+    repository::{self, RepositoryError},
+};
+
+use super::{next_id, State, Store, StoredItem};
+
+#[derive(Clone)]
+pub struct ItemRepository {
+    store: Store,
+}
+
+impl ItemRepository {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+impl State {
+    fn poll_item_mut(&mut self, poll_id: i32, item_id: i32) -> Option<&mut Item> {
+        self.items
+            .iter_mut()
+            .find(|i| i.poll_id == poll_id && i.item.id == item_id)
+            .map(|i| &mut i.item)
+    }
+}
+
+/// Items ordered by their position as the other databases do.
+fn sorted_by_position(mut items: Vec<Item>) -> Vec<Item> {
+    items.sort_by_key(|i| (i.position, i.id));
+    items
+}
+
+#[async_trait]
+impl repository::ItemRepository for ItemRepository {
+    #[tracing::instrument(skip(self))]
+    async fn find_ranked_by_ballot(
+        &self,
+        ballot_id: i32,
+    ) -> Result<Vec<RankedItem>, RepositoryError> {
+        let state = self.store.lock().await;
+        let mut items: Vec<_> = state
+            .rankings
+            .iter()
+            .filter(|r| r.ballot_id == ballot_id)
+            .filter_map(|r| {
+                let i = state.items.iter().find(|i| i.item.id == r.item_id)?;
+                Some(RankedItem {
+                    ord: r.ord,
+                    item: i.item.clone(),
+                })
+            })
+            .filter(|r| !r.item.done && !r.item.retired)
+            .collect();
+        items.sort_by_key(|r| (r.ord, r.item.position));
+        Ok(items)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_unranked_by_ballot(
+        &self,
+        poll_id: i32,
+        ballot_id: i32,
+    ) -> Result<Vec<Item>, RepositoryError> {
+        let state = self.store.lock().await;
+        let items = state
+            .items
+            .iter()
+            .filter(|i| i.poll_id == poll_id && !i.item.done && !i.item.retired)
+            .filter(|i| {
+                !state
+                    .rankings
+                    .iter()
+                    .any(|r| r.ballot_id == ballot_id && r.item_id == i.item.id)
+            })
+            .map(|i| i.item.clone())
+            .collect();
+        Ok(sorted_by_position(items))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_by_poll(&self, poll_id: i32) -> Result<Vec<Item>, RepositoryError> {
+        let state = self.store.lock().await;
+        let items = state
+            .items
+            .iter()
+            .filter(|i| i.poll_id == poll_id)
+            .map(|i| i.item.clone())
+            .collect();
+        Ok(sorted_by_position(items))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn create(&self, item: NewItem) -> Result<(), RepositoryError> {
+        let mut state = self.store.lock().await;
+        if !state.polls.iter().any(|p| p.id == item.poll_id) {
+            return Err(RepositoryError::Constraint("item must belong to a poll"));
+        }
+        let id = next_id(state.items.iter().map(|i| i.item.id));
+        let position = state
+            .items
+            .iter()
+            .filter(|i| i.poll_id == item.poll_id)
+            .map(|i| i.item.position)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        state.items.push(StoredItem {
+            poll_id: item.poll_id,
+            item: Item {
+                id,
+                title: item.title,
+                content: item.content,
+                done: false,
+                position,
+                retired: false,
+            },
+        });
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_content(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        title: &str,
+        content: &str,
+    ) -> Result<bool, RepositoryError> {
+        let mut state = self.store.lock().await;
+        Ok(match state.poll_item_mut(poll_id, item_id) {
+            Some(item) => {
+                item.title = title.to_string();
+                item.content = content.to_string();
+                true
+            }
+            None => false,
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_positions(
+        &self,
+        poll_id: i32,
+        item_ids: &[i32],
+    ) -> Result<(), RepositoryError> {
+        let mut state = self.store.lock().await;
+        for (position, item_id) in item_ids.iter().enumerate() {
+            if let Some(item) = state.poll_item_mut(poll_id, *item_id) {
+                item.position = position as i32;
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_done(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        done: bool,
+    ) -> Result<bool, RepositoryError> {
+        let mut state = self.store.lock().await;
+        Ok(match state.poll_item_mut(poll_id, item_id) {
+            Some(item) => {
+                item.done = done;
+                true
+            }
+            None => false,
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_retired(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        retired: bool,
+    ) -> Result<bool, RepositoryError> {
+        let mut state = self.store.lock().await;
+        Ok(match state.poll_item_mut(poll_id, item_id) {
+            Some(item) => {
+                item.retired = retired;
+                true
+            }
+            None => false,
+        })
+    }
+}
@@ -0,0 +1,123 @@
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+use crate::{
+    model::{BallotType, NewPoll, Poll, PollSnapshot, PollStatus},
+    repository::{self, RepositoryError},
+};
+
+use super::{next_id, Store};
+
+#[derive(Clone)]
+pub struct PollRepository {
+    store: Store,
+}
+
+impl PollRepository {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl repository::PollRepository for PollRepository {
+    async fn ping(&self) -> Result<(), RepositoryError> {
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_all(&self) -> Result<Vec<Poll>, RepositoryError> {
+        let state = self.store.lock().await;
+        let mut polls = state.polls.clone();
+        polls.sort_by_key(|p| p.id);
+        Ok(polls)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_by_id(&self, id: i32) -> Result<Option<Poll>, RepositoryError> {
+        let state = self.store.lock().await;
+        Ok(state.polls.iter().find(|p| p.id == id).cloned())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn create(&self, poll: NewPoll) -> Result<i32, RepositoryError> {
+        let mut state = self.store.lock().await;
+        let id = next_id(state.polls.iter().map(|p| p.id));
+        state.polls.push(Poll {
+            id,
+            title: poll.title,
+            description: poll.description,
+            status: PollStatus::Draft,
+            opens_at: None,
+            closes_at: None,
+            seats: 1,
+            ballot_type: BallotType::Ranked,
+        });
+        Ok(id)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_lifecycle(
+        &self,
+        id: i32,
+        status: PollStatus,
+        opens_at: Option<OffsetDateTime>,
+        closes_at: Option<OffsetDateTime>,
+    ) -> Result<bool, RepositoryError> {
+        let mut state = self.store.lock().await;
+        let Some(poll) = state.polls.iter_mut().find(|p| p.id == id) else {
+            return Ok(false);
+        };
+        poll.status = status;
+        poll.opens_at = opens_at;
+        poll.closes_at = closes_at;
+        Ok(true)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_seats(&self, id: i32, seats: i32) -> Result<bool, RepositoryError> {
+        let mut state = self.store.lock().await;
+        let Some(poll) = state.polls.iter_mut().find(|p| p.id == id) else {
+            return Ok(false);
+        };
+        poll.seats = seats;
+        Ok(true)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_ballot_type(
+        &self,
+        id: i32,
+        ballot_type: BallotType,
+    ) -> Result<bool, RepositoryError> {
+        let mut state = self.store.lock().await;
+        let Some(poll) = state.polls.iter_mut().find(|p| p.id == id) else {
+            return Ok(false);
+        };
+        poll.ballot_type = ballot_type;
+        Ok(true)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_snapshot(&self, poll_id: i32) -> Result<Option<PollSnapshot>, RepositoryError> {
+        let state = self.store.lock().await;
+        Ok(state
+            .snapshots
+            .iter()
+            .find(|s| s.poll_id == poll_id)
+            .cloned())
+    }
+
+    #[tracing::instrument(skip(self, snapshot), fields(poll_id = snapshot.poll_id))]
+    async fn create_snapshot(&self, snapshot: &PollSnapshot) -> Result<(), RepositoryError> {
+        let mut state = self.store.lock().await;
+        if !state
+            .snapshots
+            .iter()
+            .any(|s| s.poll_id == snapshot.poll_id)
+        {
+            state.snapshots.push(snapshot.clone());
+        }
+        Ok(())
+    }
+}
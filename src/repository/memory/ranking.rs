@@ -0,0 +1,304 @@
+use async_trait::async_trait;
+
+use crate::{
+    model::{BallotRevision, NewBallotRevision, NewRanking, NewScore, Ranking, Score},
+    repository::{self, RepositoryError, Transact},
+};
+
+use super::{next_id, Store, StoredRanking, StoredScore, Transaction};
+
+#[derive(Clone)]
+pub struct RankingRepository {
+    store: Store,
+}
+
+impl RankingRepository {
+    pub fn new(store: Store) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl Transact for RankingRepository {
+    type Txn<'a> = Transaction<'a>;
+
+    #[tracing::instrument(skip(self))]
+    async fn begin<'a>(&'a self) -> Result<Self::Txn<'a>, RepositoryError> {
+        let state = self.store.lock().await;
+        let working = state.clone();
+        Ok(Transaction { state, working })
+    }
+
+    #[tracing::instrument(skip(self, txn))]
+    async fn end(&self, txn: Self::Txn<'_>) -> Result<(), RepositoryError> {
+        let Transaction { mut state, working } = txn;
+        *state = working;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl repository::RankingRepository for RankingRepository {
+    #[tracing::instrument(skip(self))]
+    async fn get_by_poll(&self, poll_id: i32) -> Result<Vec<Ranking>, RepositoryError> {
+        let state = self.store.lock().await;
+        let mut rankings: Vec<_> = state
+            .rankings
+            .iter()
+            .filter(|r| {
+                state
+                    .ballots
+                    .iter()
+                    .any(|b| b.id == r.ballot_id && b.poll_id == poll_id)
+            })
+            .collect();
+        rankings.sort_by_key(|r| (r.ballot_id, r.ord, r.item_id));
+        Ok(rankings
+            .into_iter()
+            .map(|r| Ranking {
+                ballot_id: r.ballot_id,
+                item_id: r.item_id,
+                ord: r.ord,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_scores_by_poll(&self, poll_id: i32) -> Result<Vec<Score>, RepositoryError> {
+        let state = self.store.lock().await;
+        let mut scores: Vec<_> = state
+            .scores
+            .iter()
+            .filter(|s| {
+                state
+                    .ballots
+                    .iter()
+                    .any(|b| b.id == s.ballot_id && b.poll_id == poll_id)
+            })
+            .collect();
+        scores.sort_by_key(|s| (s.ballot_id, s.item_id));
+        Ok(scores
+            .into_iter()
+            .map(|s| Score {
+                ballot_id: s.ballot_id,
+                item_id: s.item_id,
+                score: s.score,
+            })
+            .collect())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_revisions_by_poll(
+        &self,
+        poll_id: i32,
+    ) -> Result<Vec<BallotRevision>, RepositoryError> {
+        let state = self.store.lock().await;
+        Ok(state
+            .revisions
+            .iter()
+            .filter(|r| r.poll_id == poll_id)
+            .cloned()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl repository::TransactableRankingRepository for RankingRepository {
+    #[tracing::instrument(skip(self, rankings, txn))]
+    async fn txn_create_bulk<I>(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        rankings: &mut I,
+    ) -> Result<(), RepositoryError>
+    where
+        I: Iterator<Item = NewRanking> + Send,
+    {
+        let state = &mut txn.working;
+        for r in rankings {
+            if !state.ballots.iter().any(|b| b.id == r.ballot_id) {
+                return Err(RepositoryError::Constraint(
+                    "ranking must belong to a ballot",
+                ));
+            }
+            if !state.items.iter().any(|i| i.item.id == r.item_id) {
+                return Err(RepositoryError::Constraint("ranking must refer to an item"));
+            }
+            if state
+                .rankings
+                .iter()
+                .any(|x| x.ballot_id == r.ballot_id && x.item_id == r.item_id)
+            {
+                return Err(RepositoryError::Constraint("item is ranked twice"));
+            }
+            state.rankings.push(StoredRanking {
+                ord: r.ord,
+                item_id: r.item_id,
+                ballot_id: r.ballot_id,
+            });
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, txn))]
+    async fn txn_remove_ballot_rankings(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        ballot_id: i32,
+    ) -> Result<(), RepositoryError> {
+        txn.working.rankings.retain(|r| r.ballot_id != ballot_id);
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, scores, txn))]
+    async fn txn_create_score_bulk<I>(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        scores: &mut I,
+    ) -> Result<(), RepositoryError>
+    where
+        I: Iterator<Item = NewScore> + Send,
+    {
+        let state = &mut txn.working;
+        for s in scores {
+            if !state.ballots.iter().any(|b| b.id == s.ballot_id) {
+                return Err(RepositoryError::Constraint("score must belong to a ballot"));
+            }
+            if !state.items.iter().any(|i| i.item.id == s.item_id) {
+                return Err(RepositoryError::Constraint("score must refer to an item"));
+            }
+            if state
+                .scores
+                .iter()
+                .any(|x| x.ballot_id == s.ballot_id && x.item_id == s.item_id)
+            {
+                return Err(RepositoryError::Constraint("item is scored twice"));
+            }
+            state.scores.push(StoredScore {
+                item_id: s.item_id,
+                ballot_id: s.ballot_id,
+                score: s.score,
+            });
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, txn))]
+    async fn txn_remove_ballot_scores(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        ballot_id: i32,
+    ) -> Result<(), RepositoryError> {
+        txn.working.scores.retain(|s| s.ballot_id != ballot_id);
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, txn))]
+    async fn txn_find_last_revision_hash(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        poll_id: i32,
+    ) -> Result<Option<String>, RepositoryError> {
+        Ok(txn
+            .working
+            .revisions
+            .iter()
+            .rev()
+            .find(|r| r.poll_id == poll_id)
+            .map(|r| r.hash.clone()))
+    }
+
+    #[tracing::instrument(skip(self, txn))]
+    async fn txn_create_revision(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        revision: NewBallotRevision,
+    ) -> Result<(), RepositoryError> {
+        let state = &mut txn.working;
+        if state
+            .revisions
+            .iter()
+            .any(|r| r.poll_id == revision.poll_id && r.previous_hash == revision.previous_hash)
+        {
+            return Err(RepositoryError::Constraint("revision chain must not fork"));
+        }
+        let id = next_id(state.revisions.iter().map(|r| r.id));
+        state.revisions.push(BallotRevision {
+            id,
+            poll_id: revision.poll_id,
+            ballot_id: revision.ballot_id,
+            items: revision.items,
+            created_at: revision.created_at,
+            previous_hash: revision.previous_hash,
+            hash: revision.hash,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::repository::{
+        memory::ballot::BallotRepository, BallotRepository as _, RankingRepository as _,
+        TransactableRankingRepository as _,
+    };
+
+    use super::*;
+
+    async fn store_with_ballot() -> Store {
+        let store = Store::default();
+        BallotRepository::new(store.clone())
+            .save_ignoring_conflict(1, Uuid::new_v4())
+            .await
+            .unwrap();
+        crate::repository::ItemRepository::create(
+            &super::super::item::ItemRepository::new(store.clone()),
+            crate::model::NewItem {
+                poll_id: 1,
+                title: "Item".to_string(),
+                content: String::new(),
+            },
+        )
+        .await
+        .unwrap();
+        store
+    }
+
+    fn ranking(ord: i32) -> NewRanking {
+        NewRanking {
+            ord,
+            item_id: 1,
+            ballot_id: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn transaction_changes_are_kept_when_it_ends() {
+        let repository = RankingRepository::new(store_with_ballot().await);
+        let mut txn = repository.begin().await.unwrap();
+        repository
+            .txn_create_bulk(&mut txn, &mut std::iter::once(ranking(0)))
+            .await
+            .unwrap();
+        repository.end(txn).await.unwrap();
+        assert_eq!(repository.get_by_poll(1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn transaction_changes_are_discarded_when_it_is_dropped() {
+        let repository = RankingRepository::new(store_with_ballot().await);
+        let mut txn = repository.begin().await.unwrap();
+        repository
+            .txn_create_bulk(&mut txn, &mut std::iter::once(ranking(0)))
+            .await
+            .unwrap();
+        // A failing statement aborts the transaction.
+        assert!(repository
+            .txn_create_bulk(&mut txn, &mut std::iter::once(ranking(1)))
+            .await
+            .is_err());
+        drop(txn);
+        assert!(repository.get_by_poll(1).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,10 @@
+//! Repositories that are backed by a MySQL database.
+
+use sqlx::MySql;
+
+use super::sql;
+
+pub type BallotRepository = sql::ballot::BallotRepository<MySql>;
+pub type ItemRepository = sql::item::ItemRepository<MySql>;
+pub type PollRepository = sql::poll::PollRepository<MySql>;
+pub type RankingRepository = sql::ranking::RankingRepository<MySql>;
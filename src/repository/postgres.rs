@@ -0,0 +1,60 @@
+//! Repositories that are backed by a PostgreSQL database.
+
+use sqlx::Postgres;
+
+use super::sql;
+
+pub type BallotRepository = sql::ballot::BallotRepository<Postgres>;
+pub type ItemRepository = sql::item::ItemRepository<Postgres>;
+pub type PollRepository = sql::poll::PollRepository<Postgres>;
+pub type RankingRepository = sql::ranking::RankingRepository<Postgres>;
+
+/// These need a PostgreSQL server, they run with `cargo test --features postgres-tests` and the
+/// `DATABASE_URL` of a user that can create databases.
+#[cfg(all(test, feature = "postgres-tests"))]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::repository::tests;
+
+    use super::{BallotRepository, ItemRepository, PollRepository, RankingRepository};
+
+    #[sqlx::test(migrations = "migrations/postgres")]
+    async fn polls_are_created_as_drafts(pool: PgPool) {
+        tests::polls_are_created_as_drafts(PollRepository::new(pool)).await;
+    }
+
+    #[sqlx::test(migrations = "migrations/postgres")]
+    async fn polls_are_updated(pool: PgPool) {
+        tests::polls_are_updated(PollRepository::new(pool)).await;
+    }
+
+    #[sqlx::test(migrations = "migrations/postgres")]
+    async fn items_are_placed_in_the_given_order(pool: PgPool) {
+        tests::items_are_placed_in_the_given_order(ItemRepository::new(pool)).await;
+    }
+
+    #[sqlx::test(migrations = "migrations/postgres")]
+    async fn voter_tokens_are_used_once(pool: PgPool) {
+        tests::voter_tokens_are_used_once(BallotRepository::new(pool)).await;
+    }
+
+    #[sqlx::test(migrations = "migrations/postgres")]
+    async fn rankings_and_scores_are_created_in_bulk(pool: PgPool) {
+        tests::rankings_and_scores_are_created_in_bulk(
+            ItemRepository::new(pool.clone()),
+            BallotRepository::new(pool.clone()),
+            RankingRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[sqlx::test(migrations = "migrations/postgres")]
+    async fn revisions_are_chained(pool: PgPool) {
+        tests::revisions_are_chained(
+            BallotRepository::new(pool.clone()),
+            RankingRepository::new(pool),
+        )
+        .await;
+    }
+}
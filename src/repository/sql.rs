@@ -0,0 +1,256 @@
+//! Repositories that are shared by every SQL database. Statements are written once with `?` for
+//! their arguments, the few ways in which the databases differ are left to `SqlDatabase`.
+
+pub mod ballot;
+pub mod item;
+pub mod poll;
+pub mod ranking;
+
+use std::borrow::Cow;
+
+use sqlx::{
+    database::HasArguments, mysql::MySqlQueryResult, postgres::PgQueryResult,
+    sqlite::SqliteQueryResult, Arguments, Database, Encode, MySql, Postgres, Sqlite, Type,
+};
+
+use crate::model::{NewRanking, NewScore};
+
+/// A SQL database that the shared statements can be built for.
+pub trait SqlDatabase: Database {
+    /// Maximum number of arguments that can be bound in a single statement.
+    const BIND_LIMIT: usize;
+
+    /// Whether the id of an inserted row is given back with `RETURNING id`, otherwise it is taken
+    /// from the result of the statement.
+    const RETURNING_ID: bool;
+
+    /// Write the arguments of a statement, which are given as `?`, the way that the database
+    /// expects them. Statements must not have a `?` anywhere else.
+    fn statement(sql: &str) -> Cow<'_, str> {
+        Cow::Borrowed(sql)
+    }
+
+    /// The clause of an insert leaving out the rows whose unique key is already taken.
+    fn ignore_conflict(key: &[&str]) -> String {
+        format!(" ON CONFLICT ({}) DO NOTHING", key.join(", "))
+    }
+
+    /// The number of rows that a statement changed.
+    fn rows_affected(result: &Self::QueryResult) -> u64;
+
+    /// The id of the row inserted by a statement, when it is not given back with `RETURNING id`.
+    fn last_insert_id(_result: &Self::QueryResult) -> Option<i64> {
+        None
+    }
+}
+
+impl SqlDatabase for MySql {
+    const BIND_LIMIT: usize = 65535;
+    const RETURNING_ID: bool = false;
+
+    fn ignore_conflict(key: &[&str]) -> String {
+        // Updating a column to itself leaves the row as it is, unlike `INSERT IGNORE` which also
+        // ignores the errors that are not about the key.
+        format!(" ON DUPLICATE KEY UPDATE {0} = {0}", key[0])
+    }
+
+    fn rows_affected(result: &MySqlQueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn last_insert_id(result: &MySqlQueryResult) -> Option<i64> {
+        Some(result.last_insert_id() as i64)
+    }
+}
+
+impl SqlDatabase for Postgres {
+    const BIND_LIMIT: usize = 65535;
+    const RETURNING_ID: bool = true;
+
+    fn statement(sql: &str) -> Cow<'_, str> {
+        let mut statement = String::with_capacity(sql.len() + 8);
+        for (i, part) in sql.split('?').enumerate() {
+            if i > 0 {
+                statement.push('$');
+                statement.push_str(&i.to_string());
+            }
+            statement.push_str(part);
+        }
+        Cow::Owned(statement)
+    }
+
+    fn rows_affected(result: &PgQueryResult) -> u64 {
+        result.rows_affected()
+    }
+}
+
+impl SqlDatabase for Sqlite {
+    const BIND_LIMIT: usize = 32766;
+    const RETURNING_ID: bool = false;
+
+    fn rows_affected(result: &SqliteQueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn last_insert_id(result: &SqliteQueryResult) -> Option<i64> {
+        Some(result.last_insert_rowid())
+    }
+}
+
+/// A statement that is built along with its arguments like a `QueryBuilder`. Both are given up
+/// to be run with `sqlx::query_with`, as the query built by a `QueryBuilder` can not outlive the
+/// builder when the database is generic.
+pub struct Statement<'args, DB: Database> {
+    pub sql: String,
+    pub arguments: <DB as HasArguments<'args>>::Arguments,
+}
+
+impl<'args, DB: Database> Statement<'args, DB> {
+    fn new(sql: &str) -> Self {
+        Self {
+            sql: sql.to_string(),
+            arguments: Default::default(),
+        }
+    }
+
+    fn push(&mut self, sql: &str) -> &mut Self {
+        self.sql.push_str(sql);
+        self
+    }
+
+    fn push_bind<T>(&mut self, value: T) -> &mut Self
+    where
+        T: 'args + Send + Encode<'args, DB> + Type<DB>,
+    {
+        self.arguments.add(value);
+        self.arguments
+            .format_placeholder(&mut self.sql)
+            .expect("writing to a string can not fail");
+        self
+    }
+
+    /// Push the values of the rows, each row binds its arguments with the given function.
+    fn push_values<I, F>(&mut self, rows: I, mut push_row: F) -> &mut Self
+    where
+        I: IntoIterator,
+        F: FnMut(&mut Self, I::Item),
+    {
+        self.push("VALUES ");
+        for (i, row) in rows.into_iter().enumerate() {
+            self.push(if i == 0 { "(" } else { ", (" });
+            push_row(self, row);
+            self.push(")");
+        }
+        self
+    }
+}
+
+/// Build a statement inserting as many rankings from the iterator as can be bound at once.
+///
+/// Callers must make sure that the iterator is not empty.
+fn insert_rankings<'args, DB, I>(rankings: &mut I) -> Statement<'args, DB>
+where
+    DB: SqlDatabase,
+    i32: Encode<'args, DB> + Type<DB>,
+    I: Iterator<Item = NewRanking>,
+{
+    let mut statement = Statement::new("INSERT INTO rankings(ord, item_id, ballot_id)\n");
+    // 3 is the number of arguments that are bound for each ranking
+    statement.push_values(rankings.take(DB::BIND_LIMIT / 3), |s, r| {
+        s.push_bind(r.ord)
+            .push(", ")
+            .push_bind(r.item_id)
+            .push(", ")
+            .push_bind(r.ballot_id);
+    });
+    statement
+}
+
+/// Build a statement inserting as many scores from the iterator as can be bound at once.
+///
+/// Callers must make sure that the iterator is not empty.
+fn insert_scores<'args, DB, I>(scores: &mut I) -> Statement<'args, DB>
+where
+    DB: SqlDatabase,
+    i32: Encode<'args, DB> + Type<DB>,
+    I: Iterator<Item = NewScore>,
+{
+    let mut statement = Statement::new("INSERT INTO scores(item_id, ballot_id, score)\n");
+    // 3 is the number of arguments that are bound for each score
+    statement.push_values(scores.take(DB::BIND_LIMIT / 3), |s, r| {
+        s.push_bind(r.item_id)
+            .push(", ")
+            .push_bind(r.ballot_id)
+            .push(", ")
+            .push_bind(r.score);
+    });
+    statement
+}
+
+/// Build a statement inserting the hashes of new voter tokens of a poll, as many hashes as can be
+/// bound at once are taken from the iterator.
+///
+/// Callers must make sure that the iterator is not empty.
+fn insert_voter_tokens<'args, DB, I>(poll_id: i32, token_hashes: &mut I) -> Statement<'args, DB>
+where
+    DB: SqlDatabase,
+    i32: Encode<'args, DB> + Type<DB>,
+    String: Encode<'args, DB> + Type<DB>,
+    I: Iterator<Item = String>,
+{
+    let mut statement = Statement::new("INSERT INTO voter_tokens(poll_id, token_hash)\n");
+    // 2 is the number of arguments that are bound for each token
+    statement.push_values(token_hashes.take(DB::BIND_LIMIT / 2), |s, token_hash| {
+        s.push_bind(poll_id).push(", ").push_bind(token_hash);
+    });
+    statement
+}
+
+/// Build a statement placing items of a poll at the given positions, as many items as can be
+/// bound at once are taken from the iterator. Items that are not given keep their position.
+///
+/// Callers must make sure that the iterator is not empty.
+fn update_positions<'args, DB, I>(poll_id: i32, positions: &mut I) -> Statement<'args, DB>
+where
+    DB: SqlDatabase,
+    i32: Encode<'args, DB> + Type<DB>,
+    I: Iterator<Item = (i32, i32)>,
+{
+    let mut statement = Statement::new("UPDATE items SET position = CASE id");
+    // 2 is the number of arguments that are bound for each item, and 1 more is bound for the poll
+    for (item_id, position) in positions.take((DB::BIND_LIMIT - 1) / 2) {
+        statement
+            .push(" WHEN ")
+            .push_bind(item_id)
+            .push(" THEN ")
+            .push_bind(position);
+    }
+    statement
+        .push(" ELSE position END WHERE poll_id = ")
+        .push_bind(poll_id);
+    statement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_are_written_for_each_database() {
+        let sql = "UPDATE items SET done = ? WHERE poll_id = ? AND id = ?";
+        assert_eq!(MySql::statement(sql), sql);
+        assert_eq!(Sqlite::statement(sql), sql);
+        assert_eq!(
+            Postgres::statement(sql),
+            "UPDATE items SET done = $1 WHERE poll_id = $2 AND id = $3"
+        );
+        assert_eq!(
+            Postgres::ignore_conflict(&["poll_id", "uuid"]),
+            " ON CONFLICT (poll_id, uuid) DO NOTHING"
+        );
+        assert_eq!(
+            MySql::ignore_conflict(&["poll_id", "uuid"]),
+            " ON DUPLICATE KEY UPDATE poll_id = poll_id"
+        );
+    }
+}
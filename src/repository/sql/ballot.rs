@@ -0,0 +1,165 @@
+use sqlx::{
+    database::HasArguments, Database, Encode, Executor, FromRow, IntoArguments, Pool, Type,
+};
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    model::{Ballot, VoterRoll},
+    repository::{self, RepositoryError},
+};
+
+use super::{SqlDatabase, Statement};
+
+pub struct BallotRepository<DB: Database> {
+    pool: Pool<DB>,
+}
+
+impl<DB: Database> BallotRepository<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+}
+
+impl<DB: Database> Clone for BallotRepository<DB> {
+    fn clone(&self) -> Self {
+        Self::new(self.pool.clone())
+    }
+}
+
+#[async_trait]
+impl<DB> repository::BallotRepository for BallotRepository<DB>
+where
+    DB: SqlDatabase,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> i32: Encode<'q, DB> + Type<DB>,
+    for<'q> Uuid: Encode<'q, DB> + Type<DB>,
+    for<'q> OffsetDateTime: Encode<'q, DB> + Type<DB>,
+    for<'q> String: Encode<'q, DB> + Type<DB>,
+    for<'q> &'q str: Encode<'q, DB> + Type<DB>,
+    for<'r> Ballot: FromRow<'r, DB::Row>,
+    for<'r> VoterRoll: FromRow<'r, DB::Row>,
+{
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn find_by_uuid(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+    ) -> Result<Option<Ballot>, RepositoryError> {
+        let query =
+            DB::statement("SELECT id, poll_id, uuid FROM ballots WHERE poll_id = ? AND uuid = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let ballot = sqlx::query_as(&query)
+            .bind(poll_id)
+            .bind(uuid)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(ballot)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn save_ignoring_conflict(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+    ) -> Result<(), RepositoryError> {
+        let query = insert_ballot::<DB>();
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        sqlx::query(&query)
+            .bind(poll_id)
+            .bind(uuid)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self, token_hashes),
+        fields(tokens = token_hashes.len(), query=tracing::field::Empty)
+    )]
+    async fn create_voter_tokens(
+        &self,
+        poll_id: i32,
+        token_hashes: &[String],
+    ) -> Result<(), RepositoryError> {
+        let mut token_hashes = token_hashes.iter().cloned();
+        while token_hashes.len() != 0 {
+            let Statement { sql, arguments } =
+                super::insert_voter_tokens::<DB, _>(poll_id, &mut token_hashes);
+            tracing::Span::current().record("query", tracing::field::display(&sql));
+            sqlx::query_with(&sql, arguments)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self, token_hash),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn save_with_voter_token(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+        token_hash: &str,
+    ) -> Result<bool, RepositoryError> {
+        let mut txn = self.pool.begin().await?;
+        let query = DB::statement(
+            r#"
+            UPDATE voter_tokens SET used_at = ?
+            WHERE poll_id = ? AND token_hash = ? AND used_at IS NULL"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(OffsetDateTime::now_utc())
+            .bind(poll_id)
+            .bind(token_hash)
+            .execute(&mut *txn)
+            .await?;
+        if DB::rows_affected(&result) == 0 {
+            // Dropping the transaction rolls it back.
+            return Ok(false);
+        }
+        let query = insert_ballot::<DB>();
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        sqlx::query(&query)
+            .bind(poll_id)
+            .bind(uuid)
+            .execute(&mut *txn)
+            .await?;
+        txn.commit().await?;
+        Ok(true)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn count_voter_tokens(&self, poll_id: i32) -> Result<VoterRoll, RepositoryError> {
+        let query = DB::statement(
+            "SELECT COUNT(*) AS issued, COUNT(used_at) AS used FROM voter_tokens WHERE poll_id = ?",
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let roll = sqlx::query_as(&query)
+            .bind(poll_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(roll)
+    }
+}
+
+/// The statement creating a ballot unless the voter already has one in the poll.
+fn insert_ballot<DB: SqlDatabase>() -> String {
+    let statement = DB::statement("INSERT INTO ballots(poll_id, uuid) VALUES (?, ?)");
+    statement.into_owned() + &DB::ignore_conflict(&["poll_id", "uuid"])
+}
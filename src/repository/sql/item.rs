@@ -0,0 +1,223 @@
+use sqlx::{
+    database::HasArguments, Database, Encode, Executor, FromRow, IntoArguments, Pool, Type,
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    model::{Item, NewItem, RankedItem},
+    repository::{self, RepositoryError},
+};
+
+use super::{SqlDatabase, Statement};
+
+pub struct ItemRepository<DB: Database> {
+    pool: Pool<DB>,
+}
+
+impl<DB: Database> ItemRepository<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+}
+
+impl<DB: Database> Clone for ItemRepository<DB> {
+    fn clone(&self) -> Self {
+        Self::new(self.pool.clone())
+    }
+}
+
+#[async_trait]
+impl<DB> repository::ItemRepository for ItemRepository<DB>
+where
+    DB: SqlDatabase,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> i32: Encode<'q, DB> + Type<DB>,
+    for<'q> bool: Encode<'q, DB> + Type<DB>,
+    for<'q> String: Encode<'q, DB> + Type<DB>,
+    for<'q> &'q str: Encode<'q, DB> + Type<DB>,
+    for<'r> Item: FromRow<'r, DB::Row>,
+    for<'r> RankedItem: FromRow<'r, DB::Row>,
+{
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn find_ranked_by_ballot(
+        &self,
+        ballot_id: i32,
+    ) -> Result<Vec<RankedItem>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT rankings.ord, items.id, items.title, items.content, items.done, items.position,
+                items.retired
+            FROM items INNER JOIN rankings ON items.id = rankings.item_id
+            WHERE NOT items.done AND NOT items.retired AND rankings.ballot_id = ?
+            ORDER BY rankings.ord ASC, items.position ASC"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let items = sqlx::query_as(&query)
+            .bind(ballot_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(items)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn find_unranked_by_ballot(
+        &self,
+        poll_id: i32,
+        ballot_id: i32,
+    ) -> Result<Vec<Item>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT items.id, items.title, items.content, items.done, items.position, items.retired
+            FROM items LEFT JOIN rankings ON items.id = rankings.item_id AND rankings.ballot_id = ?
+            WHERE NOT items.done AND NOT items.retired AND items.poll_id = ?
+                AND rankings.ballot_id IS NULL
+            ORDER BY items.position ASC"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let items = sqlx::query_as(&query)
+            .bind(ballot_id)
+            .bind(poll_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(items)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn find_by_poll(&self, poll_id: i32) -> Result<Vec<Item>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT id, title, content, done, position, retired
+            FROM items
+            WHERE poll_id = ?
+            ORDER BY position ASC, id ASC"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let items = sqlx::query_as(&query)
+            .bind(poll_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(items)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn create(&self, item: NewItem) -> Result<(), RepositoryError> {
+        let query = DB::statement(
+            r#"
+            INSERT INTO items(poll_id, title, content, position)
+            SELECT ?, ?, ?, COALESCE(MAX(position), 0) + 1 FROM items WHERE poll_id = ?"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        sqlx::query(&query)
+            .bind(item.poll_id)
+            .bind(item.title)
+            .bind(item.content)
+            .bind(item.poll_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn update_content(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        title: &str,
+        content: &str,
+    ) -> Result<bool, RepositoryError> {
+        let query =
+            DB::statement("UPDATE items SET title = ?, content = ? WHERE poll_id = ? AND id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(title)
+            .bind(content)
+            .bind(poll_id)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(DB::rows_affected(&result) > 0)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn update_positions(
+        &self,
+        poll_id: i32,
+        item_ids: &[i32],
+    ) -> Result<(), RepositoryError> {
+        let mut positions = item_ids
+            .iter()
+            .enumerate()
+            .map(|(position, item_id)| (*item_id, position as i32));
+        while positions.len() != 0 {
+            let Statement { sql, arguments } =
+                super::update_positions::<DB, _>(poll_id, &mut positions);
+            tracing::Span::current().record("query", tracing::field::display(&sql));
+            sqlx::query_with(&sql, arguments)
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn update_done(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        done: bool,
+    ) -> Result<bool, RepositoryError> {
+        let query = DB::statement("UPDATE items SET done = ? WHERE poll_id = ? AND id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(done)
+            .bind(poll_id)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(DB::rows_affected(&result) > 0)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn update_retired(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        retired: bool,
+    ) -> Result<bool, RepositoryError> {
+        let query = DB::statement("UPDATE items SET retired = ? WHERE poll_id = ? AND id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(retired)
+            .bind(poll_id)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(DB::rows_affected(&result) > 0)
+    }
+}
@@ -0,0 +1,223 @@
+use sqlx::{
+    database::HasArguments, types::Json, Connection, Database, Encode, Executor, FromRow,
+    IntoArguments, Pool, Type,
+};
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+use crate::{
+    model::{
+        BallotType, ElectionTrace, NewPoll, Poll, PollSnapshot, PollStatus, ScoreTally,
+        TallyResult, TallyTrace,
+    },
+    repository::{self, RepositoryError},
+};
+
+use super::SqlDatabase;
+
+pub struct PollRepository<DB: Database> {
+    pool: Pool<DB>,
+}
+
+impl<DB: Database> PollRepository<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+}
+
+impl<DB: Database> Clone for PollRepository<DB> {
+    fn clone(&self) -> Self {
+        Self::new(self.pool.clone())
+    }
+}
+
+#[async_trait]
+impl<DB> repository::PollRepository for PollRepository<DB>
+where
+    DB: SqlDatabase,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> i32: Encode<'q, DB> + Type<DB>,
+    for<'q> String: Encode<'q, DB> + Type<DB>,
+    for<'q> &'q str: Encode<'q, DB> + Type<DB>,
+    for<'q> OffsetDateTime: Encode<'q, DB> + Type<DB>,
+    for<'q> Option<OffsetDateTime>: Encode<'q, DB> + Type<DB>,
+    for<'a, 'q> Json<&'a TallyResult>: Encode<'q, DB> + Type<DB>,
+    for<'a, 'q> Json<&'a TallyTrace>: Encode<'q, DB> + Type<DB>,
+    for<'a, 'q> Json<&'a Option<ElectionTrace>>: Encode<'q, DB> + Type<DB>,
+    for<'a, 'q> Json<&'a Option<ScoreTally>>: Encode<'q, DB> + Type<DB>,
+    for<'r> (i32,): FromRow<'r, DB::Row>,
+    for<'r> Poll: FromRow<'r, DB::Row>,
+    for<'r> PollSnapshot: FromRow<'r, DB::Row>,
+{
+    #[tracing::instrument(skip(self))]
+    async fn ping(&self) -> Result<(), RepositoryError> {
+        let mut conn = self.pool.acquire().await?;
+        conn.ping().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn find_all(&self) -> Result<Vec<Poll>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT id, title, description, status, opens_at, closes_at, seats, ballot_type
+            FROM polls ORDER BY id ASC"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let polls = sqlx::query_as(&query).fetch_all(&self.pool).await?;
+        Ok(polls)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn find_by_id(&self, id: i32) -> Result<Option<Poll>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT id, title, description, status, opens_at, closes_at, seats, ballot_type
+            FROM polls WHERE id = ?"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let poll = sqlx::query_as(&query)
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(poll)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn create(&self, poll: NewPoll) -> Result<i32, RepositoryError> {
+        let query = DB::statement("INSERT INTO polls(title, description, status) VALUES (?, ?, ?)");
+        // Databases that can not give back the id take it from the result of the statement.
+        if DB::RETURNING_ID {
+            let query = query.into_owned() + " RETURNING id";
+            tracing::Span::current().record("query", tracing::field::display(&query));
+            let id = sqlx::query_scalar(&query)
+                .bind(poll.title)
+                .bind(poll.description)
+                .bind(PollStatus::Draft.as_str())
+                .fetch_one(&self.pool)
+                .await?;
+            return Ok(id);
+        }
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(poll.title)
+            .bind(poll.description)
+            .bind(PollStatus::Draft.as_str())
+            .execute(&self.pool)
+            .await?;
+        let id = DB::last_insert_id(&result).ok_or(sqlx::Error::RowNotFound)?;
+        Ok(id as i32)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn update_lifecycle(
+        &self,
+        id: i32,
+        status: PollStatus,
+        opens_at: Option<OffsetDateTime>,
+        closes_at: Option<OffsetDateTime>,
+    ) -> Result<bool, RepositoryError> {
+        let query =
+            DB::statement("UPDATE polls SET status = ?, opens_at = ?, closes_at = ? WHERE id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(status.as_str())
+            .bind(opens_at)
+            .bind(closes_at)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(DB::rows_affected(&result) > 0)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn update_seats(&self, id: i32, seats: i32) -> Result<bool, RepositoryError> {
+        let query = DB::statement("UPDATE polls SET seats = ? WHERE id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(seats)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(DB::rows_affected(&result) > 0)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn update_ballot_type(
+        &self,
+        id: i32,
+        ballot_type: BallotType,
+    ) -> Result<bool, RepositoryError> {
+        let query = DB::statement("UPDATE polls SET ballot_type = ? WHERE id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let result = sqlx::query(&query)
+            .bind(ballot_type.as_str())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(DB::rows_affected(&result) > 0)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn find_snapshot(&self, poll_id: i32) -> Result<Option<PollSnapshot>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT poll_id, result, trace, election, scores, created_at
+            FROM poll_results WHERE poll_id = ?"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let snapshot = sqlx::query_as(&query)
+            .bind(poll_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(snapshot)
+    }
+
+    #[tracing::instrument(
+        skip(self, snapshot),
+        fields(poll_id = snapshot.poll_id, query=tracing::field::Empty)
+    )]
+    async fn create_snapshot(&self, snapshot: &PollSnapshot) -> Result<(), RepositoryError> {
+        let query = DB::statement(
+            r#"
+            INSERT INTO poll_results(poll_id, result, trace, election, scores, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)"#,
+        )
+        .into_owned()
+            + &DB::ignore_conflict(&["poll_id"]);
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        sqlx::query(&query)
+            .bind(snapshot.poll_id)
+            .bind(Json(&snapshot.result))
+            .bind(Json(&snapshot.trace))
+            .bind(Json(&snapshot.election))
+            .bind(Json(&snapshot.scores))
+            .bind(snapshot.created_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
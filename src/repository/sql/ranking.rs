@@ -0,0 +1,266 @@
+use std::ops::DerefMut;
+
+use sqlx::{
+    database::HasArguments, Database, Encode, Executor, FromRow, IntoArguments, Pool, Transaction,
+    Type,
+};
+
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+use crate::{
+    model::{BallotRevision, NewBallotRevision, NewRanking, NewScore, Ranking, Score},
+    repository::{self, RepositoryError, Transact},
+};
+
+use super::{SqlDatabase, Statement};
+
+pub struct RankingRepository<DB: Database> {
+    pool: Pool<DB>,
+}
+
+impl<DB: Database> RankingRepository<DB> {
+    pub fn new(pool: Pool<DB>) -> Self {
+        Self { pool }
+    }
+}
+
+impl<DB: Database> Clone for RankingRepository<DB> {
+    fn clone(&self) -> Self {
+        Self::new(self.pool.clone())
+    }
+}
+
+#[async_trait]
+impl<DB> Transact for RankingRepository<DB>
+where
+    DB: SqlDatabase,
+{
+    type Txn<'a> = Transaction<'a, DB>;
+
+    #[tracing::instrument(skip(self))]
+    async fn begin<'a>(&'a self) -> Result<Self::Txn<'a>, RepositoryError> {
+        Ok(self.pool.begin().await?)
+    }
+
+    #[tracing::instrument(skip(self, txn))]
+    async fn end(&self, txn: Self::Txn<'_>) -> Result<(), RepositoryError> {
+        Ok(txn.commit().await?)
+    }
+}
+
+#[async_trait]
+impl<DB> repository::RankingRepository for RankingRepository<DB>
+where
+    DB: SqlDatabase,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> i32: Encode<'q, DB> + Type<DB>,
+    for<'r> Ranking: FromRow<'r, DB::Row>,
+    for<'r> Score: FromRow<'r, DB::Row>,
+    for<'r> BallotRevision: FromRow<'r, DB::Row>,
+{
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn get_by_poll(&self, poll_id: i32) -> Result<Vec<Ranking>, RepositoryError> {
+        // Query for item ids sorted by ballot id and ranking order
+        let query = DB::statement(
+            r#"
+            SELECT rankings.ballot_id, rankings.item_id, rankings.ord
+            FROM rankings
+            INNER JOIN ballots ON rankings.ballot_id = ballots.id
+            WHERE ballots.poll_id = ?
+            ORDER BY rankings.ballot_id ASC, rankings.ord ASC, rankings.item_id ASC"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let rankings = sqlx::query_as(&query)
+            .bind(poll_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rankings)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn get_scores_by_poll(&self, poll_id: i32) -> Result<Vec<Score>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT scores.ballot_id, scores.item_id, scores.score
+            FROM scores
+            INNER JOIN ballots ON scores.ballot_id = ballots.id
+            WHERE ballots.poll_id = ?
+            ORDER BY scores.ballot_id ASC, scores.item_id ASC"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let scores = sqlx::query_as(&query)
+            .bind(poll_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(scores)
+    }
+
+    #[tracing::instrument(
+        skip(self),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn get_revisions_by_poll(
+        &self,
+        poll_id: i32,
+    ) -> Result<Vec<BallotRevision>, RepositoryError> {
+        let query = DB::statement(
+            r#"
+            SELECT id, poll_id, ballot_id, items, created_at, previous_hash, hash
+            FROM ballot_revisions
+            WHERE poll_id = ?
+            ORDER BY id ASC"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let revisions = sqlx::query_as(&query)
+            .bind(poll_id)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(revisions)
+    }
+}
+
+#[async_trait]
+impl<DB> repository::TransactableRankingRepository for RankingRepository<DB>
+where
+    DB: SqlDatabase,
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+    for<'q> <DB as HasArguments<'q>>::Arguments: IntoArguments<'q, DB>,
+    for<'q> i32: Encode<'q, DB> + Type<DB>,
+    for<'q> String: Encode<'q, DB> + Type<DB>,
+    for<'q> OffsetDateTime: Encode<'q, DB> + Type<DB>,
+    for<'r> (String,): FromRow<'r, DB::Row>,
+    for<'r> Ranking: FromRow<'r, DB::Row>,
+    for<'r> Score: FromRow<'r, DB::Row>,
+    for<'r> BallotRevision: FromRow<'r, DB::Row>,
+{
+    #[tracing::instrument(
+        skip(self, rankings, txn),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn txn_create_bulk<I>(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        rankings: &mut I,
+    ) -> Result<(), RepositoryError>
+    where
+        I: Iterator<Item = NewRanking> + Send,
+    {
+        let Statement { sql, arguments } = super::insert_rankings::<DB, _>(rankings);
+        tracing::Span::current().record("query", tracing::field::display(&sql));
+        sqlx::query_with(&sql, arguments)
+            .execute(txn.deref_mut())
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self, txn),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn txn_remove_ballot_rankings(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        ballot_id: i32,
+    ) -> Result<(), RepositoryError> {
+        let query = DB::statement("DELETE FROM rankings WHERE rankings.ballot_id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        sqlx::query(&query)
+            .bind(ballot_id)
+            .execute(txn.deref_mut())
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self, scores, txn),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn txn_create_score_bulk<I>(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        scores: &mut I,
+    ) -> Result<(), RepositoryError>
+    where
+        I: Iterator<Item = NewScore> + Send,
+    {
+        let Statement { sql, arguments } = super::insert_scores::<DB, _>(scores);
+        tracing::Span::current().record("query", tracing::field::display(&sql));
+        sqlx::query_with(&sql, arguments)
+            .execute(txn.deref_mut())
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self, txn),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn txn_remove_ballot_scores(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        ballot_id: i32,
+    ) -> Result<(), RepositoryError> {
+        let query = DB::statement("DELETE FROM scores WHERE scores.ballot_id = ?");
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        sqlx::query(&query)
+            .bind(ballot_id)
+            .execute(txn.deref_mut())
+            .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(
+        skip(self, txn),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn txn_find_last_revision_hash(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        poll_id: i32,
+    ) -> Result<Option<String>, RepositoryError> {
+        let query = DB::statement(
+            "SELECT hash FROM ballot_revisions WHERE poll_id = ? ORDER BY id DESC LIMIT 1",
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        let hash = sqlx::query_scalar(&query)
+            .bind(poll_id)
+            .fetch_optional(txn.deref_mut())
+            .await?;
+        Ok(hash)
+    }
+
+    #[tracing::instrument(
+        skip(self, txn),
+        fields(query=tracing::field::Empty)
+    )]
+    async fn txn_create_revision(
+        &self,
+        txn: &mut Self::Txn<'_>,
+        revision: NewBallotRevision,
+    ) -> Result<(), RepositoryError> {
+        let query = DB::statement(
+            r#"
+            INSERT INTO ballot_revisions(poll_id, ballot_id, items, created_at, previous_hash, hash)
+            VALUES (?, ?, ?, ?, ?, ?)"#,
+        );
+        tracing::Span::current().record("query", tracing::field::display(&query));
+        sqlx::query(&query)
+            .bind(revision.poll_id)
+            .bind(revision.ballot_id)
+            .bind(revision.items)
+            .bind(revision.created_at)
+            .bind(revision.previous_hash)
+            .bind(revision.hash)
+            .execute(txn.deref_mut())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+//! Repositories that are backed by a SQLite database.
+
+use sqlx::Sqlite;
+
+use super::sql;
+
+pub type BallotRepository = sql::ballot::BallotRepository<Sqlite>;
+pub type ItemRepository = sql::item::ItemRepository<Sqlite>;
+pub type PollRepository = sql::poll::PollRepository<Sqlite>;
+pub type RankingRepository = sql::ranking::RankingRepository<Sqlite>;
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{sqlite::SqlitePoolOptions, SqlitePool};
+
+    use crate::repository::tests;
+
+    use super::{BallotRepository, ItemRepository, PollRepository, RankingRepository};
+
+    /// A migrated database that only lives as long as its single connection.
+    async fn pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("migrations/sqlite")
+            .run(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn polls_are_created_as_drafts() {
+        tests::polls_are_created_as_drafts(PollRepository::new(pool().await)).await;
+    }
+
+    #[tokio::test]
+    async fn polls_are_updated() {
+        tests::polls_are_updated(PollRepository::new(pool().await)).await;
+    }
+
+    #[tokio::test]
+    async fn items_are_placed_in_the_given_order() {
+        tests::items_are_placed_in_the_given_order(ItemRepository::new(pool().await)).await;
+    }
+
+    #[tokio::test]
+    async fn voter_tokens_are_used_once() {
+        tests::voter_tokens_are_used_once(BallotRepository::new(pool().await)).await;
+    }
+
+    #[tokio::test]
+    async fn rankings_and_scores_are_created_in_bulk() {
+        let pool = pool().await;
+        tests::rankings_and_scores_are_created_in_bulk(
+            ItemRepository::new(pool.clone()),
+            BallotRepository::new(pool.clone()),
+            RankingRepository::new(pool),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn revisions_are_chained() {
+        let pool = pool().await;
+        tests::revisions_are_chained(
+            BallotRepository::new(pool.clone()),
+            RankingRepository::new(pool),
+        )
+        .await;
+    }
+}
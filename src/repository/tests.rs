@@ -0,0 +1,234 @@
+//! Checks that every SQL backend must pass, the backends run them against their own database.
+
+use time::macros::datetime;
+use uuid::Uuid;
+
+use crate::model::{
+    BallotType, NewBallotRevision, NewItem, NewPoll, NewRanking, NewScore, PollStatus,
+};
+
+use super::{BallotRepository, ItemRepository, PollRepository, TransactableRankingRepository};
+
+/// More ballots than the rankings or the scores of a single statement can reference.
+const BALLOTS: usize = 12_000;
+/// More rows than a single statement can update or insert with two arguments for each.
+const ROWS: usize = 40_000;
+
+fn new_item(poll_id: i32, title: &str) -> NewItem {
+    NewItem {
+        poll_id,
+        title: title.to_string(),
+        content: String::new(),
+    }
+}
+
+pub(super) async fn polls_are_created_as_drafts(polls: impl PollRepository) {
+    let id = polls
+        .create(NewPoll {
+            title: "Lunch".to_string(),
+            description: "Where to eat".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let poll = polls.find_by_id(id).await.unwrap().unwrap();
+    assert_eq!(poll.title, "Lunch");
+    assert_eq!(poll.description, "Where to eat");
+    assert_eq!(poll.status, PollStatus::Draft);
+    assert_eq!(poll.seats, 1);
+    assert_eq!(poll.ballot_type, BallotType::Ranked);
+    assert!(polls.find_all().await.unwrap().iter().any(|p| p.id == id));
+}
+
+pub(super) async fn polls_are_updated(polls: impl PollRepository) {
+    let opens_at = datetime!(2026-10-18 09:30 UTC);
+    assert!(polls
+        .update_lifecycle(1, PollStatus::Draft, Some(opens_at), None)
+        .await
+        .unwrap());
+    assert!(polls.update_seats(1, 2).await.unwrap());
+    assert!(polls
+        .update_ballot_type(1, BallotType::Approval)
+        .await
+        .unwrap());
+
+    let poll = polls.find_by_id(1).await.unwrap().unwrap();
+    assert_eq!(poll.status, PollStatus::Draft);
+    assert_eq!(poll.opens_at, Some(opens_at));
+    assert_eq!(poll.closes_at, None);
+    assert_eq!(poll.seats, 2);
+    assert_eq!(poll.ballot_type, BallotType::Approval);
+
+    assert!(!polls.update_seats(-1, 2).await.unwrap());
+    assert!(polls.find_by_id(-1).await.unwrap().is_none());
+}
+
+pub(super) async fn items_are_placed_in_the_given_order(items: impl ItemRepository) {
+    for title in ["A", "B", "C"] {
+        items.create(new_item(1, title)).await.unwrap();
+    }
+    let ids: Vec<_> = items
+        .find_by_poll(1)
+        .await
+        .unwrap()
+        .iter()
+        .map(|item| item.id)
+        .collect();
+
+    // The existing items are at the end so that they are placed by the last statement.
+    let mut item_ids: Vec<_> = (0..ROWS as i32).map(|id| -id - 1).collect();
+    item_ids.extend(ids.iter().rev());
+    items.update_positions(1, &item_ids).await.unwrap();
+
+    let titles: Vec<_> = items
+        .find_by_poll(1)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|item| item.title)
+        .collect();
+    assert_eq!(titles, ["C", "B", "A"]);
+}
+
+pub(super) async fn voter_tokens_are_used_once(ballots: impl BallotRepository) {
+    let token_hashes: Vec<_> = (0..ROWS).map(|i| format!("hash-{i}")).collect();
+    ballots.create_voter_tokens(1, &token_hashes).await.unwrap();
+    let roll = ballots.count_voter_tokens(1).await.unwrap();
+    assert_eq!((roll.issued, roll.used), (ROWS as i64, 0));
+
+    let uuid = Uuid::new_v4();
+    assert!(!ballots
+        .save_with_voter_token(1, uuid, "unknown")
+        .await
+        .unwrap());
+    assert!(ballots.find_by_uuid(1, uuid).await.unwrap().is_none());
+
+    let last = token_hashes.last().unwrap();
+    assert!(ballots.save_with_voter_token(1, uuid, last).await.unwrap());
+    assert!(ballots.find_by_uuid(1, uuid).await.unwrap().is_some());
+    assert!(!ballots
+        .save_with_voter_token(1, Uuid::new_v4(), last)
+        .await
+        .unwrap());
+
+    let roll = ballots.count_voter_tokens(1).await.unwrap();
+    assert_eq!((roll.issued, roll.used), (ROWS as i64, 1));
+}
+
+pub(super) async fn rankings_and_scores_are_created_in_bulk<R>(
+    items: impl ItemRepository,
+    ballots: impl BallotRepository,
+    rankings: R,
+) where
+    R: TransactableRankingRepository,
+{
+    items.create(new_item(1, "A")).await.unwrap();
+    let item_id = items.find_by_poll(1).await.unwrap()[0].id;
+    let mut ballot_ids = Vec::with_capacity(BALLOTS);
+    for _ in 0..BALLOTS {
+        let uuid = Uuid::new_v4();
+        ballots.save_ignoring_conflict(1, uuid).await.unwrap();
+        ballot_ids.push(ballots.find_by_uuid(1, uuid).await.unwrap().unwrap().id);
+    }
+
+    let mut txn = rankings.begin().await.unwrap();
+    let mut new_rankings = ballot_ids.iter().map(|&ballot_id| NewRanking {
+        ord: 0,
+        item_id,
+        ballot_id,
+    });
+    let mut statements = 0;
+    while new_rankings.len() != 0 {
+        rankings
+            .txn_create_bulk(&mut txn, &mut new_rankings)
+            .await
+            .unwrap();
+        statements += 1;
+    }
+    let mut new_scores = ballot_ids.iter().map(|&ballot_id| NewScore {
+        item_id,
+        ballot_id,
+        score: 5,
+    });
+    while new_scores.len() != 0 {
+        rankings
+            .txn_create_score_bulk(&mut txn, &mut new_scores)
+            .await
+            .unwrap();
+    }
+    rankings
+        .txn_remove_ballot_rankings(&mut txn, ballot_ids[0])
+        .await
+        .unwrap();
+    rankings
+        .txn_remove_ballot_scores(&mut txn, ballot_ids[0])
+        .await
+        .unwrap();
+    rankings.end(txn).await.unwrap();
+
+    assert!(statements > 1);
+    assert_eq!(rankings.get_by_poll(1).await.unwrap().len(), BALLOTS - 1);
+    let scores = rankings.get_scores_by_poll(1).await.unwrap();
+    assert_eq!(scores.len(), BALLOTS - 1);
+    assert!(scores.iter().all(|score| score.score == 5));
+}
+
+pub(super) async fn revisions_are_chained<R>(ballots: impl BallotRepository, rankings: R)
+where
+    R: TransactableRankingRepository,
+{
+    let uuid = Uuid::new_v4();
+    ballots.save_ignoring_conflict(1, uuid).await.unwrap();
+    let ballot_id = ballots.find_by_uuid(1, uuid).await.unwrap().unwrap().id;
+    let revision = |previous_hash: &str, hash: &str| NewBallotRevision {
+        poll_id: 1,
+        ballot_id,
+        items: "[]".to_string(),
+        created_at: datetime!(2026-10-18 09:30 UTC),
+        previous_hash: previous_hash.to_string(),
+        hash: hash.to_string(),
+    };
+
+    let mut txn = rankings.begin().await.unwrap();
+    assert_eq!(
+        rankings
+            .txn_find_last_revision_hash(&mut txn, 1)
+            .await
+            .unwrap(),
+        None
+    );
+    rankings
+        .txn_create_revision(&mut txn, revision("", "first"))
+        .await
+        .unwrap();
+    rankings
+        .txn_create_revision(&mut txn, revision("first", "second"))
+        .await
+        .unwrap();
+    assert_eq!(
+        rankings
+            .txn_find_last_revision_hash(&mut txn, 1)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("second")
+    );
+    rankings.end(txn).await.unwrap();
+
+    // Every revision follows a single one.
+    let mut txn = rankings.begin().await.unwrap();
+    assert!(rankings
+        .txn_create_revision(&mut txn, revision("first", "fork"))
+        .await
+        .is_err());
+    drop(txn);
+
+    let hashes: Vec<_> = rankings
+        .get_revisions_by_poll(1)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|revision| revision.hash)
+        .collect();
+    assert_eq!(hashes, ["first", "second"]);
+}
@@ -1,4 +1,4 @@
-use std::net::TcpListener;
+use std::{future::Future, net::TcpListener};
 
 use actix_cors::Cors;
 use actix_files::Files;
@@ -7,20 +7,36 @@ use actix_session::{storage::CookieSessionStore, SessionMiddleware};
 use actix_web::{
     cookie,
     dev::{ResourceDef, Server, ServiceRequest},
-    http, ResponseError,
+    http::{self, StatusCode},
+    middleware::Condition,
+    HttpResponse, ResponseError,
 };
 use actix_web::{web, App, HttpServer};
 use actix_web_flash_messages::storage::CookieMessageStore;
-use actix_web_flash_messages::FlashMessagesFramework;
+use actix_web_flash_messages::{FlashMessage, FlashMessagesFramework};
+use sailfish::TemplateOnce;
 use secrecy::ExposeSecret;
 use tracing_actix_web::{DefaultRootSpanBuilder, TracingLogger};
 
-use crate::{conf::Configuration, middleware::RedirectMiddleware, service};
+use crate::{
+    conf::Configuration,
+    middleware::{CorrelationMiddleware, MetricsMiddleware, RedirectMiddleware, RequestContext},
+    repository::RepositoryError,
+    service::{self, ServiceError},
+    view::ErrorView,
+};
 
+pub mod admin;
+pub mod api;
 pub mod ballot;
+pub mod events;
 pub mod health;
 pub mod index;
+pub mod leaderboard;
+pub mod metrics;
+pub mod poll;
 pub mod register;
+pub mod results;
 
 #[derive(thiserror::Error, Debug)]
 pub enum RouteError {
@@ -33,47 +49,164 @@ pub enum RouteError {
     #[error(transparent)]
     IdentityGetError(#[from] actix_identity::error::GetIdentityError),
 
+    #[error(transparent)]
+    SessionInsert(#[from] actix_session::SessionInsertError),
+
     #[error(transparent)]
     Service(#[from] service::ServiceError),
 
     #[error(transparent)]
     Unexpected(#[from] anyhow::Error),
+
+    #[error("Page not found")]
+    NotFound,
+}
+
+impl ResponseError for RouteError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::IdentityGetError(_) => StatusCode::UNAUTHORIZED,
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Service(e) => service_error_status(e),
+            Self::SailfishRender(_)
+            | Self::IdentityLoginError(_)
+            | Self::SessionInsert(_)
+            | Self::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        let message = public_message(status, self);
+        let context = RequestContext::current();
+        let request_id = context.request_id.map(|id| id.to_string());
+        if context.prefers_json {
+            return HttpResponse::build(status).json(api::ErrorBody::new(
+                error_code(status),
+                message,
+                request_id,
+            ));
+        }
+        let reason = status.canonical_reason().unwrap_or_default();
+        let body = ErrorView::new(status.as_u16(), reason, &message, request_id.clone())
+            .render_once()
+            .unwrap_or_else(|_| {
+                format!("{reason}: {message} ({})", request_id.unwrap_or_default())
+            });
+        HttpResponse::build(status)
+            .content_type(http::header::ContentType::html())
+            .body(body)
+    }
 }
 
-impl ResponseError for RouteError {}
+/// The status of a response for a service error. Invalid input is rejected with 400 or 422,
+/// changes that conflict with the state of a poll with 409, and a database that can not be
+/// reached with 503.
+pub(crate) fn service_error_status(e: &ServiceError) -> StatusCode {
+    match e {
+        ServiceError::Repository(RepositoryError::Sqlx(
+            sqlx::Error::PoolTimedOut
+            | sqlx::Error::PoolClosed
+            | sqlx::Error::Io(_)
+            | sqlx::Error::WorkerCrashed,
+        )) => StatusCode::SERVICE_UNAVAILABLE,
+        ServiceError::Repository(RepositoryError::Sqlx(sqlx::Error::RowNotFound)) => {
+            StatusCode::NOT_FOUND
+        }
+        ServiceError::Repository(RepositoryError::Constraint(_)) => StatusCode::CONFLICT,
+        ServiceError::Repository(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        ServiceError::Uuid(_) => StatusCode::BAD_REQUEST,
+        ServiceError::InvalidInviteCode | ServiceError::InvalidVoterToken => {
+            StatusCode::UNAUTHORIZED
+        }
+        ServiceError::PollNotOpen
+        | ServiceError::PollClosed
+        | ServiceError::BallotTypeLocked
+        | ServiceError::ItemsLocked
+        | ServiceError::WrongBallotType(_) => StatusCode::CONFLICT,
+        ServiceError::MissingPollTitle
+        | ServiceError::MissingItemTitle
+        | ServiceError::InvalidSchedule
+        | ServiceError::InvalidSeats
+        | ServiceError::ScoredPollSeats
+        | ServiceError::InvalidScore(_)
+        | ServiceError::InvalidTokenCount(_)
+        | ServiceError::InvalidBallot(_) => StatusCode::UNPROCESSABLE_ENTITY,
+    }
+}
+
+/// The code of an error response with the given status.
+pub(crate) fn error_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        _ => "internal_error",
+    }
+}
 
-pub fn serve<IS, BS, RS>(
+/// The message of an error that can be shown to the client. Details of server errors are only
+/// logged, they are not exposed.
+pub(crate) fn public_message(status: StatusCode, e: &impl std::fmt::Display) -> String {
+    match status {
+        StatusCode::SERVICE_UNAVAILABLE => "Service is temporarily unavailable".to_string(),
+        s if s.is_server_error() => "Internal server error".to_string(),
+        _ => e.to_string(),
+    }
+}
+
+pub fn serve<PS, IS, BS, RS>(
+    listener: TcpListener,
     config: &Configuration,
+    poll_service: PS,
     item_service: IS,
     ballot_service: BS,
     ranking_service: RS,
+    shutdown: impl Future<Output = ()> + Send + 'static,
 ) -> Result<Server, std::io::Error>
 where
+    PS: 'static + service::PollService,
     IS: 'static + service::ItemService,
     BS: 'static + service::BallotService,
     RS: 'static + service::RankingService,
 {
     let config = config.clone();
-    let listener = TcpListener::bind(config.application().address())?;
+    let shutdown_timeout = config.application().shutdown_timeout();
+    // Registrations and login attempts are counted across every worker.
+    let registration_limit = web::Data::new(register::RegistrationLimit::new(
+        config.registration().rate_limit(),
+    ));
+    let login_limit = web::Data::new(admin::login::LoginLimit::new(config.admin().rate_limit()));
+    let (shutdown_sender, shutdown_receiver) = tokio::sync::watch::channel(false);
+    let shutdown_signal = web::Data::new(events::ShutdownSignal::new(shutdown_receiver));
+    let readiness_checks = web::Data::new(health::ReadinessChecks::new(
+        config.health(),
+        config.tracing(),
+    ));
     let server = HttpServer::new(move || {
         App::new()
+            .app_data(registration_limit.clone())
+            .app_data(login_limit.clone())
+            .app_data(readiness_checks.clone())
+            .app_data(shutdown_signal.clone())
+            .app_data(web::Data::new(poll_service.clone()))
             .app_data(web::Data::new(item_service.clone()))
             .app_data(web::Data::new(ballot_service.clone()))
             .app_data(web::Data::new(ranking_service.clone()))
+            .app_data(web::Data::new(admin::AdminPassword(
+                config.admin().password().clone(),
+            )))
             .wrap(RedirectMiddleware::new(
-                "/ballot",
-                |r: &ServiceRequest| r.get_identity().is_ok(),
+                "/polls/{id}",
+                |r: &ServiceRequest| r.get_identity().is_err(),
                 vec![
-                    ResourceDef::new("/"),
-                    ResourceDef::new("/login"),
-                    ResourceDef::new("/register"),
+                    ResourceDef::new("/polls/{id}/ballot"),
+                    ResourceDef::new("/polls/{id}/ballot/scores"),
                 ],
             ))
-            .wrap(RedirectMiddleware::new(
-                "/",
-                |r: &ServiceRequest| r.get_identity().is_err(),
-                vec![ResourceDef::new("/ballot")],
-            ))
             .wrap(middleware_flash_message(
                 config.application().flash_message_minimum_level(),
                 config.cookie().signing_key().expose_secret().as_bytes(),
@@ -85,23 +218,124 @@ where
                 config.cookie().session_cookie_name(),
             ))
             .wrap(middleware_cors(config.application().url()))
+            .wrap(CorrelationMiddleware)
+            .wrap(Condition::new(
+                config.metrics().enabled(),
+                MetricsMiddleware,
+            ))
             .wrap(middleware_tracing_logger())
-            .route("/", web::get().to(index::get::<RS>))
-            .route("/health", web::get().to(health::get))
-            .route("/register", web::post().to(register::post::<BS>))
+            .route("/", web::get().to(index::get::<PS>))
+            .route("/health", web::get().to(health::live))
+            .route("/health/live", web::get().to(health::live))
+            .route("/health/ready", web::get().to(health::ready::<PS>))
+            .configure(|cfg| {
+                if config.metrics().enabled() {
+                    cfg.route(config.metrics().path(), web::get().to(metrics::get));
+                }
+            })
+            .route("/register", web::post().to(register::post::<PS, BS>))
+            .route("/polls/{id}", web::get().to(poll::get::<PS, BS, RS>))
+            .route("/polls/{id}/events", web::get().to(events::get::<PS, RS>))
+            .route("/polls/{id}/results", web::get().to(results::get::<PS, RS>))
+            .route(
+                "/polls/{id}/leaderboard",
+                web::get().to(leaderboard::get::<PS, RS>),
+            )
             .service(
-                web::resource("/ballot")
-                    .route(web::get().to(ballot::get::<IS, BS, RS>))
-                    .route(web::post().to(ballot::post::<BS, RS>)),
+                web::resource("/polls/{id}/ballot")
+                    .route(web::get().to(ballot::get::<PS, IS, BS, RS>))
+                    .route(web::post().to(ballot::post::<PS, BS, RS>)),
+            )
+            .route(
+                "/polls/{id}/ballot/scores",
+                web::post().to(ballot::post_scores::<PS, BS, RS>),
             )
+            .route("/admin", web::get().to(admin::index::get::<PS>))
+            .route("/admin/polls", web::post().to(admin::poll::create::<PS>))
+            .service(
+                web::resource("/admin/login")
+                    .route(web::get().to(admin::login::get))
+                    .route(web::post().to(admin::login::post)),
+            )
+            .route("/admin/logout", web::post().to(admin::login::logout))
+            .service(
+                web::resource("/admin/polls/{id}/items")
+                    .route(web::get().to(admin::item::get::<PS, IS>))
+                    .route(web::post().to(admin::item::create::<IS>)),
+            )
+            .route(
+                "/admin/polls/{id}/items/order",
+                web::post().to(admin::item::reorder::<IS>),
+            )
+            .route(
+                "/admin/polls/{id}/items/{item_id}",
+                web::post().to(admin::item::update::<IS>),
+            )
+            .route(
+                "/admin/polls/{id}/items/{item_id}/done",
+                web::post().to(admin::item::set_done::<IS>),
+            )
+            .route(
+                "/admin/polls/{id}/items/{item_id}/retire",
+                web::post().to(admin::item::set_retired::<IS>),
+            )
+            .route(
+                "/admin/polls/{id}/lifecycle",
+                web::post().to(admin::poll::update_lifecycle::<PS, RS>),
+            )
+            .route(
+                "/admin/polls/{id}/seats",
+                web::post().to(admin::poll::update_seats::<PS>),
+            )
+            .route(
+                "/admin/polls/{id}/ballot_type",
+                web::post().to(admin::poll::update_ballot_type::<PS>),
+            )
+            .service(
+                web::resource("/admin/polls/{id}/tokens")
+                    .route(web::get().to(admin::token::get::<PS, BS>))
+                    .route(web::post().to(admin::token::issue::<PS, BS>)),
+            )
+            .route(
+                "/admin/polls/{id}/audit",
+                web::get().to(admin::audit::get::<PS, RS>),
+            )
+            .service(api::scope::<PS, IS, BS, RS>())
             .service(Files::new("/static", "static").show_files_listing())
+            .default_service(web::to(not_found))
     })
+    .shutdown_timeout(shutdown_timeout.as_secs())
+    .disable_signals()
     .listen(listener)?
     .run();
 
+    // The streams of events are ended before the server waits for its connections to close.
+    let handle = server.handle();
+    tokio::spawn(async move {
+        shutdown.await;
+        tracing::info!("Shutting down");
+        let _ = shutdown_sender.send(true);
+        handle.stop(true).await;
+    });
     Ok(server)
 }
 
+/// Send the user back to the list of polls when the requested poll does not exist.
+fn poll_not_found() -> HttpResponse {
+    FlashMessage::new(
+        "Poll not found".to_string(),
+        actix_web_flash_messages::Level::Error,
+    )
+    .send();
+    HttpResponse::SeeOther()
+        .insert_header((http::header::LOCATION, "/"))
+        .finish()
+}
+
+async fn not_found() -> Result<HttpResponse, RouteError> {
+    Err(RouteError::NotFound)
+}
+
 fn middleware_tracing_logger() -> TracingLogger<DefaultRootSpanBuilder> {
     TracingLogger::default()
 }
@@ -109,7 +343,7 @@ fn middleware_tracing_logger() -> TracingLogger<DefaultRootSpanBuilder> {
 fn middleware_cors(url: &str) -> Cors {
     Cors::default()
         .allowed_origin(url)
-        .allowed_methods([http::Method::GET, http::Method::POST])
+        .allowed_methods([http::Method::GET, http::Method::POST, http::Method::PUT])
         .supports_credentials()
         .max_age(3600)
 }
@@ -0,0 +1,66 @@
+use std::future::{ready, Ready};
+
+use actix_session::{Session, SessionExt};
+use actix_web::{
+    dev::Payload, error::InternalError, http::header, FromRequest, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use secrecy::Secret;
+
+use crate::service::ServiceError;
+
+pub mod audit;
+pub mod index;
+pub mod item;
+pub mod login;
+pub mod poll;
+pub mod token;
+
+/// Key of the session entry marking the session as belonging to an administrator.
+const ADMIN_SESSION_KEY: &str = "admin";
+
+/// The password that must be given for logging in as an administrator.
+pub struct AdminPassword(pub Secret<String>);
+
+fn is_admin_session(session: &Session) -> bool {
+    matches!(session.get::<bool>(ADMIN_SESSION_KEY), Ok(Some(true)))
+}
+
+/// Proof that the request was made by a logged in administrator. Every handler of the
+/// administration takes it, so that a request which is not logged in is sent to the login page
+/// whatever path it was routed by.
+#[derive(Debug)]
+pub struct Admin;
+
+impl FromRequest for Admin {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _: &mut Payload) -> Self::Future {
+        if is_admin_session(&request.get_session()) {
+            return ready(Ok(Admin));
+        }
+        let response = see_other("/admin/login".to_string());
+        ready(Err(
+            InternalError::from_response("Not logged in", response).into()
+        ))
+    }
+}
+
+fn see_other(location: String) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, location))
+        .finish()
+}
+
+fn flash_success(message: &str) {
+    FlashMessage::new(
+        message.to_string(),
+        actix_web_flash_messages::Level::Success,
+    )
+    .send();
+}
+
+fn flash_error(error: &ServiceError) {
+    FlashMessage::new(error.to_string(), actix_web_flash_messages::Level::Error).send();
+}
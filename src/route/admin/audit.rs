@@ -0,0 +1,64 @@
+use actix_web::{web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    model::{AuditReport, Item},
+    route::{api::result::ResultStatus, poll_not_found, RouteError},
+    service::{PollService, RankingService},
+};
+
+use super::Admin;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    /// Replay the poll with the ballots as they were at this time.
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize)]
+struct AuditData {
+    report: AuditReport,
+    #[serde(with = "time::serde::rfc3339::option")]
+    at: Option<OffsetDateTime>,
+    result: Option<AuditResult>,
+}
+
+/// The replayed result in the same form as the result of the API.
+#[derive(Serialize)]
+struct AuditResult {
+    status: ResultStatus,
+    items: Vec<Item>,
+}
+
+#[tracing::instrument(skip(_admin, poll_service, ranking_service))]
+pub async fn get<PS, RS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    query: web::Query<AuditQuery>,
+    poll_service: web::Data<PS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    RS: RankingService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let report = ranking_service.get_audit_report(poll.id).await?;
+    let result = match query.at {
+        Some(at) => {
+            let (status, items) = ResultStatus::of(ranking_service.get_result_at(&poll, at).await?);
+            Some(AuditResult { status, items })
+        }
+        None => None,
+    };
+    Ok(HttpResponse::Ok().json(AuditData {
+        report,
+        at: query.at,
+        result,
+    }))
+}
@@ -0,0 +1,21 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sailfish::TemplateOnce;
+
+use crate::{route::RouteError, service::PollService, view::AdminIndexView};
+
+use super::Admin;
+
+#[tracing::instrument(skip(_admin, flashes, poll_service))]
+pub async fn get<PS>(
+    _admin: Admin,
+    flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+{
+    let polls = poll_service.get_polls().await?;
+    let body = AdminIndexView::new(&polls, &flashes).render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
@@ -0,0 +1,185 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sailfish::TemplateOnce;
+use serde::Deserialize;
+
+use crate::{
+    route::{poll_not_found, RouteError},
+    service::{ItemService, PollService, ServiceError},
+    view::AdminItemsView,
+};
+
+use super::{flash_error, flash_success, see_other, Admin};
+
+#[tracing::instrument(skip(_admin, flashes, poll_service, item_service))]
+pub async fn get<PS, IS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
+    item_service: web::Data<IS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    IS: ItemService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let items = item_service.get_poll_items(poll.id).await?;
+    let body = AdminItemsView::new(&poll, &items, &flashes).render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemFormData {
+    title: String,
+    content: String,
+}
+
+#[tracing::instrument(skip(_admin, item_service))]
+pub async fn create<IS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    form: web::Form<ItemFormData>,
+    item_service: web::Data<IS>,
+) -> Result<HttpResponse, RouteError>
+where
+    IS: ItemService,
+{
+    let poll_id = poll_id.into_inner();
+    match item_service
+        .create_item(poll_id, &form.title, &form.content)
+        .await
+    {
+        Ok(()) => flash_success("Item created"),
+        Err(e @ (ServiceError::MissingItemTitle | ServiceError::ItemsLocked)) => flash_error(&e),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(items_page(poll_id))
+}
+
+#[tracing::instrument(skip(_admin, item_service))]
+pub async fn update<IS>(
+    _admin: Admin,
+    path: web::Path<(i32, i32)>,
+    form: web::Form<ItemFormData>,
+    item_service: web::Data<IS>,
+) -> Result<HttpResponse, RouteError>
+where
+    IS: ItemService,
+{
+    let (poll_id, item_id) = path.into_inner();
+    match item_service
+        .update_item(poll_id, item_id, &form.title, &form.content)
+        .await
+    {
+        Ok(true) => flash_success("Item updated"),
+        Ok(false) => flash_item_not_found(),
+        Err(e @ (ServiceError::MissingItemTitle | ServiceError::ItemsLocked)) => flash_error(&e),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(items_page(poll_id))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemOrderData {
+    items: Vec<String>,
+}
+
+#[tracing::instrument(skip(_admin, item_service))]
+pub async fn reorder<IS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    order: web::Json<ItemOrderData>,
+    item_service: web::Data<IS>,
+) -> Result<HttpResponse, RouteError>
+where
+    IS: ItemService,
+{
+    let item_ids: Vec<i32> = order
+        .items
+        .iter()
+        .filter_map(|id| str::parse(id).ok())
+        .collect();
+    item_service
+        .reorder_items(poll_id.into_inner(), &item_ids)
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ItemFlagData {
+    value: bool,
+}
+
+#[tracing::instrument(skip(_admin, item_service))]
+pub async fn set_done<IS>(
+    _admin: Admin,
+    path: web::Path<(i32, i32)>,
+    form: web::Form<ItemFlagData>,
+    item_service: web::Data<IS>,
+) -> Result<HttpResponse, RouteError>
+where
+    IS: ItemService,
+{
+    let (poll_id, item_id) = path.into_inner();
+    match item_service
+        .set_item_done(poll_id, item_id, form.value)
+        .await
+    {
+        Ok(true) => {
+            flash_success(if form.value {
+                "Item marked as done"
+            } else {
+                "Item marked as not done"
+            });
+        }
+        Ok(false) => flash_item_not_found(),
+        Err(e @ ServiceError::ItemsLocked) => flash_error(&e),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(items_page(poll_id))
+}
+
+#[tracing::instrument(skip(_admin, item_service))]
+pub async fn set_retired<IS>(
+    _admin: Admin,
+    path: web::Path<(i32, i32)>,
+    form: web::Form<ItemFlagData>,
+    item_service: web::Data<IS>,
+) -> Result<HttpResponse, RouteError>
+where
+    IS: ItemService,
+{
+    let (poll_id, item_id) = path.into_inner();
+    match item_service
+        .set_item_retired(poll_id, item_id, form.value)
+        .await
+    {
+        Ok(true) => {
+            flash_success(if form.value {
+                "Item retired"
+            } else {
+                "Item restored"
+            });
+        }
+        Ok(false) => flash_item_not_found(),
+        Err(e @ ServiceError::ItemsLocked) => flash_error(&e),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(items_page(poll_id))
+}
+
+fn items_page(poll_id: i32) -> HttpResponse {
+    see_other(format!("/admin/polls/{poll_id}/items"))
+}
+
+fn flash_item_not_found() {
+    FlashMessage::new(
+        "Item not found".to_string(),
+        actix_web_flash_messages::Level::Error,
+    )
+    .send();
+}
@@ -0,0 +1,90 @@
+use actix_session::Session;
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sailfish::TemplateOnce;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    conf::RateLimitConfiguration, rate_limit::AddressLimit, route::RouteError, view::AdminLoginView,
+};
+
+use super::{is_admin_session, see_other, AdminPassword, ADMIN_SESSION_KEY};
+
+/// Limit on the number of login attempts from an IP address.
+pub struct LoginLimit(AddressLimit);
+
+impl LoginLimit {
+    pub fn new(config: &RateLimitConfiguration) -> Self {
+        Self(AddressLimit::new(config))
+    }
+}
+
+/// Compare the passwords in a time that does not depend on their content. Their hashes are
+/// compared, so that the time does not depend on their length either.
+fn is_password(given: &str, expected: &str) -> bool {
+    Sha256::digest(given.as_bytes())
+        .ct_eq(&Sha256::digest(expected.as_bytes()))
+        .into()
+}
+
+#[tracing::instrument(skip(session, flashes))]
+pub async fn get(
+    session: Session,
+    flashes: IncomingFlashMessages,
+) -> Result<HttpResponse, RouteError> {
+    if is_admin_session(&session) {
+        return Ok(see_other("/admin".to_string()));
+    }
+    let body = AdminLoginView::new(&flashes).render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
+
+#[derive(Deserialize)]
+pub struct AdminLoginFormData {
+    password: Secret<String>,
+}
+
+#[tracing::instrument(skip(request, session, form, limit, password))]
+pub async fn post(
+    request: HttpRequest,
+    session: Session,
+    form: web::Form<AdminLoginFormData>,
+    limit: web::Data<LoginLimit>,
+    password: web::Data<AdminPassword>,
+) -> Result<HttpResponse, RouteError> {
+    if !limit.0.check(&request) {
+        tracing::warn!("Too many login attempts");
+        FlashMessage::new(
+            "Too many login attempts, try again later".to_string(),
+            actix_web_flash_messages::Level::Error,
+        )
+        .send();
+        return Ok(see_other("/admin/login".to_string()));
+    }
+    if !is_password(form.password.expose_secret(), password.0.expose_secret()) {
+        tracing::warn!("Invalid administrator password");
+        FlashMessage::new(
+            "Invalid password".to_string(),
+            actix_web_flash_messages::Level::Error,
+        )
+        .send();
+        return Ok(see_other("/admin/login".to_string()));
+    }
+    session.renew();
+    session.insert(ADMIN_SESSION_KEY, true)?;
+    Ok(see_other("/admin".to_string()))
+}
+
+#[tracing::instrument(skip(session))]
+pub async fn logout(session: Session) -> HttpResponse {
+    session.remove(ADMIN_SESSION_KEY);
+    FlashMessage::new(
+        "Logged out".to_string(),
+        actix_web_flash_messages::Level::Success,
+    )
+    .send();
+    see_other("/".to_string())
+}
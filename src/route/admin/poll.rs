@@ -0,0 +1,178 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use serde::Deserialize;
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use crate::{
+    model::{BallotType, PollStatus},
+    route::{poll_not_found, RouteError},
+    service::{PollService, RankingService, ServiceError},
+    view::DATETIME_LOCAL_FORMAT,
+};
+
+use super::{flash_error, flash_success, see_other, Admin};
+
+#[derive(Debug, Deserialize)]
+pub struct PollFormData {
+    title: String,
+    description: String,
+}
+
+#[tracing::instrument(skip(_admin, poll_service))]
+pub async fn create<PS>(
+    _admin: Admin,
+    form: web::Form<PollFormData>,
+    poll_service: web::Data<PS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+{
+    match poll_service
+        .create_poll(&form.title, &form.description)
+        .await
+    {
+        Ok(poll_id) => {
+            flash_success("Poll created");
+            Ok(see_other(format!("/admin/polls/{poll_id}/items")))
+        }
+        Err(e @ ServiceError::MissingPollTitle) => {
+            flash_error(&e);
+            Ok(see_other("/admin".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollLifecycleFormData {
+    status: PollStatus,
+    opens_at: String,
+    closes_at: String,
+    /// Offsets from UTC of the times in minutes, which are set by the browser. Times are in UTC
+    /// when they are not given.
+    #[serde(default)]
+    opens_at_offset: i32,
+    #[serde(default)]
+    closes_at_offset: i32,
+}
+
+#[tracing::instrument(skip(_admin, poll_service, ranking_service))]
+pub async fn update_lifecycle<PS, RS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    form: web::Form<PollLifecycleFormData>,
+    poll_service: web::Data<PS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    RS: RankingService,
+{
+    let poll_id = poll_id.into_inner();
+    let items_page = see_other(format!("/admin/polls/{poll_id}/items"));
+    let (Some(opens_at), Some(closes_at)) = (
+        parse_datetime_local(&form.opens_at, form.opens_at_offset),
+        parse_datetime_local(&form.closes_at, form.closes_at_offset),
+    ) else {
+        FlashMessage::new(
+            "Invalid time".to_string(),
+            actix_web_flash_messages::Level::Error,
+        )
+        .send();
+        return Ok(items_page);
+    };
+    match poll_service
+        .update_poll_lifecycle(poll_id, form.status, opens_at, closes_at)
+        .await
+    {
+        Ok(true) => {}
+        Ok(false) => return Ok(poll_not_found()),
+        Err(e @ (ServiceError::PollClosed | ServiceError::InvalidSchedule)) => {
+            flash_error(&e);
+            return Ok(items_page);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    let poll = match poll_service.find_poll(poll_id).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    // Take the final tally right away instead of when it is first looked at.
+    if ranking_service.get_final_tally(&poll).await?.is_some() {
+        flash_success("Poll closed");
+    } else {
+        flash_success("Poll updated");
+    }
+    Ok(items_page)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollSeatsFormData {
+    seats: i32,
+}
+
+#[tracing::instrument(skip(_admin, poll_service))]
+pub async fn update_seats<PS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    form: web::Form<PollSeatsFormData>,
+    poll_service: web::Data<PS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+{
+    let poll_id = poll_id.into_inner();
+    match poll_service.update_poll_seats(poll_id, form.seats).await {
+        Ok(true) => flash_success("Seats updated"),
+        Ok(false) => return Ok(poll_not_found()),
+        Err(
+            e @ (ServiceError::PollClosed
+            | ServiceError::InvalidSeats
+            | ServiceError::ScoredPollSeats),
+        ) => flash_error(&e),
+        Err(e) => return Err(e.into()),
+    }
+    Ok(see_other(format!("/admin/polls/{poll_id}/items")))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PollBallotTypeFormData {
+    ballot_type: BallotType,
+}
+
+#[tracing::instrument(skip(_admin, poll_service))]
+pub async fn update_ballot_type<PS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    form: web::Form<PollBallotTypeFormData>,
+    poll_service: web::Data<PS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+{
+    let poll_id = poll_id.into_inner();
+    match poll_service
+        .update_poll_ballot_type(poll_id, form.ballot_type)
+        .await
+    {
+        Ok(true) => flash_success("Ballot type updated"),
+        Ok(false) => return Ok(poll_not_found()),
+        Err(e @ (ServiceError::BallotTypeLocked | ServiceError::ScoredPollSeats)) => {
+            flash_error(&e)
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(see_other(format!("/admin/polls/{poll_id}/items")))
+}
+
+/// Parse the value of a `datetime-local` input as a time with the given offset from UTC in
+/// minutes. An empty value is no time, `None` is returned if the value or the offset is invalid.
+fn parse_datetime_local(value: &str, offset_minutes: i32) -> Option<Option<OffsetDateTime>> {
+    if value.is_empty() {
+        return Some(None);
+    }
+    let offset = UtcOffset::from_whole_seconds(offset_minutes.checked_mul(60)?).ok()?;
+    PrimitiveDateTime::parse(value, DATETIME_LOCAL_FORMAT)
+        .ok()
+        .map(|t| Some(t.assume_offset(offset).to_offset(UtcOffset::UTC)))
+}
@@ -0,0 +1,83 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sailfish::TemplateOnce;
+use serde::Deserialize;
+
+use crate::{
+    route::{poll_not_found, RouteError},
+    service::{BallotService, PollService, ServiceError},
+    view::AdminTokensView,
+};
+
+use super::{flash_error, see_other, Admin};
+
+#[tracing::instrument(skip(_admin, flashes, poll_service, ballot_service))]
+pub async fn get<PS, BS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
+    ballot_service: web::Data<BS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    BS: BallotService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let roll = ballot_service.get_voter_roll(poll.id).await?;
+    let body = AdminTokensView::new(
+        &poll,
+        ballot_service.registration_mode(),
+        roll,
+        &[],
+        &flashes,
+    )
+    .render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IssueTokensFormData {
+    count: usize,
+}
+
+/// Issue new voter tokens and show them right away, since they can not be found again.
+#[tracing::instrument(skip(_admin, flashes, poll_service, ballot_service))]
+pub async fn issue<PS, BS>(
+    _admin: Admin,
+    poll_id: web::Path<i32>,
+    form: web::Form<IssueTokensFormData>,
+    flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
+    ballot_service: web::Data<BS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    BS: BallotService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let tokens = match ballot_service.issue_voter_tokens(poll.id, form.count).await {
+        Ok(v) => v,
+        Err(e @ ServiceError::InvalidTokenCount(_)) => {
+            flash_error(&e);
+            return Ok(see_other(format!("/admin/polls/{}/tokens", poll.id)));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let roll = ballot_service.get_voter_roll(poll.id).await?;
+    let body = AdminTokensView::new(
+        &poll,
+        ballot_service.registration_mode(),
+        roll,
+        &tokens,
+        &flashes,
+    )
+    .render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
@@ -0,0 +1,182 @@
+use actix_identity::IdentityExt;
+use actix_web::{
+    http::{header, StatusCode},
+    web, HttpRequest, HttpResponse, ResponseError, Scope,
+};
+use serde::Serialize;
+
+use crate::{
+    middleware::RequestContext,
+    service::{self, BallotProblem, BallotService, ItemService, PollService, RankingService},
+};
+
+pub mod ballot;
+pub mod item;
+pub mod result;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ApiError {
+    #[error("{0}")]
+    BadRequest(String),
+
+    #[error("Missing voter credentials")]
+    Unauthorized,
+
+    #[error("Poll not found")]
+    PollNotFound,
+
+    #[error("Ballot not found, register to vote in this poll")]
+    BallotNotFound,
+
+    #[error("Resource not found")]
+    NotFound,
+
+    #[error(transparent)]
+    Service(#[from] service::ServiceError),
+}
+
+impl ApiError {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::Unauthorized => "unauthorized",
+            Self::PollNotFound => "poll_not_found",
+            Self::BallotNotFound => "ballot_not_found",
+            Self::NotFound => "not_found",
+            Self::Service(service::ServiceError::PollNotOpen) => "poll_not_open",
+            Self::Service(service::ServiceError::PollClosed) => "poll_closed",
+            Self::Service(service::ServiceError::InvalidBallot(_)) => "invalid_ballot",
+            Self::Service(e) => super::error_code(super::service_error_status(e)),
+        }
+    }
+}
+
+/// The body of an error response, `request_id` matches the one of the request's tracing span.
+#[derive(Serialize)]
+pub(crate) struct ErrorBody<'a> {
+    code: &'a str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    problems: Vec<ProblemBody>,
+}
+
+impl<'a> ErrorBody<'a> {
+    pub(crate) fn new(code: &'a str, message: String, request_id: Option<String>) -> Self {
+        Self {
+            code,
+            message,
+            request_id,
+            problems: Vec::new(),
+        }
+    }
+}
+
+/// A problem of a rejected ballot, along with the item that caused it when there is one.
+#[derive(Serialize)]
+struct ProblemBody {
+    code: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item_id: Option<i32>,
+    message: String,
+}
+
+impl From<&BallotProblem> for ProblemBody {
+    fn from(problem: &BallotProblem) -> Self {
+        let item_id = match problem {
+            BallotProblem::InvalidItemId(_) => None,
+            BallotProblem::UnknownItem(id)
+            | BallotProblem::InactiveItem(id)
+            | BallotProblem::DuplicateItem(id) => Some(*id),
+        };
+        Self {
+            code: problem.code(),
+            item_id,
+            message: problem.to_string(),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized => StatusCode::UNAUTHORIZED,
+            Self::PollNotFound | Self::BallotNotFound | Self::NotFound => StatusCode::NOT_FOUND,
+            Self::Service(e) => super::service_error_status(e),
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        // Internal errors are logged by the tracing middleware, their details are not exposed.
+        let status = self.status_code();
+        let message = super::public_message(status, self);
+        let request_id = RequestContext::current()
+            .request_id
+            .map(|id| id.to_string());
+        let problems = match self {
+            Self::Service(service::ServiceError::InvalidBallot(problems)) => {
+                problems.iter().map(ProblemBody::from).collect()
+            }
+            _ => Vec::new(),
+        };
+        HttpResponse::build(status).json(ErrorBody {
+            problems,
+            ..ErrorBody::new(self.code(), message, request_id)
+        })
+    }
+}
+
+/// Routes of the first version of the JSON API.
+pub fn scope<PS, IS, BS, RS>() -> Scope
+where
+    PS: 'static + PollService,
+    IS: 'static + ItemService,
+    BS: 'static + BallotService,
+    RS: 'static + RankingService,
+{
+    web::scope("/api/v1")
+        .app_data(
+            web::JsonConfig::default()
+                .error_handler(|err, _| ApiError::BadRequest(err.to_string()).into()),
+        )
+        .app_data(
+            web::PathConfig::default()
+                .error_handler(|err, _| ApiError::BadRequest(err.to_string()).into()),
+        )
+        .route("/polls/{id}/items", web::get().to(item::get::<PS, IS>))
+        .service(
+            web::resource("/polls/{id}/ballot")
+                .route(web::get().to(ballot::get::<IS, BS>))
+                .route(web::put().to(ballot::put::<IS, BS, RS>)),
+        )
+        .route("/polls/{id}/result", web::get().to(result::get::<PS, RS>))
+        .default_service(web::to(not_found))
+}
+
+async fn not_found() -> Result<HttpResponse, ApiError> {
+    Err(ApiError::NotFound)
+}
+
+/// Get the UUID of the voter either from the bearer token or from the identity cookie.
+fn voter_uuid(request: &HttpRequest) -> Option<String> {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|v| v.trim().to_string());
+    bearer.or_else(|| request.get_identity().and_then(|i| i.id()).ok())
+}
+
+/// Make sure that the poll exists before doing anything with it.
+async fn find_poll<PS>(poll_service: &PS, poll_id: i32) -> Result<crate::model::Poll, ApiError>
+where
+    PS: PollService,
+{
+    poll_service
+        .find_poll(poll_id)
+        .await?
+        .ok_or(ApiError::PollNotFound)
+}
@@ -0,0 +1,137 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    model::{Ballot, Item},
+    service::{BallotProblem, BallotService, ItemService, RankingService, ServiceError},
+};
+
+use super::{voter_uuid, ApiError};
+
+#[derive(Serialize)]
+struct BallotResponse {
+    poll_id: i32,
+    uuid: Uuid,
+    ranks: Vec<Vec<Item>>,
+    unranked: Vec<Item>,
+}
+
+#[tracing::instrument(skip(request, item_service, ballot_service))]
+pub async fn get<IS, BS>(
+    request: HttpRequest,
+    poll_id: web::Path<i32>,
+    item_service: web::Data<IS>,
+    ballot_service: web::Data<BS>,
+) -> Result<HttpResponse, ApiError>
+where
+    IS: ItemService,
+    BS: BallotService,
+{
+    let ballot = find_ballot(&request, poll_id.into_inner(), ballot_service.get_ref()).await?;
+    ballot_response(ballot, item_service.get_ref()).await
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BallotRankingsData {
+    items: Vec<RankData>,
+}
+
+/// A rank of a ballot, given either as a single item id or as the ids of equally preferred items.
+/// Ids are kept as they were sent, so that every id which is not a number can be reported at once.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RankData {
+    Tied(Vec<Value>),
+    Item(Value),
+}
+
+impl RankData {
+    fn ids(&self) -> &[Value] {
+        match self {
+            Self::Tied(ids) => ids,
+            Self::Item(id) => std::slice::from_ref(id),
+        }
+    }
+}
+
+/// The ranks of the ballot, or a problem for every id that is not an item id.
+fn parse_ranks(ranks: &[RankData]) -> Result<Vec<Vec<i32>>, ServiceError> {
+    let mut problems = Vec::new();
+    let ranks = ranks
+        .iter()
+        .map(|rank| {
+            rank.ids()
+                .iter()
+                .filter_map(|id| {
+                    let item_id = id.as_i64().and_then(|id| i32::try_from(id).ok());
+                    if item_id.is_none() {
+                        let id = match id {
+                            Value::String(id) => id.clone(),
+                            id => id.to_string(),
+                        };
+                        problems.push(BallotProblem::InvalidItemId(id));
+                    }
+                    item_id
+                })
+                .collect()
+        })
+        .collect();
+    if problems.is_empty() {
+        Ok(ranks)
+    } else {
+        Err(ServiceError::InvalidBallot(problems))
+    }
+}
+
+#[tracing::instrument(skip(request, item_service, ballot_service, ranking_service))]
+pub async fn put<IS, BS, RS>(
+    request: HttpRequest,
+    poll_id: web::Path<i32>,
+    rankings: web::Json<BallotRankingsData>,
+    item_service: web::Data<IS>,
+    ballot_service: web::Data<BS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, ApiError>
+where
+    IS: ItemService,
+    BS: BallotService,
+    RS: RankingService,
+{
+    let ballot = find_ballot(&request, poll_id.into_inner(), ballot_service.get_ref()).await?;
+    let ranks = parse_ranks(&rankings.items)?;
+    ranking_service
+        .update_ballot_rankings(&ballot, &ranks)
+        .await?;
+    ballot_response(ballot, item_service.get_ref()).await
+}
+
+/// Find the ballot of the voter making the request. Polls that do not exist have no ballot.
+async fn find_ballot<BS>(
+    request: &HttpRequest,
+    poll_id: i32,
+    ballot_service: &BS,
+) -> Result<Ballot, ApiError>
+where
+    BS: BallotService,
+{
+    let uuid = voter_uuid(request).ok_or(ApiError::Unauthorized)?;
+    ballot_service
+        .find_ballot(poll_id, &uuid)
+        .await?
+        .ok_or(ApiError::BallotNotFound)
+}
+
+async fn ballot_response<IS>(ballot: Ballot, item_service: &IS) -> Result<HttpResponse, ApiError>
+where
+    IS: ItemService,
+{
+    let (ranks, unranked) = item_service.get_ballot_items(&ballot).await?;
+    Ok(HttpResponse::Ok().json(BallotResponse {
+        poll_id: ballot.poll_id,
+        uuid: ballot.uuid,
+        ranks,
+        unranked,
+    }))
+}
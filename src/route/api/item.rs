@@ -0,0 +1,29 @@
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+use crate::{
+    model::Item,
+    service::{ItemService, PollService},
+};
+
+use super::{find_poll, ApiError};
+
+#[derive(Serialize)]
+struct ItemsResponse {
+    items: Vec<Item>,
+}
+
+#[tracing::instrument(skip(poll_service, item_service))]
+pub async fn get<PS, IS>(
+    poll_id: web::Path<i32>,
+    poll_service: web::Data<PS>,
+    item_service: web::Data<IS>,
+) -> Result<HttpResponse, ApiError>
+where
+    PS: PollService,
+    IS: ItemService,
+{
+    let poll = find_poll(poll_service.get_ref(), poll_id.into_inner()).await?;
+    let items = item_service.get_poll_items(poll.id).await?;
+    Ok(HttpResponse::Ok().json(ItemsResponse { items }))
+}
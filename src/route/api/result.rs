@@ -0,0 +1,74 @@
+use actix_web::{web, HttpResponse};
+use serde::Serialize;
+
+use crate::{
+    model::{BallotType, Item, PollStatus, TallyResult},
+    service::{PollService, RankingService},
+};
+
+use super::{find_poll, ApiError};
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ResultStatus {
+    Winner,
+    Tied,
+    NoWinner,
+}
+
+impl ResultStatus {
+    /// The status of the result with the winner, or every tied item.
+    pub(crate) fn of(result: TallyResult) -> (Self, Vec<Item>) {
+        match result {
+            TallyResult::NoWinner => (Self::NoWinner, Vec::new()),
+            TallyResult::Tied(items) => (Self::Tied, items),
+            TallyResult::Winner(item) => (Self::Winner, vec![item]),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ResultResponse {
+    poll_id: i32,
+    /// The result of a closed poll is final.
+    poll_status: PollStatus,
+    /// The method that decides the poll, which is the method of the ballot type for scored
+    /// ballots and the single transferable vote for ranked polls with several seats.
+    method: &'static str,
+    ballot_type: BallotType,
+    status: ResultStatus,
+    /// The winner, or every tied item. Empty when there is no winner.
+    items: Vec<Item>,
+    seats: usize,
+    /// The winner, or every item that was elected by the single transferable vote when the
+    /// poll has more than one seat.
+    elected: Vec<Item>,
+}
+
+#[tracing::instrument(skip(poll_service, ranking_service))]
+pub async fn get<PS, RS>(
+    poll_id: web::Path<i32>,
+    poll_service: web::Data<PS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, ApiError>
+where
+    PS: PollService,
+    RS: RankingService,
+{
+    let poll = find_poll(poll_service.get_ref(), poll_id.into_inner()).await?;
+    let (result, elected) = futures::try_join!(
+        ranking_service.get_result(&poll),
+        ranking_service.get_best_items(&poll),
+    )?;
+    let (status, items) = ResultStatus::of(result);
+    Ok(HttpResponse::Ok().json(ResultResponse {
+        poll_id: poll.id,
+        poll_status: poll.current_status(),
+        method: poll.method(ranking_service.voting_method()).1,
+        ballot_type: poll.ballot_type,
+        status,
+        items,
+        seats: poll.elected_seats(),
+        elected,
+    }))
+}
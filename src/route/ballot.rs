@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use actix_identity::Identity;
 use actix_web::{http::header, web, HttpResponse};
 use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
@@ -5,49 +7,59 @@ use sailfish::TemplateOnce;
 use serde::Deserialize;
 
 use crate::{
-    service::{BallotService, ItemService, RankingService},
+    service::{
+        BallotProblem, BallotService, ItemService, PollService, RankingService, ServiceError,
+    },
     view::{BallotView, BestItemView},
 };
 
-use super::RouteError;
+use super::{poll_not_found, RouteError};
 
-#[tracing::instrument(skip(identity, flashes, item_service, ballot_service, ranking_service))]
-pub async fn get<IS, BS, RS>(
+#[tracing::instrument(skip(
+    identity,
+    flashes,
+    poll_service,
+    item_service,
+    ballot_service,
+    ranking_service
+))]
+pub async fn get<PS, IS, BS, RS>(
+    poll_id: web::Path<i32>,
     identity: Identity,
     flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
     item_service: web::Data<IS>,
     ballot_service: web::Data<BS>,
     ranking_service: web::Data<RS>,
 ) -> Result<HttpResponse, RouteError>
 where
+    PS: PollService,
     IS: ItemService,
     BS: BallotService,
     RS: RankingService,
 {
-    let ballot = match ballot_service.find_ballot(&identity.id()?).await? {
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
         Some(v) => v,
-        None => {
-            Identity::logout(identity);
-            FlashMessage::new(
-                "Invalid session".to_string(),
-                actix_web_flash_messages::Level::Error,
-            )
-            .send();
-            return Ok(HttpResponse::SeeOther()
-                .insert_header((header::LOCATION, "/"))
-                .finish());
-        }
+        None => return Ok(poll_not_found()),
+    };
+    let ballot = match ballot_service.find_ballot(poll.id, &identity.id()?).await? {
+        Some(v) => v,
+        None => return Ok(ballot_not_found(poll.id)),
     };
-    let (best_item, (ranked_items, unranked_items)) = futures::try_join!(
-        ranking_service.get_instant_runoff_result(),
-        item_service.get_ballot_items(ballot.id)
+    let (best_items, (ranked_items, unranked_items), scores) = futures::try_join!(
+        ranking_service.get_best_items(&poll),
+        item_service.get_ballot_items(&ballot),
+        ranking_service.get_ballot_scores(&poll, &ballot),
     )?;
     let body = BallotView::new(
+        &poll,
         &ballot.uuid,
-        &best_item,
+        &best_items,
+        ranking_service.voting_method(),
         &flashes,
         &ranked_items,
         &unranked_items,
+        &scores,
     )
     .render_once()?;
     Ok(HttpResponse::Ok().body(body))
@@ -58,43 +70,157 @@ pub struct BallotUpdateData {
     items: Vec<String>,
 }
 
-#[tracing::instrument(skip(identity, ballot_service, ranking_service))]
-pub async fn post<BS, RS>(
+#[tracing::instrument(skip(identity, poll_service, ballot_service, ranking_service))]
+pub async fn post<PS, BS, RS>(
+    poll_id: web::Path<i32>,
     identity: Identity,
     ballot_update_data: web::Json<BallotUpdateData>,
+    poll_service: web::Data<PS>,
     ballot_service: web::Data<BS>,
     ranking_service: web::Data<RS>,
 ) -> Result<HttpResponse, RouteError>
 where
+    PS: PollService,
     BS: BallotService,
     RS: RankingService,
 {
-    let ballot = match ballot_service.find_ballot(&identity.id()?).await? {
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
         Some(v) => v,
-        None => {
-            Identity::logout(identity);
-            FlashMessage::new(
-                "Invalid session".to_string(),
-                actix_web_flash_messages::Level::Error,
-            )
-            .send();
-            return Ok(HttpResponse::SeeOther()
-                .insert_header((header::LOCATION, "/"))
-                .finish());
-        }
+        None => return Ok(poll_not_found()),
+    };
+    let poll_id = poll.id;
+    let ballot = match ballot_service.find_ballot(poll_id, &identity.id()?).await? {
+        Some(v) => v,
+        None => return Ok(ballot_not_found(poll_id)),
     };
 
-    let ranked_item_ids: Vec<i32> = ballot_update_data
+    // An id that starts with `=` shares the rank of the item before it.
+    let mut ranks: Vec<Vec<i32>> = Vec::new();
+    let mut problems = Vec::new();
+    for id in ballot_update_data
         .items
         .iter()
         .take_while(|id| id.as_str() != "<DELIMITER>")
-        .map_while(|id| str::parse(id).ok())
-        .collect();
-    ranking_service
-        .update_ballot_rankings(ballot.id, &ranked_item_ids)
-        .await?;
+    {
+        let (tied, item_id) = match id.strip_prefix('=') {
+            Some(item_id) => (true, item_id),
+            None => (false, id.as_str()),
+        };
+        let Ok(item_id) = item_id.parse() else {
+            problems.push(BallotProblem::InvalidItemId(id.clone()));
+            continue;
+        };
+        match ranks.last_mut() {
+            Some(rank) if tied => rank.push(item_id),
+            _ => ranks.push(vec![item_id]),
+        }
+    }
+    if !problems.is_empty() {
+        return Ok(ballot_rejected(
+            poll_id,
+            &ServiceError::InvalidBallot(problems),
+        ));
+    }
+    match ranking_service
+        .update_ballot_rankings(&ballot, &ranks)
+        .await
+    {
+        Ok(()) => {}
+        Err(
+            e @ (ServiceError::PollNotOpen
+            | ServiceError::PollClosed
+            | ServiceError::WrongBallotType(_)
+            | ServiceError::InvalidBallot(_)),
+        ) => return Ok(ballot_rejected(poll_id, &e)),
+        Err(e) => return Err(e.into()),
+    }
 
-    let best_item = ranking_service.get_instant_runoff_result().await?;
-    let body = BestItemView::new(&best_item).render_once()?;
+    let best_items = ranking_service.get_best_items(&poll).await?;
+    let body =
+        BestItemView::new(&poll, &best_items, ranking_service.voting_method()).render_once()?;
     Ok(HttpResponse::Ok().body(body))
 }
+
+/// The scores of a ballot, each field is named after the id of the scored item.
+pub type BallotScoresData = HashMap<String, String>;
+
+#[tracing::instrument(skip(identity, poll_service, ballot_service, ranking_service))]
+pub async fn post_scores<PS, BS, RS>(
+    poll_id: web::Path<i32>,
+    identity: Identity,
+    ballot_scores_data: web::Json<BallotScoresData>,
+    poll_service: web::Data<PS>,
+    ballot_service: web::Data<BS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    BS: BallotService,
+    RS: RankingService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let poll_id = poll.id;
+    let ballot = match ballot_service.find_ballot(poll_id, &identity.id()?).await? {
+        Some(v) => v,
+        None => return Ok(ballot_not_found(poll_id)),
+    };
+
+    let mut scores: Vec<(i32, u32)> = Vec::new();
+    let mut problems = Vec::new();
+    for (id, score) in ballot_scores_data.iter() {
+        let Ok(item_id) = id.parse() else {
+            problems.push(BallotProblem::InvalidItemId(id.clone()));
+            continue;
+        };
+        let Ok(score) = score.parse() else {
+            let e = ServiceError::InvalidScore(poll.ballot_type.max_score());
+            return Ok(ballot_rejected(poll_id, &e));
+        };
+        scores.push((item_id, score));
+    }
+    if !problems.is_empty() {
+        return Ok(ballot_rejected(
+            poll_id,
+            &ServiceError::InvalidBallot(problems),
+        ));
+    }
+    match ranking_service.update_ballot_scores(&ballot, &scores).await {
+        Ok(()) => {}
+        Err(
+            e @ (ServiceError::PollNotOpen
+            | ServiceError::PollClosed
+            | ServiceError::WrongBallotType(_)
+            | ServiceError::InvalidScore(_)
+            | ServiceError::InvalidBallot(_)),
+        ) => return Ok(ballot_rejected(poll_id, &e)),
+        Err(e) => return Err(e.into()),
+    }
+
+    let best_items = ranking_service.get_best_items(&poll).await?;
+    let body =
+        BestItemView::new(&poll, &best_items, ranking_service.voting_method()).render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
+
+/// Reload the ballot page for showing why the ballot could not be changed.
+fn ballot_rejected(poll_id: i32, e: &ServiceError) -> HttpResponse {
+    FlashMessage::new(e.to_string(), actix_web_flash_messages::Level::Error).send();
+    HttpResponse::Ok()
+        .insert_header(("HX-Redirect", format!("/polls/{poll_id}/ballot")))
+        .finish()
+}
+
+/// Send the voter back to the poll page so that they can register for the poll.
+fn ballot_not_found(poll_id: i32) -> HttpResponse {
+    FlashMessage::new(
+        "Register to vote in this poll".to_string(),
+        actix_web_flash_messages::Level::Warning,
+    )
+    .send();
+    HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, format!("/polls/{poll_id}")))
+        .finish()
+}
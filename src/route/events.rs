@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use actix_web::{http::header, web, web::Bytes, HttpResponse};
+use sailfish::TemplateOnce;
+use tokio::sync::{broadcast::error::RecvError, watch};
+
+use crate::{
+    service::{PollService, RankingService},
+    view::BestItemView,
+};
+
+use super::{poll_not_found, RouteError};
+
+/// Name of the event carrying a rendered `BestItemView`.
+const BEST_ITEM_EVENT: &str = "best-item";
+
+/// Time without events of the poll after which a comment is sent so that proxies keep the
+/// connection open.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Tells the streams of events that the server is shutting down, so that they end instead of
+/// holding their connections open until the shutdown timeout.
+#[derive(Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    pub fn new(receiver: watch::Receiver<bool>) -> Self {
+        Self(receiver)
+    }
+
+    /// Wait until the shutdown starts, or until the sender is gone.
+    async fn wait(&mut self) {
+        let _ = self.0.wait_for(|&shutdown| shutdown).await;
+    }
+}
+
+/// Stream the best items of the poll as server-sent events every time one of its ballots changes.
+#[tracing::instrument(skip(poll_service, ranking_service, shutdown))]
+pub async fn get<PS, RS>(
+    poll_id: web::Path<i32>,
+    poll_service: web::Data<PS>,
+    ranking_service: web::Data<RS>,
+    shutdown: web::Data<ShutdownSignal>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    RS: 'static + RankingService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let updates = ranking_service.subscribe();
+    // The timer is only restarted by the events of this poll, updates of the other polls do not
+    // hold the keep-alive back.
+    let mut keep_alive = tokio::time::interval_at(
+        tokio::time::Instant::now() + KEEP_ALIVE_INTERVAL,
+        KEEP_ALIVE_INTERVAL,
+    );
+    keep_alive.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let events = futures::stream::unfold(
+        (
+            updates,
+            keep_alive,
+            shutdown.get_ref().clone(),
+            ranking_service,
+            poll,
+        ),
+        |(mut updates, mut keep_alive, mut shutdown, ranking_service, poll)| async move {
+            loop {
+                let update = tokio::select! {
+                    update = updates.recv() => update,
+                    _ = shutdown.wait() => return None,
+                    _ = keep_alive.tick() => {
+                        let comment = Bytes::from_static(b": keep-alive\n\n");
+                        let state = (updates, keep_alive, shutdown, ranking_service, poll);
+                        return Some((Ok(comment), state));
+                    }
+                };
+                let best_items = match update {
+                    Ok(update) if update.poll_id == poll.id => Ok(update.best_items),
+                    Ok(_) => continue,
+                    // Updates of this poll might have been skipped, so the poll is run again.
+                    Err(RecvError::Lagged(_)) => ranking_service.get_best_items(&poll).await,
+                    Err(RecvError::Closed) => return None,
+                };
+                let event = best_items.map_err(RouteError::from).and_then(|best_items| {
+                    let body =
+                        BestItemView::new(&poll, &best_items, ranking_service.voting_method())
+                            .render_once()?;
+                    Ok(server_sent_event(BEST_ITEM_EVENT, &body))
+                });
+                keep_alive.reset();
+                let state = (updates, keep_alive, shutdown, ranking_service, poll);
+                return Some((event, state));
+            }
+        },
+    );
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/event-stream"))
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .streaming(events))
+}
+
+/// Encode an event, every line of its data is sent in a separate field.
+fn server_sent_event(name: &str, data: &str) -> Bytes {
+    let mut event = format!("event: {name}\n");
+    for line in data.lines() {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    Bytes::from(event)
+}
@@ -1,6 +1,147 @@
-use actix_web::HttpResponse;
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
 
+use actix_web::{
+    http::{self, Uri},
+    web, HttpResponse,
+};
+use serde::Serialize;
+
+use crate::{
+    conf::{HealthConfiguration, TracingConfiguration},
+    service::PollService,
+};
+
+/// The dependencies that are checked for readiness and how long to wait for each of them.
+#[derive(Clone)]
+pub struct ReadinessChecks {
+    database_timeout: Duration,
+    /// The exporter of traces, when one is configured.
+    otlp_endpoint: Option<String>,
+    otlp_timeout: Duration,
+}
+
+impl ReadinessChecks {
+    pub fn new(health: &HealthConfiguration, tracing: &TracingConfiguration) -> Self {
+        Self {
+            database_timeout: health.database_timeout(),
+            otlp_endpoint: tracing.jaeger_endpoint().clone(),
+            otlp_timeout: health.otlp_timeout(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum CheckStatus {
+    Up,
+    Down,
+}
+
+#[derive(Serialize)]
+struct HealthResponse {
+    status: CheckStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    checks: Vec<CheckReport>,
+}
+
+#[derive(Serialize)]
+struct CheckReport {
+    name: &'static str,
+    status: CheckStatus,
+    latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// The process is running and can answer requests, its dependencies are not checked.
 #[tracing::instrument]
-pub async fn get() -> HttpResponse {
-    HttpResponse::Ok().finish()
+pub async fn live() -> HttpResponse {
+    HttpResponse::Ok().json(HealthResponse {
+        status: CheckStatus::Up,
+        checks: Vec::new(),
+    })
+}
+
+/// The process can serve traffic, which requires the database to be reachable. Each check is
+/// reported with its latency, and the response is `503 Service Unavailable` if the database is
+/// down. The exporter of traces is only reported, requests are served fine without it.
+#[tracing::instrument(skip(poll_service, checks))]
+pub async fn ready<PS>(
+    poll_service: web::Data<PS>,
+    checks: web::Data<ReadinessChecks>,
+) -> HttpResponse
+where
+    PS: PollService,
+{
+    let database = run_check("database", checks.database_timeout, async {
+        poll_service
+            .check_database()
+            .await
+            .map_err(|e| e.to_string())
+    });
+    let reports = match &checks.otlp_endpoint {
+        Some(endpoint) => {
+            let otlp = run_check("otlp_exporter", checks.otlp_timeout, connect(endpoint));
+            let (database, otlp) = futures::join!(database, otlp);
+            vec![database, otlp]
+        }
+        None => vec![database.await],
+    };
+
+    let status = reports[0].status;
+    let response = HealthResponse {
+        status,
+        checks: reports,
+    };
+    match status {
+        CheckStatus::Up => HttpResponse::Ok().json(response),
+        CheckStatus::Down => HttpResponse::ServiceUnavailable().json(response),
+    }
+}
+
+async fn run_check<F>(name: &'static str, timeout: Duration, check: F) -> CheckReport
+where
+    F: Future<Output = Result<(), String>>,
+{
+    let start = Instant::now();
+    let result = match tokio::time::timeout(timeout, check).await {
+        Ok(result) => result,
+        Err(_) => Err(format!("Timed out after {}ms", timeout.as_millis())),
+    };
+    let latency_ms = start.elapsed().as_secs_f64() * 1000.0;
+    if let Err(error) = &result {
+        tracing::warn!(check = name, %error, "readiness check failed");
+    }
+    CheckReport {
+        name,
+        status: if result.is_ok() {
+            CheckStatus::Up
+        } else {
+            CheckStatus::Down
+        },
+        latency_ms,
+        error: result.err(),
+    }
+}
+
+/// Open a TCP connection to the host of an endpoint URL.
+async fn connect(endpoint: &str) -> Result<(), String> {
+    let uri: Uri = endpoint
+        .parse()
+        .map_err(|e: http::uri::InvalidUri| e.to_string())?;
+    let host = uri.host().ok_or("Endpoint has no host")?;
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("https") {
+            443
+        } else {
+            80
+        });
+    tokio::net::TcpStream::connect((host, port))
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
 }
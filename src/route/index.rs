@@ -2,19 +2,19 @@ use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use sailfish::TemplateOnce;
 
-use crate::{service::RankingService, view::IndexView};
+use crate::{service::PollService, view::IndexView};
 
 use super::RouteError;
 
-#[tracing::instrument(skip(flashes, ranking_service))]
-pub async fn get<RS>(
+#[tracing::instrument(skip(flashes, poll_service))]
+pub async fn get<PS>(
     flashes: IncomingFlashMessages,
-    ranking_service: web::Data<RS>,
+    poll_service: web::Data<PS>,
 ) -> Result<HttpResponse, RouteError>
 where
-    RS: RankingService,
+    PS: PollService,
 {
-    let best_item = ranking_service.get_instant_runoff_result().await?;
-    let body = IndexView::new(&best_item, &flashes).render_once()?;
+    let polls = poll_service.get_polls().await?;
+    let body = IndexView::new(&polls, &flashes).render_once()?;
     Ok(HttpResponse::Ok().body(body))
 }
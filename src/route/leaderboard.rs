@@ -0,0 +1,31 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sailfish::TemplateOnce;
+
+use crate::{
+    service::{PollService, RankingService},
+    view::LeaderboardView,
+};
+
+use super::{poll_not_found, RouteError};
+
+#[tracing::instrument(skip(flashes, poll_service, ranking_service))]
+pub async fn get<PS, RS>(
+    poll_id: web::Path<i32>,
+    flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    RS: RankingService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let standings = ranking_service.get_leaderboard(&poll).await?;
+    let body = LeaderboardView::new(&poll, &standings, ranking_service.voting_method(), &flashes)
+        .render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
@@ -0,0 +1,10 @@
+use actix_web::{http::header, HttpResponse};
+
+use crate::metrics::metrics;
+
+/// Every metric of the application in the Prometheus text format.
+pub async fn get() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, prometheus::TEXT_FORMAT))
+        .body(metrics().render())
+}
@@ -0,0 +1,50 @@
+use actix_identity::Identity;
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sailfish::TemplateOnce;
+
+use crate::{
+    service::{BallotService, PollService, RankingService},
+    view::PollView,
+};
+
+use super::{poll_not_found, RouteError};
+
+#[tracing::instrument(skip(identity, flashes, poll_service, ballot_service, ranking_service))]
+pub async fn get<PS, BS, RS>(
+    poll_id: web::Path<i32>,
+    identity: Option<Identity>,
+    flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
+    ballot_service: web::Data<BS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    BS: BallotService,
+    RS: RankingService,
+{
+    let poll_id = poll_id.into_inner();
+    let poll = match poll_service.find_poll(poll_id).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+
+    let uuid = identity.map(|identity| identity.id()).transpose()?;
+    let ballot = match &uuid {
+        Some(uuid) => ballot_service.find_ballot(poll.id, uuid).await?,
+        None => None,
+    };
+    let best_items = ranking_service.get_best_items(&poll).await?;
+    let body = PollView::new(
+        &poll,
+        uuid.as_deref(),
+        ballot.is_some(),
+        &best_items,
+        ranking_service.voting_method(),
+        ballot_service.registration_mode(),
+        &flashes,
+    )
+    .render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
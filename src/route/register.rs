@@ -3,25 +3,64 @@ use actix_web::{http::header, web, HttpMessage, HttpRequest, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use serde::Deserialize;
 
-use crate::service::{BallotService, ServiceError};
+use crate::{
+    conf::RateLimitConfiguration,
+    rate_limit::AddressLimit,
+    service::{BallotService, PollService, ServiceError},
+};
 
-use super::RouteError;
+use super::{poll_not_found, RouteError};
+
+/// Limit on the number of registrations from an IP address.
+pub struct RegistrationLimit(AddressLimit);
+
+impl RegistrationLimit {
+    pub fn new(config: &RateLimitConfiguration) -> Self {
+        Self(AddressLimit::new(config))
+    }
+}
 
 #[derive(Debug, Deserialize)]
 pub struct RegisterFormData {
+    poll_id: i32,
     uuid: String,
+    /// The invite code or the voter token, depending on the registration mode.
+    code: Option<String>,
 }
 
-#[tracing::instrument(skip(request, ballot_service))]
-pub async fn post<BS>(
+#[tracing::instrument(skip(request, form, limit, poll_service, ballot_service), fields(poll_id = form.poll_id))]
+pub async fn post<PS, BS>(
     request: HttpRequest,
     form: web::Form<RegisterFormData>,
+    limit: web::Data<RegistrationLimit>,
+    poll_service: web::Data<PS>,
     ballot_service: web::Data<BS>,
 ) -> Result<HttpResponse, RouteError>
 where
+    PS: PollService,
     BS: BallotService,
 {
-    let uuid = match ballot_service.register(form.uuid.as_str()).await {
+    let poll = match poll_service.find_poll(form.poll_id).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let poll_page = HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, format!("/polls/{}", poll.id)))
+        .finish();
+    if !limit.0.check(&request) {
+        tracing::warn!("Too many registrations");
+        FlashMessage::new(
+            "Too many registrations, try again later".to_string(),
+            actix_web_flash_messages::Level::Error,
+        )
+        .send();
+        return Ok(poll_page);
+    }
+    let code = form.code.as_deref().filter(|c| !c.is_empty());
+    let uuid = match ballot_service
+        .register(poll.id, form.uuid.as_str(), code)
+        .await
+    {
         Ok(v) => v,
         Err(ServiceError::Uuid(e)) => {
             tracing::warn!(error = %e, "Invalid UUID");
@@ -30,9 +69,12 @@ where
                 actix_web_flash_messages::Level::Error,
             )
             .send();
-            return Ok(HttpResponse::SeeOther()
-                .insert_header((header::LOCATION, "/"))
-                .finish());
+            return Ok(poll_page);
+        }
+        Err(e @ (ServiceError::InvalidInviteCode | ServiceError::InvalidVoterToken)) => {
+            tracing::warn!(error = %e, "Registration refused");
+            FlashMessage::new(e.to_string(), actix_web_flash_messages::Level::Error).send();
+            return Ok(poll_page);
         }
         Err(e) => return Err(e.into()),
     };
@@ -43,6 +85,6 @@ where
     )
     .send();
     Ok(HttpResponse::SeeOther()
-        .insert_header((header::LOCATION, "/ballot"))
+        .insert_header((header::LOCATION, format!("/polls/{}/ballot", poll.id)))
         .finish())
 }
@@ -0,0 +1,48 @@
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use sailfish::TemplateOnce;
+
+use crate::{
+    service::{PollService, RankingService},
+    view::ResultsView,
+};
+
+use super::{poll_not_found, RouteError};
+
+#[tracing::instrument(skip(flashes, poll_service, ranking_service))]
+pub async fn get<PS, RS>(
+    poll_id: web::Path<i32>,
+    flashes: IncomingFlashMessages,
+    poll_service: web::Data<PS>,
+    ranking_service: web::Data<RS>,
+) -> Result<HttpResponse, RouteError>
+where
+    PS: PollService,
+    RS: RankingService,
+{
+    let poll = match poll_service.find_poll(poll_id.into_inner()).await? {
+        Some(v) => v,
+        None => return Ok(poll_not_found()),
+    };
+    let (trace, pairwise) = futures::try_join!(
+        ranking_service.get_instant_runoff_trace(&poll),
+        ranking_service.get_pairwise_comparison(&poll),
+    )?;
+    let (election, scores) = if poll.ballot_type.is_scored() {
+        (None, Some(ranking_service.get_score_tally(&poll).await?))
+    } else if poll.seats > 1 {
+        (Some(ranking_service.get_election_trace(&poll).await?), None)
+    } else {
+        (None, None)
+    };
+    let body = ResultsView::new(
+        &poll,
+        &trace,
+        election.as_ref(),
+        &pairwise,
+        scores.as_ref(),
+        &flashes,
+    )
+    .render_once()?;
+    Ok(HttpResponse::Ok().body(body))
+}
@@ -0,0 +1,320 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::voting::{
+    tie_break::{TieBreak, TieBreaker},
+    VotingResult,
+};
+
+/// Highest score that can be given on score and STAR ballots.
+pub const MAX_SCORE: u32 = 5;
+
+/// The full record of a poll with scored ballots
+pub struct ScoreVotingTrace<'a, T> {
+    /// Total score of every item, sorted by descending score
+    pub totals: Vec<(&'a T, u32)>,
+
+    /// Number of ballots preferring each finalist of the automatic runoff, only used by STAR
+    pub runoff: Vec<(&'a T, u32)>,
+
+    /// The rule that decided between items that had the same total
+    pub tie_break: Option<TieBreak>,
+
+    pub result: VotingResult<'a, T>,
+}
+
+/// Determine the best item(s) using approval voting, the item approved on the most ballots wins.
+/// Every item with a score above zero is approved.
+pub fn approval_vote<'a, T>(
+    ballots: &'a [&'a [(T, u32)]],
+    tie_breaker: &mut TieBreaker,
+) -> ScoreVotingTrace<'a, T>
+where
+    T: 'a + Ord + Hash,
+{
+    highest_total(totals(ballots, |score| u32::from(score > 0)), tie_breaker)
+}
+
+/// Determine the best item(s) using score voting, the item with the highest total score wins.
+pub fn score_vote<'a, T>(
+    ballots: &'a [&'a [(T, u32)]],
+    tie_breaker: &mut TieBreaker,
+) -> ScoreVotingTrace<'a, T>
+where
+    T: 'a + Ord + Hash,
+{
+    highest_total(totals(ballots, |score| score), tie_breaker)
+}
+
+/// Determine the best item(s) using STAR voting (score then automatic runoff). The two items with
+/// the highest total scores are finalists, and the finalist that is scored higher on more ballots
+/// wins. When the finalists are preferred on as many ballots, the one with the higher total wins.
+///
+/// Ties for becoming a finalist are broken by the tie breaker. If it does not break ties, every
+/// tied item becomes a finalist and each finalist counts the ballots that score it above all the
+/// other finalists.
+pub fn star_vote<'a, T>(
+    ballots: &'a [&'a [(T, u32)]],
+    tie_breaker: &mut TieBreaker,
+) -> ScoreVotingTrace<'a, T>
+where
+    T: 'a + Ord + Hash,
+{
+    let totals = totals(ballots, |score| score);
+    let history = [totals.clone()];
+    let mut tie_break = None;
+
+    // Pick the finalists from the highest totals
+    let mut finalists: Vec<&T> = Vec::new();
+    let mut rest = totals.clone();
+    'pick: while finalists.len() < 2 {
+        let Some(&(_, max)) = rest.first() else {
+            break;
+        };
+        let mut best: Vec<&T> = rest
+            .iter()
+            .filter(|&&(_, v)| v == max)
+            .map(|&(k, _)| k)
+            .collect();
+        while best.len() > 2 - finalists.len() {
+            match tie_breaker.strongest(&best, &history, &[]) {
+                Some((item, rule)) => {
+                    tie_break = Some(rule);
+                    finalists.push(item);
+                    best.retain(|&other| other != item);
+                }
+                None => {
+                    finalists.append(&mut best);
+                    break 'pick;
+                }
+            }
+            if finalists.len() == 2 {
+                break 'pick;
+            }
+        }
+        finalists.append(&mut best);
+        rest.retain(|(item, _)| !finalists.contains(item));
+    }
+
+    // Count the ballots that score a finalist above every other finalist
+    let mut runoff: Vec<(&T, u32)> = finalists.iter().map(|&item| (item, 0)).collect();
+    for &ballot in ballots {
+        let score = |item: &T| {
+            ballot
+                .iter()
+                .find(|(other, _)| other == item)
+                .map_or(0, |&(_, score)| score)
+        };
+        for (item, count) in runoff.iter_mut() {
+            let own = score(item);
+            if finalists
+                .iter()
+                .all(|&other| other == *item || score(other) < own)
+            {
+                *count += 1;
+            }
+        }
+    }
+    runoff.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+
+    let result = match runoff.first() {
+        None => VotingResult::NoWinner,
+        Some(&(_, max)) => {
+            let preferred: Vec<_> = runoff
+                .iter()
+                .filter(|&&(_, v)| v == max)
+                .map(|&(k, _)| k)
+                .collect();
+            // Finalists preferred on as many ballots are compared by their totals
+            let preferred_totals: Vec<_> = totals
+                .iter()
+                .filter(|(item, _)| preferred.contains(item))
+                .copied()
+                .collect();
+            let trace = highest_total(preferred_totals, tie_breaker);
+            if trace.tie_break.is_some() {
+                tie_break = trace.tie_break;
+            }
+            trace.result
+        }
+    };
+    ScoreVotingTrace {
+        totals,
+        runoff,
+        tie_break,
+        result,
+    }
+}
+
+/// Sum the scores of every item after mapping them, sorted by descending total.
+fn totals<'a, T, F>(ballots: &'a [&'a [(T, u32)]], map: F) -> Vec<(&'a T, u32)>
+where
+    T: Ord + Hash,
+    F: Fn(u32) -> u32,
+{
+    let mut totals: HashMap<&T, u32> = HashMap::new();
+    for &(ref item, score) in ballots.iter().flat_map(|ballot| ballot.iter()) {
+        *totals.entry(item).or_insert(0) += map(score);
+    }
+    let mut totals: Vec<_> = totals.into_iter().collect();
+    totals.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+    totals
+}
+
+/// Choose the item with the highest total, the tie breaker decides between items with the same
+/// total.
+fn highest_total<'a, T>(
+    totals: Vec<(&'a T, u32)>,
+    tie_breaker: &mut TieBreaker,
+) -> ScoreVotingTrace<'a, T>
+where
+    T: 'a + Ord + Hash,
+{
+    let mut tie_break = None;
+    let result = match totals.first() {
+        None => VotingResult::NoWinner,
+        Some(&(_, max)) => {
+            let best: Vec<_> = totals
+                .iter()
+                .filter(|&&(_, v)| v == max)
+                .map(|&(k, _)| k)
+                .collect();
+            if best.len() == 1 {
+                VotingResult::Winner(best[0])
+            } else {
+                match tie_breaker.strongest(&best, std::slice::from_ref(&totals), &[]) {
+                    Some((item, rule)) => {
+                        tie_break = Some(rule);
+                        VotingResult::Winner(item)
+                    }
+                    None => VotingResult::Tied(best),
+                }
+            }
+        }
+    };
+    ScoreVotingTrace {
+        totals,
+        runoff: Vec::new(),
+        tie_break,
+        result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn winner<'a, T>(trace: &ScoreVotingTrace<'a, T>) -> &'a T {
+        match trace.result {
+            VotingResult::Winner(winner) => winner,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn approval_counts_approved_items() {
+        let vote_a = vec![("bob", 1), ("sue", 1)];
+        let vote_b = vec![("sue", 1), ("bill", 0)];
+        let vote_c = vec![("bill", 1)];
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice(), vote_c.as_slice()];
+        let trace = approval_vote(&votes, &mut TieBreaker::default());
+        assert_eq!(trace.totals, vec![(&"sue", 2), (&"bill", 1), (&"bob", 1)]);
+        assert_eq!(winner(&trace), &"sue");
+    }
+
+    #[test]
+    fn score_ties_are_kept_without_tie_break() {
+        let vote_a = vec![("bob", 5), ("sue", 2)];
+        let vote_b = vec![("sue", 3)];
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        let trace = score_vote(&votes, &mut TieBreaker::default());
+        match trace.result {
+            VotingResult::Tied(items) => assert_eq!(items, vec![&"bob", &"sue"]),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn score_ties_are_broken_by_every_rule() {
+        let vote_a = vec![("bob", 5), ("sue", 0)];
+        let vote_b = vec![("sue", 5), ("bill", 0)];
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+
+        for rule in [
+            TieBreak::None,
+            TieBreak::Backward,
+            TieBreak::Forward,
+            TieBreak::Borda,
+            TieBreak::Random,
+        ] {
+            for trace in [
+                approval_vote(&votes, &mut TieBreaker::new(rule, 1)),
+                score_vote(&votes, &mut TieBreaker::new(rule, 1)),
+                star_vote(&votes, &mut TieBreaker::new(rule, 1)),
+            ] {
+                match trace.result {
+                    VotingResult::Tied(items) => {
+                        assert_eq!(rule, TieBreak::None);
+                        assert!(items.len() > 1);
+                    }
+                    VotingResult::Winner(_) => {
+                        assert_ne!(rule, TieBreak::None);
+                        // Nothing but the random draw can tell the tied items apart
+                        assert_eq!(trace.tie_break, Some(TieBreak::Random), "{rule:?}");
+                    }
+                    VotingResult::NoWinner => unreachable!(),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn star_runoff_can_overturn_totals() {
+        // bob has the highest total but sue is scored higher on more ballots
+        let vote_a = vec![("bob", 5), ("sue", 0), ("bill", 1)];
+        let vote_b = vec![("bob", 3), ("sue", 4), ("bill", 1)];
+        let vote_c = vec![("bob", 3), ("sue", 4), ("bill", 1)];
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice(), vote_c.as_slice()];
+        let trace = star_vote(&votes, &mut TieBreaker::default());
+        assert_eq!(trace.totals[0], (&"bob", 11));
+        assert_eq!(trace.runoff, vec![(&"sue", 2), (&"bob", 1)]);
+        assert_eq!(winner(&trace), &"sue");
+    }
+
+    #[test]
+    fn star_equal_preference_goes_to_higher_total() {
+        let vote_a = vec![("bob", 5), ("sue", 1)];
+        let vote_b = vec![("bob", 2), ("sue", 3)];
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        let trace = star_vote(&votes, &mut TieBreaker::default());
+        assert_eq!(trace.runoff, vec![(&"bob", 1), (&"sue", 1)]);
+        assert_eq!(winner(&trace), &"bob");
+    }
+
+    #[test]
+    fn star_tie_break_picks_finalists() {
+        let vote_a = vec![("bob", 5), ("sue", 5), ("bill", 5)];
+
+        let votes = vec![vote_a.as_slice()];
+        let trace = star_vote(&votes, &mut TieBreaker::default());
+        assert_eq!(trace.runoff.len(), 3);
+        assert!(matches!(trace.result, VotingResult::Tied(_)));
+
+        let mut tie_breaker = TieBreaker::new(TieBreak::Random, 7);
+        let trace = star_vote(&votes, &mut tie_breaker);
+        assert_eq!(trace.runoff.len(), 2);
+        assert!(matches!(trace.result, VotingResult::Winner(_)));
+    }
+
+    #[test]
+    fn score_no_vote() {
+        let votes: Vec<&[(&str, u32)]> = vec![];
+        assert!(matches!(
+            star_vote(&votes, &mut TieBreaker::default()).result,
+            VotingResult::NoWinner
+        ));
+    }
+}
@@ -1,14 +1,23 @@
 use async_trait::async_trait;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 use crate::{
-    model::{Ballot, Item},
+    model::{
+        AuditReport, Ballot, BallotType, BestItemUpdate, ElectionTrace, Item, PairwiseComparison,
+        Poll, PollSnapshot, PollStatus, RegistrationMode, ScoreTally, Standing, TallyResult,
+        TallyTrace, VoterRoll,
+    },
     repository::RepositoryError,
+    voting::Method,
 };
 
 pub mod ballot;
 pub mod item;
+pub mod poll;
 pub mod ranking;
+pub mod tally;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ServiceError {
@@ -17,34 +26,286 @@ pub enum ServiceError {
 
     #[error(transparent)]
     Uuid(#[from] uuid::Error),
+
+    #[error("Poll must have a title")]
+    MissingPollTitle,
+
+    #[error("Item must have a title")]
+    MissingItemTitle,
+
+    #[error("Poll is not open for voting yet")]
+    PollNotOpen,
+
+    #[error("Poll is closed, ballots can no longer be changed")]
+    PollClosed,
+
+    #[error("Poll must close after it opens")]
+    InvalidSchedule,
+
+    #[error("Poll must have at least one seat")]
+    InvalidSeats,
+
+    #[error("Polls with scored ballots elect a single item")]
+    ScoredPollSeats,
+
+    #[error("Poll is closed, its items can no longer be changed")]
+    ItemsLocked,
+
+    #[error("Ballot type can only be changed before the poll opens")]
+    BallotTypeLocked,
+
+    #[error("This poll uses {0} ballots")]
+    WrongBallotType(BallotType),
+
+    #[error("Scores must be between 0 and {0}")]
+    InvalidScore(u32),
+
+    #[error("Invalid invite code")]
+    InvalidInviteCode,
+
+    #[error("Invalid voter token, it might have been used already")]
+    InvalidVoterToken,
+
+    #[error("Between 1 and {0} voter tokens can be issued at once")]
+    InvalidTokenCount(usize),
+
+    #[error("Ballot was rejected: {}", display_problems(.0))]
+    InvalidBallot(Vec<BallotProblem>),
+}
+
+/// Something that is wrong with a submitted ballot. Every problem of a ballot is reported at
+/// once, so that the voter can fix all of them.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum BallotProblem {
+    #[error("\"{0}\" is not an item id")]
+    InvalidItemId(String),
+
+    #[error("Item {0} is not part of this poll")]
+    UnknownItem(i32),
+
+    #[error("Item {0} is done or retired")]
+    InactiveItem(i32),
+
+    #[error("Item {0} is given more than once")]
+    DuplicateItem(i32),
+}
+
+impl BallotProblem {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidItemId(_) => "invalid_item_id",
+            Self::UnknownItem(_) => "unknown_item",
+            Self::InactiveItem(_) => "inactive_item",
+            Self::DuplicateItem(_) => "duplicate_item",
+        }
+    }
+}
+
+fn display_problems(problems: &[BallotProblem]) -> String {
+    problems
+        .iter()
+        .map(|p| p.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[async_trait]
+pub trait PollService: Clone + Send + Sync {
+    /// Check that the database storing the polls can be reached.
+    async fn check_database(&self) -> Result<(), ServiceError>;
+
+    async fn get_polls(&self) -> Result<Vec<Poll>, ServiceError>;
+
+    async fn find_poll(&self, poll_id: i32) -> Result<Option<Poll>, ServiceError>;
+
+    /// Create a new draft poll, returns its id. The poll is set up and opened afterwards.
+    async fn create_poll(&self, title: &str, description: &str) -> Result<i32, ServiceError>;
+
+    /// Change the status and the scheduled times of a poll, returns `false` if the poll was not
+    /// found. Closed polls can not be changed anymore.
+    async fn update_poll_lifecycle(
+        &self,
+        poll_id: i32,
+        status: PollStatus,
+        opens_at: Option<OffsetDateTime>,
+        closes_at: Option<OffsetDateTime>,
+    ) -> Result<bool, ServiceError>;
+
+    /// Change the number of items that the poll elects, returns `false` if the poll was not
+    /// found. Closed polls can not be changed anymore.
+    async fn update_poll_seats(&self, poll_id: i32, seats: i32) -> Result<bool, ServiceError>;
+
+    /// Change how voters fill in the ballots of the poll, returns `false` if the poll was not
+    /// found. Only draft polls can be changed, so that every ballot has the same type.
+    async fn update_poll_ballot_type(
+        &self,
+        poll_id: i32,
+        ballot_type: BallotType,
+    ) -> Result<bool, ServiceError>;
 }
 
 #[async_trait]
 pub trait ItemService: Clone + Send + Sync {
+    /// Get the items of the ballot's poll, split into the ranks of the ranked ones, from the
+    /// most preferred, and the unranked ones.
     async fn get_ballot_items(
         &self,
-        ballot_id: i32,
-    ) -> Result<(Vec<Item>, Vec<Item>), ServiceError>;
+        ballot: &Ballot,
+    ) -> Result<(Vec<Vec<Item>>, Vec<Item>), ServiceError>;
+
+    /// Get every item of the poll in their current order.
+    async fn get_poll_items(&self, poll_id: i32) -> Result<Vec<Item>, ServiceError>;
+
+    /// Add a new item at the end of the poll.
+    async fn create_item(
+        &self,
+        poll_id: i32,
+        title: &str,
+        content: &str,
+    ) -> Result<(), ServiceError>;
+
+    /// Change the title and the content of an item, returns `false` if the item was not found.
+    async fn update_item(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        title: &str,
+        content: &str,
+    ) -> Result<bool, ServiceError>;
+
+    /// Place the items of the poll in the given order.
+    async fn reorder_items(&self, poll_id: i32, item_ids: &[i32]) -> Result<(), ServiceError>;
+
+    /// Mark an item as done, returns `false` if the item was not found.
+    async fn set_item_done(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        done: bool,
+    ) -> Result<bool, ServiceError>;
+
+    /// Retire an item so that it can no longer be voted for, returns `false` if the item was not
+    /// found.
+    async fn set_item_retired(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        retired: bool,
+    ) -> Result<bool, ServiceError>;
 }
 
 #[async_trait]
 pub trait BallotService: Clone + Send + Sync {
-    /// Register a new ballot with the given string; the string must be a valid UUID
-    /// If the UUID already exists, do nothing and simply return it back to the caller.
-    async fn register(&self, uuid: &str) -> Result<Uuid, ServiceError>;
+    /// What voters must give for getting a new ballot.
+    fn registration_mode(&self) -> RegistrationMode;
+
+    /// Register a new ballot in the poll with the given string; the string must be a valid UUID
+    /// If the UUID already exists, do nothing and simply return it back to the caller. Otherwise,
+    /// the credential must be the invite code or an unused voter token depending on the
+    /// registration mode.
+    async fn register(
+        &self,
+        poll_id: i32,
+        uuid: &str,
+        credential: Option<&str>,
+    ) -> Result<Uuid, ServiceError>;
 
-    /// Find new ballot with the given string and guaranteed to return `None`
+    /// Find the ballot of the poll with the given string and guaranteed to return `None`
     /// if the string is not a valid UUID.
-    async fn find_ballot(&self, uuid: &str) -> Result<Option<Ballot>, ServiceError>;
+    async fn find_ballot(&self, poll_id: i32, uuid: &str) -> Result<Option<Ballot>, ServiceError>;
+
+    /// Issue new one-time tokens for registering in the poll. The tokens are only returned here,
+    /// they can not be found again later.
+    async fn issue_voter_tokens(
+        &self,
+        poll_id: i32,
+        count: usize,
+    ) -> Result<Vec<String>, ServiceError>;
+
+    /// Count the issued and the used voter tokens of the poll.
+    async fn get_voter_roll(&self, poll_id: i32) -> Result<VoterRoll, ServiceError>;
 }
 
 #[async_trait]
 pub trait RankingService: Clone + Send + Sync {
-    async fn get_instant_runoff_result(&self) -> Result<Option<Item>, ServiceError>;
+    /// The voting method that is used for determining the best item.
+    fn voting_method(&self) -> Method;
+
+    /// Run the poll and return its best items. That is the winner of the configured voting
+    /// method if there is one, or every elected item of a poll with more than one seat. Polls
+    /// with scored ballots have a single winner.
+    async fn get_best_items(&self, poll: &Poll) -> Result<Vec<Item>, ServiceError>;
 
+    /// Run the poll using the configured voting method, or the method of its ballot type when
+    /// ballots are scored, and return its result, which can also be a tie or have no winner. The
+    /// result of a closed poll is its final tally.
+    async fn get_result(&self, poll: &Poll) -> Result<TallyResult, ServiceError>;
+
+    /// Run the poll again using the ballots as they were at the given time. Items are taken as
+    /// they are now.
+    async fn get_result_at(
+        &self,
+        poll: &Poll,
+        at: OffsetDateTime,
+    ) -> Result<TallyResult, ServiceError>;
+
+    /// Verify the chain of ballot revisions of the poll and compare it with the current rankings.
+    async fn get_audit_report(&self, poll_id: i32) -> Result<AuditReport, ServiceError>;
+
+    /// Run the poll and return every counting round along with the final result. The trace of a
+    /// closed poll is the one of its final tally.
+    async fn get_instant_runoff_trace(&self, poll: &Poll) -> Result<TallyTrace, ServiceError>;
+
+    /// Elect as many items as the poll has seats using the single transferable vote, keeping
+    /// every round. The trace of a closed poll is the one of its final tally.
+    async fn get_election_trace(&self, poll: &Poll) -> Result<ElectionTrace, ServiceError>;
+
+    /// Order every item of the poll that is neither done nor retired, by running the poll again
+    /// without the items that are already placed, or by total score when ballots are scored.
+    /// Items that are not on any ballot come last.
+    async fn get_leaderboard(&self, poll: &Poll) -> Result<Vec<Standing>, ServiceError>;
+
+    /// Total the scores of a poll with scored ballots, along with the runoff of a STAR poll. The
+    /// totals of a closed poll are the ones of its final tally.
+    async fn get_score_tally(&self, poll: &Poll) -> Result<ScoreTally, ServiceError>;
+
+    /// Compare every pair of items of the poll head to head.
+    async fn get_pairwise_comparison(
+        &self,
+        poll: &Poll,
+    ) -> Result<PairwiseComparison, ServiceError>;
+
+    /// The final tally of the poll if it is closed. It is stored when it is first needed and does
+    /// not change after that.
+    async fn get_final_tally(&self, poll: &Poll) -> Result<Option<PollSnapshot>, ServiceError>;
+
+    /// Replace the rankings of the ballot, which is only allowed while its poll is open. Ranks
+    /// are given from the most preferred, items that share a rank are equally preferred and
+    /// items that are left out are ranked below every ranked item. The ballot is rejected with
+    /// every problem found when it gives an item more than once or gives items that can not be
+    /// ranked. Subscribers are notified of the new best items of the ballot's poll once the
+    /// change is committed.
     async fn update_ballot_rankings(
         &self,
-        ballot_id: i32,
-        ranked_item_ids: &[i32],
+        ballot: &Ballot,
+        ranks: &[Vec<i32>],
+    ) -> Result<(), ServiceError>;
+
+    /// Get the scores of the ballot of the poll as item ids with their score.
+    async fn get_ballot_scores(
+        &self,
+        poll: &Poll,
+        ballot: &Ballot,
+    ) -> Result<Vec<(i32, u32)>, ServiceError>;
+
+    /// Replace the scores of the ballot, which is only allowed while its poll is open and uses
+    /// scored ballots. Items that are not given a score are scored 0.
+    async fn update_ballot_scores(
+        &self,
+        ballot: &Ballot,
+        scores: &[(i32, u32)],
     ) -> Result<(), ServiceError>;
+
+    /// Receive the best items of a poll every time one of its ballots is changed.
+    fn subscribe(&self) -> broadcast::Receiver<BestItemUpdate>;
 }
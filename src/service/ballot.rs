@@ -1,18 +1,86 @@
 use async_trait::async_trait;
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 
-use crate::{model::Ballot, repository::BallotRepository};
+use crate::{
+    metrics::metrics,
+    model::{Ballot, RegistrationMode, VoterRoll},
+    repository::BallotRepository,
+};
 
 use super::ServiceError;
 
+/// Most voter tokens that can be issued at once.
+const MAX_ISSUED_TOKENS: usize = 1000;
+
 #[derive(Clone)]
 pub struct BallotService<B> {
     ballot_repository: B,
+    registration_mode: RegistrationMode,
+    invite_code: Option<Secret<String>>,
 }
 
 impl<B> BallotService<B> {
-    pub fn new(ballot_repository: B) -> Self {
-        Self { ballot_repository }
+    pub fn new(
+        ballot_repository: B,
+        registration_mode: RegistrationMode,
+        invite_code: Option<Secret<String>>,
+    ) -> Self {
+        Self {
+            ballot_repository,
+            registration_mode,
+            invite_code,
+        }
+    }
+}
+
+impl<B> BallotService<B>
+where
+    B: BallotRepository,
+{
+    /// Create a ballot for a voter who gives the credential required by the registration mode.
+    async fn save_with_credential(
+        &self,
+        poll_id: i32,
+        uuid: Uuid,
+        credential: Option<&str>,
+    ) -> Result<(), ServiceError> {
+        match self.registration_mode {
+            RegistrationMode::Open => {}
+            RegistrationMode::InviteCode => {
+                let valid = match (credential, &self.invite_code) {
+                    // Hashes are compared in constant time, so that the time taken does not
+                    // depend on the code or its length.
+                    (Some(given), Some(code)) => token_hash(given)
+                        .as_bytes()
+                        .ct_eq(token_hash(code.expose_secret()).as_bytes())
+                        .into(),
+                    _ => false,
+                };
+                if !valid {
+                    return Err(ServiceError::InvalidInviteCode);
+                }
+            }
+            RegistrationMode::VoterRoll => {
+                // The token is only used up along with the creation of the ballot.
+                let token = credential.ok_or(ServiceError::InvalidVoterToken)?;
+                return if self
+                    .ballot_repository
+                    .save_with_voter_token(poll_id, uuid, &token_hash(token))
+                    .await?
+                {
+                    Ok(())
+                } else {
+                    Err(ServiceError::InvalidVoterToken)
+                };
+            }
+        }
+        self.ballot_repository
+            .save_ignoring_conflict(poll_id, uuid)
+            .await?;
+        Ok(())
     }
 }
 
@@ -21,17 +89,135 @@ impl<B> super::BallotService for BallotService<B>
 where
     B: BallotRepository,
 {
-    #[tracing::instrument(skip(self))]
-    async fn register(&self, uuid: &str) -> Result<Uuid, ServiceError> {
+    fn registration_mode(&self) -> RegistrationMode {
+        self.registration_mode
+    }
+
+    #[tracing::instrument(skip(self, credential))]
+    async fn register(
+        &self,
+        poll_id: i32,
+        uuid: &str,
+        credential: Option<&str>,
+    ) -> Result<Uuid, ServiceError> {
         let uuid = Uuid::parse_str(uuid)?;
-        self.ballot_repository.save_ignoring_conflict(uuid).await?;
+        if self
+            .ballot_repository
+            .find_by_uuid(poll_id, uuid)
+            .await?
+            .is_some()
+        {
+            return Ok(uuid);
+        }
+        self.save_with_credential(poll_id, uuid, credential).await?;
+        metrics().ballot_registered(poll_id);
         Ok(uuid)
     }
 
     #[tracing::instrument(skip(self))]
-    async fn find_ballot(&self, uuid: &str) -> Result<Option<Ballot>, ServiceError> {
-        let uuid = Uuid::parse_str(uuid)?;
-        let ballot = self.ballot_repository.find_by_uuid(uuid).await?;
+    async fn find_ballot(&self, poll_id: i32, uuid: &str) -> Result<Option<Ballot>, ServiceError> {
+        let uuid = match Uuid::parse_str(uuid) {
+            Ok(v) => v,
+            Err(_) => return Ok(None),
+        };
+        let ballot = self.ballot_repository.find_by_uuid(poll_id, uuid).await?;
         Ok(ballot)
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn issue_voter_tokens(
+        &self,
+        poll_id: i32,
+        count: usize,
+    ) -> Result<Vec<String>, ServiceError> {
+        if count == 0 || count > MAX_ISSUED_TOKENS {
+            return Err(ServiceError::InvalidTokenCount(MAX_ISSUED_TOKENS));
+        }
+        let tokens: Vec<String> = (0..count)
+            .map(|_| Uuid::new_v4().simple().to_string())
+            .collect();
+        let token_hashes: Vec<String> = tokens.iter().map(|t| token_hash(t)).collect();
+        self.ballot_repository
+            .create_voter_tokens(poll_id, &token_hashes)
+            .await?;
+        Ok(tokens)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_voter_roll(&self, poll_id: i32) -> Result<VoterRoll, ServiceError> {
+        let roll = self.ballot_repository.count_voter_tokens(poll_id).await?;
+        Ok(roll)
+    }
+}
+
+/// Voter tokens are only stored as the hex encoded SHA-256 hash of their text.
+fn token_hash(token: &str) -> String {
+    Sha256::digest(token.trim().as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{repository::memory, service::BallotService as _};
+
+    use super::*;
+
+    const VOTER: &str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+    const OTHER_VOTER: &str = "1f0e6a0a-6f2d-4d39-8f55-2f5d2b8a3c11";
+
+    fn service(mode: RegistrationMode) -> BallotService<memory::ballot::BallotRepository> {
+        BallotService::new(
+            memory::ballot::BallotRepository::new(memory::Store::default()),
+            mode,
+            Some(Secret::new("letmein".to_string())),
+        )
+    }
+
+    #[tokio::test]
+    async fn register_with_invite_code() {
+        let service = service(RegistrationMode::InviteCode);
+        assert!(matches!(
+            service.register(1, VOTER, None).await,
+            Err(ServiceError::InvalidInviteCode)
+        ));
+        assert!(matches!(
+            service.register(1, VOTER, Some("guess")).await,
+            Err(ServiceError::InvalidInviteCode)
+        ));
+        service.register(1, VOTER, Some("letmein")).await.unwrap();
+        // Voters with a ballot do not need the code again
+        service.register(1, VOTER, None).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn register_with_voter_token_once() {
+        let service = service(RegistrationMode::VoterRoll);
+        let tokens = service.issue_voter_tokens(1, 2).await.unwrap();
+        assert_eq!(tokens.len(), 2);
+
+        service.register(1, VOTER, Some(&tokens[0])).await.unwrap();
+        assert!(matches!(
+            service.register(1, OTHER_VOTER, Some(&tokens[0])).await,
+            Err(ServiceError::InvalidVoterToken)
+        ));
+        service.register(1, VOTER, None).await.unwrap();
+
+        let roll = service.get_voter_roll(1).await.unwrap();
+        assert_eq!((roll.issued, roll.used), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn voter_token_is_kept_when_the_ballot_is_not_created() {
+        let service = service(RegistrationMode::VoterRoll);
+        // There is no poll 2, so its ballots can not be created
+        let tokens = service.issue_voter_tokens(2, 1).await.unwrap();
+        assert!(matches!(
+            service.register(2, VOTER, Some(&tokens[0])).await,
+            Err(ServiceError::Repository(_))
+        ));
+        let roll = service.get_voter_roll(2).await.unwrap();
+        assert_eq!((roll.issued, roll.used), (1, 0));
+    }
 }
@@ -1,33 +1,165 @@
-use crate::{model::Item, repository::ItemRepository};
+use crate::{
+    model::{Ballot, Item, NewItem, PollStatus},
+    repository::{ItemRepository, PollRepository},
+};
 use async_trait::async_trait;
 
-use super::ServiceError;
+use super::{tally::TallyCache, ServiceError};
 
 #[derive(Clone)]
-pub struct ItemService<I> {
+pub struct ItemService<P, I> {
+    poll_repository: P,
     item_repository: I,
+    tally_cache: TallyCache,
 }
 
-impl<I> ItemService<I> {
-    pub fn new(item_repository: I) -> Self {
-        Self { item_repository }
+impl<P, I> ItemService<P, I>
+where
+    P: PollRepository,
+{
+    pub fn new(poll_repository: P, item_repository: I, tally_cache: TallyCache) -> Self {
+        Self {
+            poll_repository,
+            item_repository,
+            tally_cache,
+        }
+    }
+
+    /// The items of a closed poll are part of its final tally, so they can not be changed.
+    async fn ensure_not_closed(&self, poll_id: i32) -> Result<(), ServiceError> {
+        match self.poll_repository.find_by_id(poll_id).await? {
+            Some(poll) if poll.current_status() == PollStatus::Closed => {
+                Err(ServiceError::ItemsLocked)
+            }
+            _ => Ok(()),
+        }
     }
 }
 
 #[async_trait]
-impl<I> super::ItemService for ItemService<I>
+impl<P, I> super::ItemService for ItemService<P, I>
 where
+    P: PollRepository,
     I: ItemRepository,
 {
     #[tracing::instrument(skip(self))]
     async fn get_ballot_items(
         &self,
-        ballot_id: i32,
-    ) -> Result<(Vec<Item>, Vec<Item>), ServiceError> {
+        ballot: &Ballot,
+    ) -> Result<(Vec<Vec<Item>>, Vec<Item>), ServiceError> {
         let (ranked, unranked) = futures::try_join!(
-            self.item_repository.find_ranked_by_ballot(ballot_id),
-            self.item_repository.find_unranked_by_ballot(ballot_id),
+            self.item_repository.find_ranked_by_ballot(ballot.id),
+            self.item_repository
+                .find_unranked_by_ballot(ballot.poll_id, ballot.id),
         )?;
-        Ok((ranked, unranked))
+        let ranks = ranked
+            .chunk_by(|a, b| a.ord == b.ord)
+            .map(|rank| rank.iter().map(|r| r.item.clone()).collect())
+            .collect();
+        Ok((ranks, unranked))
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_poll_items(&self, poll_id: i32) -> Result<Vec<Item>, ServiceError> {
+        let items = self.item_repository.find_by_poll(poll_id).await?;
+        Ok(items)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn create_item(
+        &self,
+        poll_id: i32,
+        title: &str,
+        content: &str,
+    ) -> Result<(), ServiceError> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(ServiceError::MissingItemTitle);
+        }
+        let cached = self.tally_cache.poll(poll_id);
+        let _writes = cached.lock_writes().await;
+        self.ensure_not_closed(poll_id).await?;
+        self.item_repository
+            .create(NewItem {
+                poll_id,
+                title: title.to_string(),
+                content: content.trim().to_string(),
+            })
+            .await?;
+        cached.invalidate();
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_item(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        title: &str,
+        content: &str,
+    ) -> Result<bool, ServiceError> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(ServiceError::MissingItemTitle);
+        }
+        let cached = self.tally_cache.poll(poll_id);
+        let _writes = cached.lock_writes().await;
+        self.ensure_not_closed(poll_id).await?;
+        let updated = self
+            .item_repository
+            .update_content(poll_id, item_id, title, content.trim())
+            .await?;
+        cached.invalidate();
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn reorder_items(&self, poll_id: i32, item_ids: &[i32]) -> Result<(), ServiceError> {
+        if !item_ids.is_empty() {
+            let cached = self.tally_cache.poll(poll_id);
+            let _writes = cached.lock_writes().await;
+            self.ensure_not_closed(poll_id).await?;
+            self.item_repository
+                .update_positions(poll_id, item_ids)
+                .await?;
+            cached.invalidate();
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn set_item_done(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        done: bool,
+    ) -> Result<bool, ServiceError> {
+        let cached = self.tally_cache.poll(poll_id);
+        let _writes = cached.lock_writes().await;
+        self.ensure_not_closed(poll_id).await?;
+        let updated = self
+            .item_repository
+            .update_done(poll_id, item_id, done)
+            .await?;
+        cached.invalidate();
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn set_item_retired(
+        &self,
+        poll_id: i32,
+        item_id: i32,
+        retired: bool,
+    ) -> Result<bool, ServiceError> {
+        let cached = self.tally_cache.poll(poll_id);
+        let _writes = cached.lock_writes().await;
+        self.ensure_not_closed(poll_id).await?;
+        let updated = self
+            .item_repository
+            .update_retired(poll_id, item_id, retired)
+            .await?;
+        cached.invalidate();
+        Ok(updated)
     }
 }
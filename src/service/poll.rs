@@ -0,0 +1,130 @@
+use async_trait::async_trait;
+use time::OffsetDateTime;
+
+use crate::{
+    model::{BallotType, NewPoll, Poll, PollStatus},
+    repository::PollRepository,
+};
+
+use super::ServiceError;
+
+#[derive(Clone)]
+pub struct PollService<P> {
+    poll_repository: P,
+}
+
+impl<P> PollService<P> {
+    pub fn new(poll_repository: P) -> Self {
+        Self { poll_repository }
+    }
+}
+
+#[async_trait]
+impl<P> super::PollService for PollService<P>
+where
+    P: PollRepository,
+{
+    #[tracing::instrument(skip(self))]
+    async fn check_database(&self) -> Result<(), ServiceError> {
+        self.poll_repository.ping().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_polls(&self) -> Result<Vec<Poll>, ServiceError> {
+        let polls = self.poll_repository.find_all().await?;
+        Ok(polls)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn find_poll(&self, poll_id: i32) -> Result<Option<Poll>, ServiceError> {
+        let poll = self.poll_repository.find_by_id(poll_id).await?;
+        Ok(poll)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn create_poll(&self, title: &str, description: &str) -> Result<i32, ServiceError> {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(ServiceError::MissingPollTitle);
+        }
+        let poll_id = self
+            .poll_repository
+            .create(NewPoll {
+                title: title.to_string(),
+                description: description.trim().to_string(),
+            })
+            .await?;
+        Ok(poll_id)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_poll_lifecycle(
+        &self,
+        poll_id: i32,
+        status: PollStatus,
+        opens_at: Option<OffsetDateTime>,
+        closes_at: Option<OffsetDateTime>,
+    ) -> Result<bool, ServiceError> {
+        if let (Some(opens_at), Some(closes_at)) = (opens_at, closes_at) {
+            if closes_at <= opens_at {
+                return Err(ServiceError::InvalidSchedule);
+            }
+        }
+        let poll = match self.poll_repository.find_by_id(poll_id).await? {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+        // The final tally of a closed poll must stay as it is.
+        if poll.current_status() == PollStatus::Closed {
+            return Err(ServiceError::PollClosed);
+        }
+        let updated = self
+            .poll_repository
+            .update_lifecycle(poll_id, status, opens_at, closes_at)
+            .await?;
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_poll_seats(&self, poll_id: i32, seats: i32) -> Result<bool, ServiceError> {
+        if seats < 1 {
+            return Err(ServiceError::InvalidSeats);
+        }
+        let poll = match self.poll_repository.find_by_id(poll_id).await? {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+        if poll.current_status() == PollStatus::Closed {
+            return Err(ServiceError::PollClosed);
+        }
+        if seats > 1 && poll.ballot_type.is_scored() {
+            return Err(ServiceError::ScoredPollSeats);
+        }
+        let updated = self.poll_repository.update_seats(poll_id, seats).await?;
+        Ok(updated)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_poll_ballot_type(
+        &self,
+        poll_id: i32,
+        ballot_type: BallotType,
+    ) -> Result<bool, ServiceError> {
+        let poll = match self.poll_repository.find_by_id(poll_id).await? {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+        if poll.current_status() != PollStatus::Draft {
+            return Err(ServiceError::BallotTypeLocked);
+        }
+        if poll.seats > 1 && ballot_type.is_scored() {
+            return Err(ServiceError::ScoredPollSeats);
+        }
+        let updated = self
+            .poll_repository
+            .update_ballot_type(poll_id, ballot_type)
+            .await?;
+        Ok(updated)
+    }
+}
@@ -1,63 +1,552 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
 use async_trait::async_trait;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
 
 use crate::{
-    irv::{instant_runoff_vote, InstantRunoffVotingResult},
-    model::Item,
-    repository::TransactableRankingRepository,
+    audit,
+    irv::instant_runoff_trace,
+    metrics::metrics,
+    model::{
+        AuditReport, Ballot, BallotType, BestItemUpdate, ElectionTrace, Item, NewBallotRevision,
+        NewScore, PairwiseComparison, Poll, PollSnapshot, PollStatus, ScoreTally, Standing,
+        TallyResult, TallyTrace,
+    },
+    pairwise::PairwiseMatrix,
+    repository::{ItemRepository, PollRepository, TransactableRankingRepository},
+    score::{approval_vote, score_vote, star_vote},
+    stv::single_transferable_vote_trace,
+    voting::{
+        complete_ranking,
+        tie_break::{TieBreak, TieBreaker},
+        Method, VotingMethod,
+    },
 };
 
-use super::ServiceError;
+use super::{
+    tally::{PollTally, TallyCache},
+    BallotProblem, ServiceError,
+};
 
 #[derive(Clone)]
-pub struct RankingService<R> {
+pub struct RankingService<R, I, P> {
     ranking_repository: R,
+    item_repository: I,
+    poll_repository: P,
+    tally_cache: TallyCache,
+    voting_method: Method,
+    tie_break: TieBreak,
+    seed: u64,
+    updates: broadcast::Sender<BestItemUpdate>,
 }
 
-impl<R> RankingService<R> {
-    pub fn new(ranking_repository: R) -> Self {
-        Self { ranking_repository }
+/// Number of updates kept for subscribers that fall behind.
+const UPDATES_CAPACITY: usize = 64;
+
+impl<R, I, P> RankingService<R, I, P> {
+    pub fn new(
+        ranking_repository: R,
+        item_repository: I,
+        poll_repository: P,
+        tally_cache: TallyCache,
+        voting_method: Method,
+        tie_break: TieBreak,
+        seed: u64,
+    ) -> Self {
+        let (updates, _) = broadcast::channel(UPDATES_CAPACITY);
+        Self {
+            ranking_repository,
+            item_repository,
+            poll_repository,
+            tally_cache,
+            voting_method,
+            tie_break,
+            seed,
+            updates,
+        }
+    }
+
+    fn tie_breaker(&self) -> TieBreaker {
+        TieBreaker::new(self.tie_break, self.seed)
+    }
+
+    /// Run the poll on the given ballots using the configured voting method, or the method of
+    /// the ballot type when ballots are scored.
+    fn tally(&self, tally: &PollTally, ballot_type: BallotType) -> TallyResult {
+        if ballot_type.is_scored() {
+            return self.score_tally(tally, ballot_type).result;
+        }
+        let ballots = tally.ballots();
+        let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+        metrics().time_tally(self.voting_method.as_str(), || {
+            self.voting_method
+                .tally(&ballots, &mut self.tie_breaker())
+                .into()
+        })
+    }
+
+    /// Total the scored ballots using the method of the ballot type.
+    fn score_tally(&self, tally: &PollTally, ballot_type: BallotType) -> ScoreTally {
+        let ballots = tally.score_ballots();
+        let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+        let mut tie_breaker = self.tie_breaker();
+        metrics().time_tally(ballot_type.as_str(), || match ballot_type {
+            BallotType::Approval => approval_vote(&ballots, &mut tie_breaker).into(),
+            BallotType::Star => star_vote(&ballots, &mut tie_breaker).into(),
+            BallotType::Ranked | BallotType::Score => score_vote(&ballots, &mut tie_breaker).into(),
+        })
+    }
+
+    /// Run the poll on the given ballots using instant-runoff voting, keeping every round.
+    fn trace(&self, tally: &PollTally) -> TallyTrace {
+        let ballots = tally.ballots();
+        let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+        let mut tie_breaker = self.tie_breaker();
+        let trace = metrics().time_tally(Method::InstantRunoff.as_str(), || {
+            instant_runoff_trace(&ballots, &mut tie_breaker)
+        });
+        TallyTrace::new(trace, &tie_breaker)
+    }
+
+    /// Place every item of the poll, tied items share a place.
+    fn leaderboard(&self, tally: &PollTally, ballot_type: BallotType) -> Vec<Standing> {
+        let places: Vec<Vec<&Item>> = if ballot_type.is_scored() {
+            // Items with the same total share a place.
+            let ballots = tally.score_ballots();
+            let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+            let mut tie_breaker = self.tie_breaker();
+            let trace = match ballot_type {
+                BallotType::Approval => approval_vote(&ballots, &mut tie_breaker),
+                _ => score_vote(&ballots, &mut tie_breaker),
+            };
+            trace
+                .totals
+                .chunk_by(|(_, x), (_, y)| x == y)
+                .map(|totals| totals.iter().map(|&(&item, _)| item).collect())
+                .collect()
+        } else {
+            let ballots = tally.ballots();
+            let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+            complete_ranking(&self.voting_method, &ballots, &mut self.tie_breaker())
+        };
+
+        let ranked: HashSet<i32> = places.iter().flatten().map(|item| item.id).collect();
+        let unranked: Vec<&Item> = tally
+            .items()
+            .filter(|item| !ranked.contains(&item.id))
+            .collect();
+        let mut groups: Vec<_> = places.into_iter().map(|items| (items, false)).collect();
+        if !unranked.is_empty() {
+            groups.push((unranked, true));
+        }
+
+        let mut place = 1;
+        groups
+            .into_iter()
+            .map(|(mut items, unranked)| {
+                items.sort_by_key(|item| item.position);
+                let standing = Standing {
+                    place,
+                    items: items.into_iter().cloned().collect(),
+                    unranked,
+                };
+                place += standing.items.len();
+                standing
+            })
+            .collect()
+    }
+
+    /// Elect the given number of items using the single transferable vote, keeping every round.
+    fn elect(&self, tally: &PollTally, seats: usize) -> ElectionTrace {
+        let ballots = tally.ballots();
+        let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+        let mut tie_breaker = self.tie_breaker();
+        let trace = metrics().time_tally("single_transferable_vote", || {
+            single_transferable_vote_trace(&ballots, seats, &mut tie_breaker)
+        });
+        ElectionTrace::new(trace, &tie_breaker)
+    }
+}
+
+impl<R, I, P> RankingService<R, I, P>
+where
+    R: TransactableRankingRepository,
+    I: ItemRepository,
+    P: PollRepository,
+{
+    /// Run a function on the cached ballots of the poll, loading them if they are not cached.
+    /// The ballots of a closed poll are not cached, they are only read again for what is not part
+    /// of its final tally.
+    async fn with_poll_tally<F, T>(&self, poll: &Poll, f: F) -> Result<T, ServiceError>
+    where
+        F: FnOnce(&PollTally) -> T + Send,
+    {
+        let cached = self.tally_cache.poll(poll.id);
+        if poll.current_status() == PollStatus::Closed {
+            cached.invalidate();
+            drop(cached);
+            return Ok(f(&self.load_tally(poll.id).await?));
+        }
+        let (f, generation) = match cached.with_tally(f) {
+            Ok(v) => return Ok(v),
+            Err(v) => v,
+        };
+        let tally = self.load_tally(poll.id).await?;
+        Ok(cached.load(generation, tally, f))
+    }
+
+    async fn load_tally(&self, poll_id: i32) -> Result<PollTally, ServiceError> {
+        let (items, rankings, scores) = futures::try_join!(
+            self.item_repository.find_by_poll(poll_id),
+            self.ranking_repository.get_by_poll(poll_id),
+            self.ranking_repository.get_scores_by_poll(poll_id),
+        )?;
+        Ok(PollTally::new(items, rankings, scores))
+    }
+
+    /// Check that a ballot only gives active items of the poll, each at most once.
+    async fn validate_ballot<T>(&self, poll_id: i32, item_ids: T) -> Result<(), ServiceError>
+    where
+        T: IntoIterator<Item = i32> + Send,
+    {
+        let items = self.item_repository.find_by_poll(poll_id).await?;
+        let problems = ballot_problems(&items, item_ids);
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ServiceError::InvalidBallot(problems))
+        }
+    }
+
+    /// Check that the poll is open while holding the cached ballots, so that its final tally can
+    /// not be taken in the middle of a change.
+    async fn ensure_open(&self, poll_id: i32) -> Result<Poll, ServiceError> {
+        let poll = self.poll_repository.find_by_id(poll_id).await?;
+        match poll {
+            Some(poll) => match poll.current_status() {
+                PollStatus::Open => Ok(poll),
+                PollStatus::Closed => Err(ServiceError::PollClosed),
+                PollStatus::Draft => Err(ServiceError::PollNotOpen),
+            },
+            None => Err(ServiceError::PollNotOpen),
+        }
+    }
+
+    /// Store a new revision of the ballot that is chained to the previous revision of its poll.
+    async fn txn_record_revision(
+        &self,
+        txn: &mut R::Txn<'_>,
+        ballot: &Ballot,
+        items: String,
+    ) -> Result<(), ServiceError> {
+        let previous_hash = self
+            .ranking_repository
+            .txn_find_last_revision_hash(txn, ballot.poll_id)
+            .await?
+            .unwrap_or_else(|| audit::GENESIS_HASH.to_string());
+        let created_at = audit::revision_timestamp(OffsetDateTime::now_utc());
+        let hash = audit::revision_hash(
+            &previous_hash,
+            ballot.poll_id,
+            ballot.id,
+            &items,
+            created_at,
+        );
+        self.ranking_repository
+            .txn_create_revision(
+                txn,
+                NewBallotRevision {
+                    poll_id: ballot.poll_id,
+                    ballot_id: ballot.id,
+                    items,
+                    created_at,
+                    previous_hash,
+                    hash,
+                },
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Notify the subscribers of the new best items of the poll. The change of the ballot is
+    /// already stored, so failing to run the poll again is only logged.
+    async fn broadcast_best_items(&self, poll: &Poll) {
+        // Only run the poll again when someone is listening.
+        if self.updates.receiver_count() == 0 {
+            return;
+        }
+        match super::RankingService::get_best_items(self, poll).await {
+            Ok(best_items) => {
+                // Sending only fails when every subscriber is gone in the meantime.
+                let _ = self.updates.send(BestItemUpdate {
+                    poll_id: poll.id,
+                    best_items,
+                });
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, poll_id = poll.id, "Failed to broadcast the best items")
+            }
+        }
     }
 }
 
 #[async_trait]
-impl<R> super::RankingService for RankingService<R>
+impl<R, I, P> super::RankingService for RankingService<R, I, P>
 where
     R: TransactableRankingRepository,
+    I: ItemRepository,
+    P: PollRepository,
 {
+    fn voting_method(&self) -> Method {
+        self.voting_method
+    }
+
     #[tracing::instrument(skip(self))]
-    async fn get_instant_runoff_result(&self) -> Result<Option<Item>, ServiceError> {
-        let rankings = self.ranking_repository.get_all().await?;
+    async fn get_best_items(&self, poll: &Poll) -> Result<Vec<Item>, ServiceError> {
+        if poll.elected_seats() > 1 {
+            let election = self.get_election_trace(poll).await?;
+            return Ok(election.elected);
+        }
+        let best_items = match self.get_result(poll).await? {
+            TallyResult::NoWinner => Vec::new(),
+            TallyResult::Tied(_) => Vec::new(),
+            TallyResult::Winner(winner) => vec![winner],
+        };
+        Ok(best_items)
+    }
 
-        let mut ballots = vec![Vec::new()];
-        let mut current_ballot = 0;
-        let mut last_ballot_id = None;
-        for ranking in rankings {
-            if let Some(ballot_id) = last_ballot_id {
-                if ballot_id != ranking.ballot.id {
-                    ballots.push(Vec::new());
-                    current_ballot += 1;
-                }
-            }
-            last_ballot_id = Some(ranking.ballot.id);
-            ballots[current_ballot].push(ranking.item);
+    #[tracing::instrument(skip(self))]
+    async fn get_result(&self, poll: &Poll) -> Result<TallyResult, ServiceError> {
+        if let Some(snapshot) = self.get_final_tally(poll).await? {
+            return Ok(snapshot.result);
         }
+        let ballot_type = poll.ballot_type;
+        self.with_poll_tally(poll, |tally| {
+            if let Some(result) = tally.result(ballot_type) {
+                return result.clone();
+            }
+            let result = self.tally(tally, ballot_type);
+            tally.set_result(ballot_type, result.clone());
+            result
+        })
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_result_at(
+        &self,
+        poll: &Poll,
+        at: OffsetDateTime,
+    ) -> Result<TallyResult, ServiceError> {
+        let (items, revisions) = futures::try_join!(
+            self.item_repository.find_by_poll(poll.id),
+            self.ranking_repository.get_revisions_by_poll(poll.id),
+        )?;
+        let tally = PollTally::new(
+            items,
+            audit::rankings_at(&revisions, at),
+            audit::scores_at(&revisions, at),
+        );
+        Ok(self.tally(&tally, poll.ballot_type))
+    }
 
-        // Get poll result
-        let ballots: Vec<_> = ballots.iter().map(|v| v.as_slice()).collect();
-        let best_item = match instant_runoff_vote(&ballots) {
-            InstantRunoffVotingResult::NoWinner => None,
-            InstantRunoffVotingResult::Tied(_) => None,
-            InstantRunoffVotingResult::Winner(winner) => Some(winner.clone()),
+    #[tracing::instrument(skip(self))]
+    async fn get_audit_report(&self, poll_id: i32) -> Result<AuditReport, ServiceError> {
+        let (rankings, scores, revisions) = futures::try_join!(
+            self.ranking_repository.get_by_poll(poll_id),
+            self.ranking_repository.get_scores_by_poll(poll_id),
+            self.ranking_repository.get_revisions_by_poll(poll_id),
+        )?;
+        let (chain_error, broken_revision) = match audit::verify_chain(&revisions) {
+            Ok(()) => (None, None),
+            Err(err) => (Some(err.to_string()), Some(err.revision_id())),
         };
-        Ok(best_item)
+
+        // Items that share a rank are compared regardless of their order within the rank.
+        let mut current: BTreeMap<i32, BTreeMap<i32, Vec<i32>>> = BTreeMap::new();
+        for ranking in rankings {
+            current
+                .entry(ranking.ballot_id)
+                .or_default()
+                .entry(ranking.ord)
+                .or_default()
+                .push(ranking.item_id);
+        }
+        let current: BTreeMap<i32, Vec<Vec<i32>>> = current
+            .into_iter()
+            .map(|(ballot_id, ranks)| (ballot_id, ranks.into_values().map(sorted).collect()))
+            .collect();
+        let mut current_scores: BTreeMap<i32, Vec<(i32, u32)>> = BTreeMap::new();
+        for score in scores {
+            current_scores
+                .entry(score.ballot_id)
+                .or_default()
+                .push((score.item_id, score.score.max(0) as u32));
+        }
+        let latest = audit::latest_revisions(&revisions, None);
+        let mismatched_ballots: Vec<i32> = latest
+            .iter()
+            .filter(|(ballot_id, revision)| {
+                let items = current
+                    .get(ballot_id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                // Scores are stored by item id, in whatever order they were recorded.
+                let mut recorded_scores = audit::decode_scores(&revision.items);
+                recorded_scores.sort_unstable();
+                let scores = current_scores
+                    .get(ballot_id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let recorded_items: Vec<_> = audit::decode_items(&revision.items)
+                    .into_iter()
+                    .map(sorted)
+                    .collect();
+                recorded_items != items || recorded_scores != scores
+            })
+            .map(|(ballot_id, _)| *ballot_id)
+            .collect();
+        let unrecorded_ballots: BTreeSet<i32> = current
+            .keys()
+            .chain(current_scores.keys())
+            .filter(|ballot_id| !latest.contains_key(ballot_id))
+            .copied()
+            .collect();
+
+        Ok(AuditReport {
+            valid: chain_error.is_none()
+                && mismatched_ballots.is_empty()
+                && unrecorded_ballots.is_empty(),
+            revisions: revisions.len(),
+            chain_error,
+            broken_revision,
+            mismatched_ballots,
+            unrecorded_ballots: unrecorded_ballots.into_iter().collect(),
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_instant_runoff_trace(&self, poll: &Poll) -> Result<TallyTrace, ServiceError> {
+        if let Some(snapshot) = self.get_final_tally(poll).await? {
+            return Ok(snapshot.trace);
+        }
+        self.with_poll_tally(poll, |tally| self.trace(tally)).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_election_trace(&self, poll: &Poll) -> Result<ElectionTrace, ServiceError> {
+        if let Some(election) = self
+            .get_final_tally(poll)
+            .await?
+            .and_then(|snapshot| snapshot.election)
+        {
+            return Ok(election);
+        }
+        let seats = poll.elected_seats();
+        self.with_poll_tally(poll, |tally| self.elect(tally, seats))
+            .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_final_tally(&self, poll: &Poll) -> Result<Option<PollSnapshot>, ServiceError> {
+        // Polls that are open are read without waiting for the changes that are being made. A
+        // closed poll can not be opened again.
+        if poll.current_status() != PollStatus::Closed {
+            return Ok(None);
+        }
+        let poll_id = poll.id;
+        if let Some(snapshot) = self.poll_repository.find_snapshot(poll_id).await? {
+            return Ok(Some(snapshot));
+        }
+        // A change that was checked just before the poll closed is stored before the writes are
+        // given up, so the final tally is taken while holding them.
+        let cached = self.tally_cache.poll(poll_id);
+        let _writes = cached.lock_writes().await;
+        if let Some(snapshot) = self.poll_repository.find_snapshot(poll_id).await? {
+            return Ok(Some(snapshot));
+        }
+        let (ballot_type, seats) = (poll.ballot_type, poll.elected_seats());
+        let (result, trace, election, scores) = self
+            .with_poll_tally(poll, |tally| {
+                let election = (seats > 1).then(|| self.elect(tally, seats));
+                let scores = ballot_type
+                    .is_scored()
+                    .then(|| self.score_tally(tally, ballot_type));
+                (
+                    self.tally(tally, ballot_type),
+                    self.trace(tally),
+                    election,
+                    scores,
+                )
+            })
+            .await?;
+        self.poll_repository
+            .create_snapshot(&PollSnapshot {
+                poll_id,
+                result,
+                trace,
+                election,
+                scores,
+                created_at: audit::revision_timestamp(OffsetDateTime::now_utc()),
+            })
+            .await?;
+        // Another request might have stored its snapshot first, which is the one that is kept.
+        let snapshot = self.poll_repository.find_snapshot(poll_id).await?;
+        Ok(snapshot)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_leaderboard(&self, poll: &Poll) -> Result<Vec<Standing>, ServiceError> {
+        let ballot_type = poll.ballot_type;
+        self.with_poll_tally(poll, |tally| self.leaderboard(tally, ballot_type))
+            .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_score_tally(&self, poll: &Poll) -> Result<ScoreTally, ServiceError> {
+        if let Some(scores) = self
+            .get_final_tally(poll)
+            .await?
+            .and_then(|snapshot| snapshot.scores)
+        {
+            return Ok(scores);
+        }
+        let ballot_type = poll.ballot_type;
+        self.with_poll_tally(poll, |tally| self.score_tally(tally, ballot_type))
+            .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_pairwise_comparison(
+        &self,
+        poll: &Poll,
+    ) -> Result<PairwiseComparison, ServiceError> {
+        self.with_poll_tally(poll, |tally| {
+            let ballots = tally.ballots();
+            let ballots: Vec<_> = ballots.iter().map(Vec::as_slice).collect();
+            PairwiseMatrix::new(&ballots).into()
+        })
+        .await
     }
 
     #[tracing::instrument(skip(self))]
     async fn update_ballot_rankings(
         &self,
-        ballot_id: i32,
-        ranked_item_ids: &[i32],
+        ballot: &Ballot,
+        ranks: &[Vec<i32>],
     ) -> Result<(), ServiceError> {
+        let ballot_id = ballot.id;
+        let ranks: Vec<_> = ranks.iter().filter(|r| !r.is_empty()).cloned().collect();
+        let cached = self.tally_cache.poll(ballot.poll_id);
+        let writes = cached.lock_writes().await;
+        let poll = self.ensure_open(ballot.poll_id).await?;
+        if poll.ballot_type.is_scored() {
+            return Err(ServiceError::WrongBallotType(poll.ballot_type));
+        }
+        self.validate_ballot(poll.id, ranks.iter().flatten().copied().collect::<Vec<_>>())
+            .await?;
+
         // START TRANSACTION
         let mut txn = self.ranking_repository.begin().await?;
 
@@ -65,23 +554,360 @@ where
             .txn_remove_ballot_rankings(&mut txn, ballot_id)
             .await?;
 
-        let mut rankings_iter =
-            ranked_item_ids
-                .iter()
-                .enumerate()
-                .map(|(ord, item_id)| crate::model::NewRanking {
-                    ord: ord as i32,
-                    item_id: *item_id,
-                    ballot_id,
-                });
+        // Items that share a rank are stored with the same order.
+        let mut rankings = Vec::new();
+        for (ord, rank) in ranks.iter().enumerate() {
+            rankings.extend(rank.iter().map(|item_id| crate::model::NewRanking {
+                ord: ord as i32,
+                item_id: *item_id,
+                ballot_id,
+            }));
+        }
+        let mut rankings_iter = rankings.into_iter();
         while rankings_iter.len() != 0 {
             self.ranking_repository
                 .txn_create_bulk(&mut txn, &mut rankings_iter)
                 .await?;
         }
 
+        // Every change is kept as a revision that is chained to the previous one of the poll.
+        self.txn_record_revision(&mut txn, ballot, audit::encode_items(&ranks))
+            .await?;
+
+        // END TRANSACTION
+        self.ranking_repository.end(txn).await?;
+
+        cached.update(|tally| tally.update_ballot(ballot_id, &ranks));
+        drop(writes);
+        metrics().ballot_updated(ballot.poll_id);
+
+        self.broadcast_best_items(&poll).await;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_ballot_scores(
+        &self,
+        poll: &Poll,
+        ballot: &Ballot,
+    ) -> Result<Vec<(i32, u32)>, ServiceError> {
+        self.with_poll_tally(poll, |tally| tally.ballot_scores(ballot.id))
+            .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_ballot_scores(
+        &self,
+        ballot: &Ballot,
+        scores: &[(i32, u32)],
+    ) -> Result<(), ServiceError> {
+        let ballot_id = ballot.id;
+        let cached = self.tally_cache.poll(ballot.poll_id);
+        let writes = cached.lock_writes().await;
+        let poll = self.ensure_open(ballot.poll_id).await?;
+        if !poll.ballot_type.is_scored() {
+            return Err(ServiceError::WrongBallotType(poll.ballot_type));
+        }
+        let max_score = poll.ballot_type.max_score();
+        if scores.iter().any(|&(_, score)| score > max_score) {
+            return Err(ServiceError::InvalidScore(max_score));
+        }
+        self.validate_ballot(
+            poll.id,
+            scores.iter().map(|&(id, _)| id).collect::<Vec<_>>(),
+        )
+        .await?;
+        // Items that are scored 0 are the same as items without a score.
+        let mut scores: Vec<_> = scores
+            .iter()
+            .copied()
+            .filter(|&(_, score)| score > 0)
+            .collect();
+        scores.sort_unstable();
+
+        // START TRANSACTION
+        let mut txn = self.ranking_repository.begin().await?;
+
+        self.ranking_repository
+            .txn_remove_ballot_scores(&mut txn, ballot_id)
+            .await?;
+
+        let mut scores_iter = scores.iter().map(|&(item_id, score)| NewScore {
+            item_id,
+            ballot_id,
+            score: score as i32,
+        });
+        while scores_iter.len() != 0 {
+            self.ranking_repository
+                .txn_create_score_bulk(&mut txn, &mut scores_iter)
+                .await?;
+        }
+
+        self.txn_record_revision(&mut txn, ballot, audit::encode_scores(&scores))
+            .await?;
+
         // END TRANSACTION
         self.ranking_repository.end(txn).await?;
+
+        cached.update(|tally| tally.update_ballot_scores(ballot_id, &scores));
+        drop(writes);
+        metrics().ballot_updated(ballot.poll_id);
+
+        self.broadcast_best_items(&poll).await;
         Ok(())
     }
+
+    fn subscribe(&self) -> broadcast::Receiver<BestItemUpdate> {
+        self.updates.subscribe()
+    }
+}
+
+fn sorted(mut item_ids: Vec<i32>) -> Vec<i32> {
+    item_ids.sort_unstable();
+    item_ids
+}
+
+/// Every problem of a ballot giving the item ids in order, each problem is reported once.
+fn ballot_problems<T>(items: &[Item], item_ids: T) -> Vec<BallotProblem>
+where
+    T: IntoIterator<Item = i32>,
+{
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for id in item_ids {
+        let problem = if !seen.insert(id) {
+            BallotProblem::DuplicateItem(id)
+        } else {
+            match items.iter().find(|item| item.id == id) {
+                None => BallotProblem::UnknownItem(id),
+                Some(item) if item.done || item.retired => BallotProblem::InactiveItem(id),
+                Some(_) => continue,
+            }
+        };
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use crate::{
+        model::{NewItem, NewPoll},
+        repository::{memory, BallotRepository as _, Transact as _},
+        service::RankingService as _,
+    };
+
+    use super::*;
+
+    type Service = RankingService<
+        memory::ranking::RankingRepository,
+        memory::item::ItemRepository,
+        memory::poll::PollRepository,
+    >;
+
+    /// A service over two open polls, each with two items and two ballots.
+    async fn service(tally_cache: TallyCache) -> (Service, Vec<Ballot>) {
+        let store = memory::Store::default();
+        let poll_repository = memory::poll::PollRepository::new(store.clone());
+        let item_repository = memory::item::ItemRepository::new(store.clone());
+        let ballot_repository = memory::ballot::BallotRepository::new(store.clone());
+        let poll_id = poll_repository
+            .create(NewPoll {
+                title: "Other poll".to_string(),
+                description: String::new(),
+            })
+            .await
+            .unwrap();
+        poll_repository
+            .update_lifecycle(poll_id, PollStatus::Open, None, None)
+            .await
+            .unwrap();
+        let mut ballots = Vec::new();
+        for poll_id in [1, poll_id] {
+            for title in ["Ada Lovelace", "Alan Turing"] {
+                item_repository
+                    .create(NewItem {
+                        poll_id,
+                        title: title.to_string(),
+                        content: String::new(),
+                    })
+                    .await
+                    .unwrap();
+            }
+            for _ in 0..2 {
+                let uuid = Uuid::new_v4();
+                ballot_repository
+                    .save_ignoring_conflict(poll_id, uuid)
+                    .await
+                    .unwrap();
+                let ballot = ballot_repository.find_by_uuid(poll_id, uuid).await;
+                ballots.push(ballot.unwrap().unwrap());
+            }
+        }
+        let service = RankingService::new(
+            memory::ranking::RankingRepository::new(store),
+            item_repository,
+            poll_repository,
+            tally_cache,
+            Method::Schulze,
+            TieBreak::None,
+            0,
+        );
+        (service, ballots)
+    }
+
+    async fn find_poll(service: &Service, poll_id: i32) -> Poll {
+        let poll = service.poll_repository.find_by_id(poll_id).await;
+        poll.unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn polls_do_not_wait_for_the_changes_of_other_polls() {
+        let tally_cache = TallyCache::default();
+        let (service, ballots) = service(tally_cache.clone()).await;
+        let timeout = Duration::from_millis(500);
+
+        // A change of the first poll that is being stored
+        let cached = tally_cache.poll(1);
+        let writes = cached.lock_writes().await;
+
+        let ranks = [vec![3]];
+        tokio::time::timeout(timeout, service.update_ballot_rankings(&ballots[2], &ranks))
+            .await
+            .expect("Other poll waited")
+            .unwrap();
+        let poll = find_poll(&service, 1).await;
+        let result = tokio::time::timeout(timeout, service.get_best_items(&poll))
+            .await
+            .expect("Reading the poll waited");
+        assert!(result.unwrap().is_empty());
+
+        let ranks = [vec![1]];
+        let update = service.update_ballot_rankings(&ballots[0], &ranks);
+        assert!(tokio::time::timeout(Duration::from_millis(50), update)
+            .await
+            .is_err());
+        drop(writes);
+    }
+
+    #[tokio::test]
+    async fn final_tally_waits_for_the_changes_being_stored() {
+        let tally_cache = TallyCache::default();
+        let (service, _) = service(tally_cache.clone()).await;
+
+        // A change that was checked just before the poll closed
+        let cached = tally_cache.poll(1);
+        let writes = cached.lock_writes().await;
+        service
+            .poll_repository
+            .update_lifecycle(1, PollStatus::Closed, None, None)
+            .await
+            .unwrap();
+
+        let poll = find_poll(&service, 1).await;
+        let final_tally = service.get_final_tally(&poll);
+        tokio::pin!(final_tally);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut final_tally)
+                .await
+                .is_err()
+        );
+        drop(writes);
+        assert!(final_tally.await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn closed_poll_is_dropped_from_the_cache() {
+        let tally_cache = TallyCache::default();
+        let (service, ballots) = service(tally_cache.clone()).await;
+        service
+            .update_ballot_rankings(&ballots[0], &[vec![1]])
+            .await
+            .unwrap();
+        let poll = find_poll(&service, 1).await;
+        service.get_best_items(&poll).await.unwrap();
+        assert!(tally_cache.poll(1).with_tally(|_| ()).is_ok());
+
+        service
+            .poll_repository
+            .update_lifecycle(1, PollStatus::Closed, None, None)
+            .await
+            .unwrap();
+        let poll = find_poll(&service, 1).await;
+        let best_items = service.get_best_items(&poll).await.unwrap();
+        assert_eq!(best_items.iter().map(|i| i.id).collect::<Vec<_>>(), [1]);
+        assert!(tally_cache.poll(1).with_tally(|_| ()).is_err());
+
+        // Reading the ballots of the closed poll again does not cache them
+        service.get_leaderboard(&poll).await.unwrap();
+        assert!(tally_cache.poll(1).with_tally(|_| ()).is_err());
+    }
+
+    #[tokio::test]
+    async fn audit_reports_rankings_stored_without_a_revision() {
+        let (service, ballots) = service(TallyCache::default()).await;
+        let ranks = [vec![1]];
+        service
+            .update_ballot_rankings(&ballots[0], &ranks)
+            .await
+            .unwrap();
+        assert!(service.get_audit_report(1).await.unwrap().valid);
+
+        // A ranking that is written to the database without going through the service
+        let repository = &service.ranking_repository;
+        let mut txn = repository.begin().await.unwrap();
+        let mut rankings = std::iter::once(crate::model::NewRanking {
+            ord: 0,
+            item_id: 2,
+            ballot_id: ballots[1].id,
+        });
+        repository
+            .txn_create_bulk(&mut txn, &mut rankings)
+            .await
+            .unwrap();
+        repository.end(txn).await.unwrap();
+
+        let report = service.get_audit_report(1).await.unwrap();
+        assert!(!report.valid);
+        assert!(report.mismatched_ballots.is_empty());
+        assert_eq!(report.unrecorded_ballots, [ballots[1].id]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_changes_are_all_cached() {
+        let (service, ballots) = service(TallyCache::default()).await;
+        let mut tasks = Vec::new();
+        for round in 0..20 {
+            for ballot in ballots.clone() {
+                let service = service.clone();
+                // The first item of each poll is ranked first in the last round
+                let first = if ballot.poll_id == 1 { 1 } else { 3 };
+                let ranks = if round % 2 == 1 {
+                    vec![vec![first], vec![first + 1]]
+                } else {
+                    vec![vec![first + 1], vec![first]]
+                };
+                tasks.push(tokio::spawn(async move {
+                    service.update_ballot_rankings(&ballot, &ranks).await?;
+                    let poll = find_poll(&service, ballot.poll_id).await;
+                    service.get_best_items(&poll).await
+                }));
+            }
+            for task in tasks.drain(..) {
+                task.await.unwrap().unwrap();
+            }
+        }
+
+        for (poll_id, winner) in [(1, 1), (2, 3)] {
+            let poll = find_poll(&service, poll_id).await;
+            let cached = service.get_best_items(&poll).await.unwrap();
+            assert_eq!(cached.iter().map(|i| i.id).collect::<Vec<_>>(), [winner]);
+        }
+    }
 }
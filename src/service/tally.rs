@@ -0,0 +1,465 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Deref,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use crate::model::{BallotType, Item, Ranking, Score, TallyResult};
+
+/// Ballots of the polls that are kept in memory, so that a poll can be run again without loading
+/// all of its rankings. Entries are updated when a ballot changes and are dropped when an item of
+/// the poll changes. Every poll has its own locks, so that polls never wait for each other.
+#[derive(Clone, Default)]
+pub struct TallyCache {
+    polls: Arc<Mutex<HashMap<i32, Arc<CachedPoll>>>>,
+}
+
+impl TallyCache {
+    fn polls(&self) -> std::sync::MutexGuard<'_, HashMap<i32, Arc<CachedPoll>>> {
+        self.polls.lock().expect("tally cache lock is poisoned")
+    }
+
+    /// The cached ballots of the poll, which might not be loaded yet. The poll is only kept in
+    /// the cache while it is used or while its tally is loaded, so that polls which are gone or
+    /// whose tally was invalidated do not stay around.
+    pub fn poll(&self, poll_id: i32) -> PollEntry {
+        let cached = self.polls().entry(poll_id).or_default().clone();
+        PollEntry {
+            cache: self.clone(),
+            poll_id,
+            cached: Some(cached),
+        }
+    }
+}
+
+/// A poll of the cache that is in use.
+pub struct PollEntry {
+    cache: TallyCache,
+    poll_id: i32,
+    cached: Option<Arc<CachedPoll>>,
+}
+
+impl Deref for PollEntry {
+    type Target = CachedPoll;
+
+    fn deref(&self) -> &CachedPoll {
+        self.cached.as_ref().expect("poll entry is in use")
+    }
+}
+
+impl Drop for PollEntry {
+    fn drop(&mut self) {
+        // Entries are only shared and given up under the lock of the cache, so no other user can
+        // get hold of an entry that is removed.
+        let mut polls = self.cache.polls();
+        if let Some(cached) = self.cached.take() {
+            if Arc::strong_count(&cached) == 2 && !cached.is_loaded() {
+                polls.remove(&self.poll_id);
+            }
+        }
+    }
+}
+
+/// The cached ballots of a poll. The tally is only locked while it is taken or changed in memory,
+/// never while waiting for the database or while the poll is run on it.
+#[derive(Default)]
+pub struct CachedPoll {
+    writes: tokio::sync::Mutex<()>,
+    state: Mutex<CachedTally>,
+}
+
+#[derive(Default)]
+struct CachedTally {
+    /// Shared with the readers that are running the poll, a change copies it when it is in use.
+    tally: Option<Arc<PollTally>>,
+    /// Incremented on every change, so that a tally that was loaded while the poll changed is
+    /// not cached.
+    generation: u64,
+}
+
+impl CachedPoll {
+    /// Wait for the changes of the ballots and the items of the poll that are being made, the
+    /// changes are made one at a time so that the revisions of the poll form a single chain.
+    /// Reading the tally does not wait for them.
+    pub async fn lock_writes(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.writes.lock().await
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CachedTally> {
+        self.state.lock().expect("tally cache lock is poisoned")
+    }
+
+    fn is_loaded(&self) -> bool {
+        self.state().tally.is_some()
+    }
+
+    /// Run a function on the tally if it is loaded, otherwise the function is given back along
+    /// with the generation that a tally being loaded must be stored with.
+    pub fn with_tally<F, T>(&self, f: F) -> Result<T, (F, u64)>
+    where
+        F: FnOnce(&PollTally) -> T,
+    {
+        let (tally, generation) = {
+            let state = self.state();
+            (state.tally.clone(), state.generation)
+        };
+        match tally {
+            Some(tally) => Ok(f(&tally)),
+            None => Err((f, generation)),
+        }
+    }
+
+    /// Run a function on a tally that was loaded from the database. The tally is cached unless
+    /// the poll changed since `generation`, in which case it might be missing the change.
+    pub fn load<F, T>(&self, generation: u64, tally: PollTally, f: F) -> T
+    where
+        F: FnOnce(&PollTally) -> T,
+    {
+        let tally = Arc::new(tally);
+        {
+            let mut state = self.state();
+            if state.generation == generation {
+                state.tally = Some(tally.clone());
+            }
+        }
+        f(&tally)
+    }
+
+    /// Apply a change that was stored in the database to the tally if it is loaded.
+    pub fn update(&self, f: impl FnOnce(&mut PollTally)) {
+        let mut state = self.state();
+        if let Some(tally) = state.tally.as_mut() {
+            f(Arc::make_mut(tally));
+        }
+        state.generation += 1;
+    }
+
+    /// Drop the tally after a change that it can not be updated with, it is loaded again when
+    /// it is next used.
+    pub fn invalidate(&self) {
+        let mut state = self.state();
+        state.tally = None;
+        state.generation += 1;
+    }
+}
+
+/// The ballots of a poll, made of ranks of item ids, along with the items that can be ranked.
+#[derive(Clone)]
+pub struct PollTally {
+    items: HashMap<i32, Item>,
+    ballots: BTreeMap<i32, Vec<Vec<i32>>>,
+    scores: BTreeMap<i32, Vec<(i32, u32)>>,
+    /// Set by the first reader that runs the poll after a change.
+    result: OnceLock<(BallotType, TallyResult)>,
+}
+
+impl PollTally {
+    /// Create the tally of a poll from its items and its rankings sorted by ballot id and
+    /// ranking order, and its scores sorted by ballot id. Items that are done or retired are left
+    /// out.
+    pub fn new(items: Vec<Item>, rankings: Vec<Ranking>, scores: Vec<Score>) -> Self {
+        let items: HashMap<_, _> = items
+            .into_iter()
+            .filter(|item| !item.done && !item.retired)
+            .map(|item| (item.id, item))
+            .collect();
+        let mut ballots: BTreeMap<i32, Vec<Vec<i32>>> = BTreeMap::new();
+        let mut last_ord = None;
+        for ranking in rankings {
+            if !items.contains_key(&ranking.item_id) {
+                continue;
+            }
+            let ranks = ballots.entry(ranking.ballot_id).or_default();
+            let ord = Some((ranking.ballot_id, ranking.ord));
+            match ranks.last_mut() {
+                Some(rank) if last_ord == ord => rank.push(ranking.item_id),
+                _ => ranks.push(vec![ranking.item_id]),
+            }
+            last_ord = ord;
+        }
+        let mut score_ballots: BTreeMap<i32, Vec<(i32, u32)>> = BTreeMap::new();
+        for score in scores {
+            if items.contains_key(&score.item_id) {
+                score_ballots
+                    .entry(score.ballot_id)
+                    .or_default()
+                    .push((score.item_id, score.score.max(0) as u32));
+            }
+        }
+        Self {
+            items,
+            ballots,
+            scores: score_ballots,
+            result: OnceLock::new(),
+        }
+    }
+
+    /// The ballots sorted by their id, each ballot lists its ranks from the most preferred.
+    pub fn ballots(&self) -> Vec<Vec<Vec<&Item>>> {
+        self.ballots
+            .values()
+            .map(|ranks| {
+                ranks
+                    .iter()
+                    .map(|ids| ids.iter().filter_map(|id| self.items.get(id)).collect())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The scored ballots sorted by their id, each ballot lists the scores of its items.
+    pub fn score_ballots(&self) -> Vec<Vec<(&Item, u32)>> {
+        self.scores
+            .values()
+            .map(|scores| {
+                scores
+                    .iter()
+                    .filter_map(|(id, score)| Some((self.items.get(id)?, *score)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The scores of a ballot as item ids with their score.
+    pub fn ballot_scores(&self, ballot_id: i32) -> Vec<(i32, u32)> {
+        self.scores.get(&ballot_id).cloned().unwrap_or_default()
+    }
+
+    /// The items that can be ranked, in no particular order.
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.items.values()
+    }
+
+    /// The result that was memoized since the last change of a ballot, if it was taken from the
+    /// ballots of the given type.
+    pub fn result(&self, ballot_type: BallotType) -> Option<&TallyResult> {
+        self.result
+            .get()
+            .filter(|(t, _)| *t == ballot_type)
+            .map(|(_, result)| result)
+    }
+
+    /// Memoize the result, unless another reader already did.
+    pub fn set_result(&self, ballot_type: BallotType, result: TallyResult) {
+        let _ = self.result.set((ballot_type, result));
+    }
+
+    /// Replace the rankings of a ballot.
+    pub fn update_ballot(&mut self, ballot_id: i32, ranks: &[Vec<i32>]) {
+        let ranks: Vec<Vec<_>> = ranks
+            .iter()
+            .map(|rank| {
+                rank.iter()
+                    .copied()
+                    .filter(|id| self.items.contains_key(id))
+                    .collect()
+            })
+            .filter(|rank: &Vec<_>| !rank.is_empty())
+            .collect();
+        if ranks.is_empty() {
+            self.ballots.remove(&ballot_id);
+        } else {
+            self.ballots.insert(ballot_id, ranks);
+        }
+        self.result = OnceLock::new();
+    }
+
+    /// Replace the scores of a ballot.
+    pub fn update_ballot_scores(&mut self, ballot_id: i32, scores: &[(i32, u32)]) {
+        let scores: Vec<_> = scores
+            .iter()
+            .copied()
+            .filter(|(id, _)| self.items.contains_key(id))
+            .collect();
+        if scores.is_empty() {
+            self.scores.remove(&ballot_id);
+        } else {
+            self.scores.insert(ballot_id, scores);
+        }
+        self.result = OnceLock::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: i32, done: bool, retired: bool) -> Item {
+        Item {
+            id,
+            title: format!("Item {id}"),
+            content: String::new(),
+            done,
+            position: id,
+            retired,
+        }
+    }
+
+    fn ranking(ballot_id: i32, item_id: i32, ord: i32) -> Ranking {
+        Ranking {
+            ballot_id,
+            item_id,
+            ord,
+        }
+    }
+
+    fn ballot_ids(tally: &PollTally) -> Vec<Vec<Vec<i32>>> {
+        tally
+            .ballots()
+            .into_iter()
+            .map(|ballot| {
+                ballot
+                    .into_iter()
+                    .map(|rank| rank.into_iter().map(|item| item.id).collect())
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn poll_tally_leaves_out_inactive_items() {
+        let items = vec![
+            item(1, false, false),
+            item(2, true, false),
+            item(3, false, true),
+        ];
+        let rankings = vec![
+            ranking(1, 2, 0),
+            ranking(1, 1, 1),
+            ranking(2, 3, 0),
+            ranking(3, 3, 0),
+            ranking(3, 1, 1),
+        ];
+        let tally = PollTally::new(items, rankings, Vec::new());
+        assert_eq!(ballot_ids(&tally), vec![vec![vec![1]], vec![vec![1]]]);
+    }
+
+    #[test]
+    fn poll_tally_groups_equal_ranks() {
+        let items = (1..=4).map(|id| item(id, false, false)).collect();
+        let rankings = vec![
+            ranking(1, 1, 0),
+            ranking(1, 2, 0),
+            ranking(1, 3, 1),
+            ranking(2, 4, 0),
+            ranking(2, 3, 0),
+        ];
+        let mut tally = PollTally::new(items, rankings, Vec::new());
+        assert_eq!(
+            ballot_ids(&tally),
+            vec![vec![vec![1, 2], vec![3]], vec![vec![4, 3]]]
+        );
+
+        tally.update_ballot(2, &[vec![5], vec![2, 1], vec![]]);
+        assert_eq!(
+            ballot_ids(&tally),
+            vec![vec![vec![1, 2], vec![3]], vec![vec![2, 1]]]
+        );
+    }
+
+    #[test]
+    fn poll_tally_update_ballot_replaces_rankings() {
+        let items = vec![item(1, false, false), item(2, false, false)];
+        let rankings = vec![ranking(1, 1, 0), ranking(1, 2, 1), ranking(2, 2, 0)];
+        let mut tally = PollTally::new(items, rankings, Vec::new());
+        tally.set_result(BallotType::Ranked, TallyResult::NoWinner);
+        assert!(tally.result(BallotType::Approval).is_none());
+
+        tally.update_ballot(3, &[vec![2], vec![1]]);
+        tally.update_ballot(1, &[vec![2], vec![4]]);
+        assert!(tally.result(BallotType::Ranked).is_none());
+        assert_eq!(
+            ballot_ids(&tally),
+            vec![vec![vec![2]], vec![vec![2]], vec![vec![2], vec![1]]]
+        );
+
+        tally.update_ballot(2, &[]);
+        assert_eq!(
+            ballot_ids(&tally),
+            vec![vec![vec![2]], vec![vec![2], vec![1]]]
+        );
+    }
+
+    #[test]
+    fn poll_tally_update_ballot_scores() {
+        let items = vec![item(1, false, false), item(2, false, true)];
+        let scores = vec![Score {
+            ballot_id: 1,
+            item_id: 1,
+            score: 3,
+        }];
+        let mut tally = PollTally::new(items, Vec::new(), scores);
+        tally.update_ballot_scores(2, &[(1, 5), (2, 4)]);
+
+        let ballots: Vec<Vec<(i32, u32)>> = tally
+            .score_ballots()
+            .into_iter()
+            .map(|ballot| ballot.into_iter().map(|(item, s)| (item.id, s)).collect())
+            .collect();
+        assert_eq!(ballots, vec![vec![(1, 3)], vec![(1, 5)]]);
+        assert!(tally.ballots().is_empty());
+    }
+
+    #[test]
+    fn tally_loaded_during_a_change_is_not_cached() {
+        let cached = CachedPoll::default();
+        let Err((_, generation)) = cached.with_tally(|_| ()) else {
+            panic!("tally is not loaded yet");
+        };
+        cached.update(|_| ());
+        let tally = PollTally::new(vec![item(1, false, false)], vec![], vec![]);
+        assert_eq!(cached.load(generation, tally, |t| t.items().count()), 1);
+        assert!(cached.with_tally(|_| ()).is_err());
+
+        let Err((_, generation)) = cached.with_tally(|_| ()) else {
+            panic!("tally is not loaded yet");
+        };
+        let tally = PollTally::new(vec![item(1, false, false)], vec![], vec![]);
+        cached.load(generation, tally, |_| ());
+        assert!(cached.with_tally(|_| ()).is_ok());
+        cached.invalidate();
+        assert!(cached.with_tally(|_| ()).is_err());
+    }
+
+    #[test]
+    fn polls_are_only_cached_while_used_or_loaded() {
+        let cache = TallyCache::default();
+        let cached = cache.poll(1);
+        drop(cache.poll(1));
+        assert_eq!(cache.polls().len(), 1);
+        drop(cached);
+        assert!(cache.polls().is_empty());
+
+        let cached = cache.poll(1);
+        let Err((_, generation)) = cached.with_tally(|_| ()) else {
+            panic!("tally is not loaded yet");
+        };
+        cached.load(generation, PollTally::new(vec![], vec![], vec![]), |_| ());
+        drop(cached);
+        assert!(cache.poll(1).with_tally(|_| ()).is_ok());
+
+        cache.poll(1).invalidate();
+        assert!(cache.polls().is_empty());
+    }
+
+    #[test]
+    fn tally_is_changed_while_a_reader_runs_the_poll() {
+        let cached = CachedPoll::default();
+        let Err((_, generation)) = cached.with_tally(|_| ()) else {
+            panic!("tally is not loaded yet");
+        };
+        let tally = PollTally::new(vec![item(1, false, false)], vec![], vec![]);
+        cached.load(generation, tally, |_| ());
+
+        // The reader keeps the ballots that it started with
+        let read = cached.with_tally(|tally| {
+            cached.update(|tally| tally.update_ballot(1, &[vec![1]]));
+            tally.ballots().len()
+        });
+        assert_eq!(read.ok(), Some(0));
+        assert_eq!(
+            cached.with_tally(|tally| tally.ballots().len()).ok(),
+            Some(1)
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    hash::Hash,
+};
+
+use crate::voting::{
+    shares,
+    tie_break::{TieBreak, TieBreaker},
+    transfers, BALLOT_VALUE,
+};
+
+/// Value that moved from an elected or eliminated item to the next preference of its ballots.
+pub struct SingleTransferableVoteTransfer<'a, T> {
+    /// The item that the ballots were counted for
+    pub from: &'a T,
+
+    /// The next remaining preference on the ballots, `None` if the ballots are exhausted
+    pub to: Option<&'a T>,
+
+    /// Value that was transferred, in fractions of `BALLOT_VALUE`
+    pub value: u64,
+}
+
+/// A single counting round of a poll with several seats
+pub struct SingleTransferableVoteRound<'a, T> {
+    /// Value of the ballots held by each remaining item, sorted by descending value
+    pub counts: Vec<(&'a T, u64)>,
+
+    /// Items that were elected at the end of the round
+    pub elected: Vec<&'a T>,
+
+    /// Items that were eliminated at the end of the round
+    pub eliminated: Vec<&'a T>,
+
+    /// The rule that decided which item to eliminate when several items had the lowest value
+    pub tie_break: Option<TieBreak>,
+
+    /// Where the surpluses of the elected items and the ballots of the eliminated items went to
+    pub transfers: Vec<SingleTransferableVoteTransfer<'a, T>>,
+
+    /// Value of the ballots without any remaining preference during the round
+    pub exhausted: u64,
+}
+
+/// The full record of a poll with several seats, containing every counting round and the
+/// elected items
+pub struct SingleTransferableVoteTrace<'a, T> {
+    pub seats: usize,
+
+    /// Value needed for being elected, in fractions of `BALLOT_VALUE`
+    pub quota: u64,
+
+    pub rounds: Vec<SingleTransferableVoteRound<'a, T>>,
+
+    /// Elected items in the order they were elected
+    pub elected: Vec<&'a T>,
+
+    /// Items that are tied for the seats that could not be filled
+    pub tied: Vec<&'a T>,
+}
+
+/// The Droop quota, the smallest number of whole ballots that only `seats` items can reach.
+pub fn droop_quota(ballots: usize, seats: usize) -> u64 {
+    (ballots / (seats + 1) + 1) as u64 * BALLOT_VALUE
+}
+
+/// Elect up to `seats` items using the single transferable vote with the Droop quota.
+///
+/// The surplus of an elected item is transferred using the weighted inclusive Gregory method:
+/// every ballot held by the item moves on to its next preference, keeping the fraction of its
+/// value that was not needed for reaching the quota. When no item reaches the quota, the item
+/// with the lowest value is eliminated and its ballots move on at their current value. Ballots
+/// ranking several remaining items first are split between them, and ties are handled as in
+/// instant-runoff voting.
+pub fn single_transferable_vote_trace<'a, T>(
+    ballots: &'a [&'a [Vec<T>]],
+    seats: usize,
+    tie_breaker: &mut TieBreaker,
+) -> SingleTransferableVoteTrace<'a, T>
+where
+    T: 'a + Ord + Hash,
+{
+    let candidates: BTreeSet<&T> = ballots
+        .iter()
+        .flat_map(|vote| vote.iter().flatten())
+        .collect();
+    let quota = droop_quota(
+        ballots.iter().filter(|vote| !vote.is_empty()).count(),
+        seats,
+    );
+    let mut values = vec![BALLOT_VALUE; ballots.len()];
+    let mut rounds = Vec::new();
+    let mut history = Vec::new();
+    let mut elected = Vec::new();
+    let mut tied = Vec::new();
+    // Elected and eliminated items, which are skipped when looking for the next preference
+    let mut removed: HashSet<&T> = HashSet::new();
+
+    while elected.len() < seats {
+        // Count ballots, items without any ballot are still counted
+        let mut exhausted = 0;
+        let mut ballots_value: HashMap<&T, u64> = candidates
+            .iter()
+            .filter(|item| !removed.contains(*item))
+            .map(|&item| (item, 0))
+            .collect();
+        for (&vote, &value) in ballots.iter().zip(values.iter()) {
+            let shares = shares(vote, &removed, value);
+            if shares.is_empty() {
+                exhausted += value;
+            }
+            for (opt, share) in shares {
+                *ballots_value.entry(opt).or_insert(0) += share;
+            }
+        }
+        let mut counts: Vec<_> = ballots_value.into_iter().collect();
+        counts.sort_by(|(a, x), (b, y)| y.cmp(x).then(a.cmp(b)));
+        history.push(counts.clone());
+        let mut round = SingleTransferableVoteRound {
+            counts,
+            elected: Vec::new(),
+            eliminated: Vec::new(),
+            tie_break: None,
+            transfers: Vec::new(),
+            exhausted,
+        };
+
+        // Every remaining item gets a seat when there are not more of them than seats
+        let open_seats = seats - elected.len();
+        if round.counts.len() <= open_seats {
+            round.elected = round.counts.iter().map(|&(item, _)| item).collect();
+            elected.extend(round.elected.iter().copied());
+            rounds.push(round);
+            break;
+        }
+
+        let reached: Vec<_> = round
+            .counts
+            .iter()
+            .filter(|&&(_, v)| v >= quota)
+            .copied()
+            .take(open_seats)
+            .collect();
+        let current_shares: Vec<_> = ballots
+            .iter()
+            .zip(values.iter())
+            .map(|(&vote, &value)| shares(vote, &removed, value))
+            .collect();
+        if reached.is_empty() {
+            let min_count = round.counts.last().map(|&(_, v)| v).unwrap_or_default();
+            let worst_items: Vec<_> = round
+                .counts
+                .iter()
+                .filter(|&&(_, v)| v == min_count)
+                .map(|&(k, _)| k)
+                .collect();
+            round.eliminated = match tie_breaker.weakest(&worst_items, &history, ballots) {
+                Some((opt, rule)) => {
+                    if worst_items.len() > 1 {
+                        round.tie_break = Some(rule);
+                    }
+                    vec![opt]
+                }
+                // The remaining seats are tied when eliminating every worst item leaves too few
+                None if round.counts.len() - worst_items.len() < open_seats => {
+                    round.elected = round
+                        .counts
+                        .iter()
+                        .map(|&(item, _)| item)
+                        .filter(|item| !worst_items.contains(item))
+                        .collect();
+                    elected.extend(round.elected.iter().copied());
+                    tied = worst_items;
+                    rounds.push(round);
+                    break;
+                }
+                None => worst_items,
+            };
+            removed.extend(round.eliminated.iter().copied());
+        } else {
+            // The ballots of an elected item keep the fraction of their share above the quota
+            round.elected = reached.iter().map(|&(item, _)| item).collect();
+            removed.extend(round.elected.iter().copied());
+            elected.extend(round.elected.iter().copied());
+            for (value, current) in values.iter_mut().zip(current_shares.iter()) {
+                for &(item, share) in current {
+                    if let Some(&(_, total)) = reached.iter().find(|(other, _)| *other == item) {
+                        let kept = share as u128 * (total - quota) as u128 / total as u128;
+                        *value -= share - kept as u64;
+                    }
+                }
+            }
+        }
+
+        // Record where the ballots of the removed items go to
+        for ((&vote, &value), before) in ballots.iter().zip(values.iter()).zip(current_shares) {
+            let after = shares(vote, &removed, value);
+            for (from, to, value) in transfers(&before, &after, &removed, value) {
+                match round
+                    .transfers
+                    .iter_mut()
+                    .find(|t| t.from == from && t.to == to)
+                {
+                    Some(transfer) => transfer.value += value,
+                    None => {
+                        round
+                            .transfers
+                            .push(SingleTransferableVoteTransfer { from, to, value })
+                    }
+                }
+            }
+        }
+        rounds.push(round);
+    }
+    SingleTransferableVoteTrace {
+        seats,
+        quota,
+        rounds,
+        elected,
+        tied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::strict;
+
+    #[test]
+    fn stv_droop_quota() {
+        assert_eq!(droop_quota(100, 1), 51 * BALLOT_VALUE);
+        assert_eq!(droop_quota(100, 3), 26 * BALLOT_VALUE);
+        assert_eq!(droop_quota(0, 2), BALLOT_VALUE);
+    }
+
+    #[test]
+    fn stv_transfers_surplus() {
+        let vote_a = strict(&["bob", "sue"]);
+        let vote_b = strict(&["bob", "bill"]);
+        let vote_c = strict(&["bill"]);
+        let vote_d = strict(&["ann"]);
+
+        // 4 ballots for bob, 1 for bill and 2 for ann
+        let mut votes = vec![vote_a.as_slice(); 2];
+        votes.extend([vote_b.as_slice(); 2]);
+        votes.push(vote_c.as_slice());
+        votes.extend([vote_d.as_slice(); 2]);
+        let trace = single_transferable_vote_trace(&votes, 2, &mut TieBreaker::default());
+        assert_eq!(trace.quota, 3 * BALLOT_VALUE);
+
+        // bob has one ballot above the quota, a quarter of each of his ballots moves on
+        let first = &trace.rounds[0];
+        assert_eq!(first.elected, vec![&"bob"]);
+        assert_eq!(first.transfers.len(), 2);
+        assert!(first
+            .transfers
+            .iter()
+            .any(|t| t.from == &"bob" && t.to == Some(&"sue") && t.value == BALLOT_VALUE / 2));
+
+        // sue is eliminated with half a ballot, then ann beats bill with 2 ballots against 1.5
+        let second = &trace.rounds[1];
+        assert_eq!(
+            second.counts,
+            vec![
+                (&"ann", 2 * BALLOT_VALUE),
+                (&"bill", 3 * BALLOT_VALUE / 2),
+                (&"sue", BALLOT_VALUE / 2)
+            ]
+        );
+        assert_eq!(second.eliminated, vec![&"sue"]);
+        assert_eq!(trace.elected, vec![&"bob", &"ann"]);
+        assert!(trace.tied.is_empty());
+    }
+
+    #[test]
+    fn stv_fills_every_seat() {
+        let vote_a = strict(&["bob", "sue", "bill"]);
+        let vote_b = strict(&["sue", "bob"]);
+
+        let votes = vec![vote_a.as_slice(), vote_a.as_slice(), vote_b.as_slice()];
+        let trace = single_transferable_vote_trace(&votes, 3, &mut TieBreaker::default());
+        assert_eq!(trace.elected.len(), 3);
+        assert_eq!(trace.elected[0], &"bob");
+    }
+
+    #[test]
+    fn stv_tied_seats() {
+        let vote_a = strict(&["bob"]);
+        let vote_b = strict(&["sue"]);
+        let vote_c = strict(&["bill"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice(), vote_c.as_slice()];
+        let trace = single_transferable_vote_trace(&votes, 2, &mut TieBreaker::default());
+        assert!(trace.elected.is_empty());
+        assert_eq!(trace.tied.len(), 3);
+
+        let mut tie_breaker = TieBreaker::new(TieBreak::Random, 7);
+        let trace = single_transferable_vote_trace(&votes, 2, &mut tie_breaker);
+        assert_eq!(trace.elected.len(), 2);
+        assert_eq!(trace.rounds[0].tie_break, Some(TieBreak::Random));
+    }
+
+    #[test]
+    fn stv_no_vote() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        let trace = single_transferable_vote_trace(&votes, 2, &mut TieBreaker::default());
+        assert!(trace.elected.is_empty());
+        assert!(trace.tied.is_empty());
+    }
+}
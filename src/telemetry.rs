@@ -8,28 +8,47 @@ use tracing::Subscriber;
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_opentelemetry::OpenTelemetryLayer;
-use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, Registry};
+use tracing_subscriber::{layer::SubscriberExt, registry::LookupSpan, Layer, Registry};
 
-use crate::conf::{Configuration, TracingConfiguration};
+use crate::{
+    conf::{Configuration, TracingConfiguration},
+    metrics::QueryMetricsLayer,
+};
 
 /// Register a subscriber as global default to process span data.
 ///
+/// The log level only filters the logs and the exported traces, the spans of the repositories are
+/// always timed when metrics are enabled.
+///
 /// It should only be called once!
 pub fn setup_tracing(config: &Configuration) -> Result<(), anyhow::Error> {
     LogTracer::init().expect("Failed to set tracing logger");
-    tracing::subscriber::set_global_default(
-        Registry::default()
-            .with(config.tracing().env_filter())
-            .with(JsonStorageLayer)
-            .with(BunyanFormattingLayer::new(
-                config.tracing().service_name().to_string(),
-                std::io::stdout,
-            ))
-            .with(otel_jaeger_tracing_layer(config.tracing())?),
-    )?;
+    let output = JsonStorageLayer
+        .and_then(BunyanFormattingLayer::new(
+            config.tracing().service_name().to_string(),
+            std::io::stdout,
+        ))
+        .and_then(otel_jaeger_tracing_layer(config.tracing())?)
+        .with_filter(config.tracing().env_filter());
+    let query_metrics = config
+        .metrics()
+        .enabled()
+        .then(|| QueryMetricsLayer.with_filter(QueryMetricsLayer::filter()));
+    tracing::subscriber::set_global_default(Registry::default().with(output).with(query_metrics))?;
     Ok(())
 }
 
+/// Export the spans that are still buffered and stop the exporter of traces. It should be called
+/// once the application has stopped, spans that are created afterwards are dropped.
+pub async fn shutdown_tracing() {
+    // Dropping the provider waits for its batch of spans to be exported, which blocks the thread.
+    if let Err(e) =
+        tokio::task::spawn_blocking(opentelemetry::global::shutdown_tracer_provider).await
+    {
+        tracing::error!(error = %e, "Failed to shut down the tracer provider");
+    }
+}
+
 fn otel_jaeger_tracing_layer<S>(
     config: &TracingConfiguration,
 ) -> Result<Option<OpenTelemetryLayer<S, opentelemetry_sdk::trace::Tracer>>, TraceError>
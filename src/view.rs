@@ -1,21 +1,92 @@
 use actix_web_flash_messages::IncomingFlashMessages;
 use sailfish::TemplateOnce;
 use serde::Serialize;
+use time::{format_description::FormatItem, macros::format_description, OffsetDateTime, UtcOffset};
 use uuid::Uuid;
 
-use crate::model::Item;
+use crate::{
+    model::{
+        BallotType, ElectionTrace, Item, PairwiseComparison, Poll, PollStatus, RegistrationMode,
+        ScoreTally, Standing, TallyResult, TallyTrace, VoterRoll,
+    },
+    voting::Method,
+};
+
+/// Format of the value of a `datetime-local` input, the time is in UTC unless the browser sends
+/// its offset.
+pub const DATETIME_LOCAL_FORMAT: &[FormatItem<'static>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]");
+
+/// Format a time as the value of a `datetime-local` input, nothing is shown for no time.
+fn datetime_local(time: &Option<OffsetDateTime>) -> String {
+    time.and_then(|t| {
+        t.to_offset(UtcOffset::UTC)
+            .format(DATETIME_LOCAL_FORMAT)
+            .ok()
+    })
+    .unwrap_or_default()
+}
+
+/// Format a value of ballots, which can be a fraction in a poll with several seats.
+pub(crate) fn display_votes(votes: f64) -> String {
+    let votes = format!("{votes:.3}");
+    votes
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+/// Format a time for showing it to the voters.
+fn display_time(time: &OffsetDateTime) -> String {
+    let format = format_description!("[year]-[month]-[day] [hour]:[minute] UTC");
+    time.format(format).unwrap_or_default()
+}
 
 #[derive(Serialize, TemplateOnce)]
 #[template(path = "index.stpl")]
 pub struct IndexView<'a> {
-    best_item_view: BestItemView<'a>,
+    polls: &'a [Poll],
     flash_messages_view: FlashMessagesView<'a>,
 }
 
 impl<'a> IndexView<'a> {
-    pub fn new(best_item: &'a Option<Item>, flashes: &'a IncomingFlashMessages) -> Self {
+    pub fn new(polls: &'a [Poll], flashes: &'a IncomingFlashMessages) -> Self {
         IndexView {
-            best_item_view: BestItemView::new(best_item),
+            polls,
+            flash_messages_view: FlashMessagesView::new(flashes),
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "poll.stpl")]
+pub struct PollView<'a> {
+    poll: &'a Poll,
+    status: PollStatus,
+    registration_mode: RegistrationMode,
+    uuid: Option<&'a str>,
+    has_ballot: bool,
+    best_item_view: BestItemView<'a>,
+    flash_messages_view: FlashMessagesView<'a>,
+}
+
+impl<'a> PollView<'a> {
+    pub fn new(
+        poll: &'a Poll,
+        uuid: Option<&'a str>,
+        has_ballot: bool,
+        best_items: &'a [Item],
+        voting_method: Method,
+        registration_mode: RegistrationMode,
+        flashes: &'a IncomingFlashMessages,
+    ) -> Self {
+        PollView {
+            poll,
+            status: poll.current_status(),
+            registration_mode,
+            uuid,
+            has_ballot,
+            best_item_view: BestItemView::new(poll, best_items, voting_method),
             flash_messages_view: FlashMessagesView::new(flashes),
         }
     }
@@ -24,27 +95,224 @@ impl<'a> IndexView<'a> {
 #[derive(Serialize, TemplateOnce)]
 #[template(path = "ballot.stpl")]
 pub struct BallotView<'a> {
+    poll: &'a Poll,
+    status: PollStatus,
     uuid: &'a Uuid,
     best_item_view: BestItemView<'a>,
     flash_messages_view: FlashMessagesView<'a>,
-    ranked_items: &'a [Item],
+    /// The ranks of the ranked items, from the most preferred.
+    ranked_items: &'a [Vec<Item>],
     unranked_items: &'a [Item],
+    /// Every item with the score that the ballot gives it, only used when ballots are scored.
+    scored_items: Vec<(&'a Item, u32)>,
 }
 
 impl<'a> BallotView<'a> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        poll: &'a Poll,
         uuid: &'a Uuid,
-        best_item: &'a Option<Item>,
+        best_items: &'a [Item],
+        voting_method: Method,
         flashes: &'a IncomingFlashMessages,
-        ranked_items: &'a [Item],
+        ranked_items: &'a [Vec<Item>],
         unranked_items: &'a [Item],
+        scores: &'a [(i32, u32)],
     ) -> Self {
+        let scored_items = ranked_items
+            .iter()
+            .flatten()
+            .chain(unranked_items)
+            .map(|item| {
+                let score = scores
+                    .iter()
+                    .find(|(id, _)| *id == item.id)
+                    .map_or(0, |&(_, score)| score);
+                (item, score)
+            })
+            .collect();
         Self {
+            poll,
+            status: poll.current_status(),
             uuid,
-            best_item_view: BestItemView::new(best_item),
+            best_item_view: BestItemView::new(poll, best_items, voting_method),
             flash_messages_view: FlashMessagesView::new(flashes),
             ranked_items,
             unranked_items,
+            scored_items,
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "results.stpl")]
+pub struct ResultsView<'a> {
+    poll: &'a Poll,
+    status: PollStatus,
+    trace: &'a TallyTrace,
+    election: Option<&'a ElectionTrace>,
+    pairwise: &'a PairwiseComparison,
+    /// The totals of a poll with scored ballots, which are shown instead of the rounds.
+    scores: Option<&'a ScoreTally>,
+    flash_messages_view: FlashMessagesView<'a>,
+}
+
+impl<'a> ResultsView<'a> {
+    pub fn new(
+        poll: &'a Poll,
+        trace: &'a TallyTrace,
+        election: Option<&'a ElectionTrace>,
+        pairwise: &'a PairwiseComparison,
+        scores: Option<&'a ScoreTally>,
+        flashes: &'a IncomingFlashMessages,
+    ) -> Self {
+        Self {
+            poll,
+            status: poll.current_status(),
+            trace,
+            election,
+            pairwise,
+            scores,
+            flash_messages_view: FlashMessagesView::new(flashes),
+        }
+    }
+
+    /// Style for a cell of the pairwise matrix depending on whether the row beats the column.
+    pub fn pairwise_class(pairwise: &PairwiseComparison, i: usize, j: usize) -> &'static str {
+        let (wins, losses) = (pairwise.preferences[i][j], pairwise.preferences[j][i]);
+        if i == j {
+            ""
+        } else if wins > losses {
+            "has-text-success"
+        } else if wins < losses {
+            "has-text-danger"
+        } else {
+            "has-text-grey"
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "leaderboard.stpl")]
+pub struct LeaderboardView<'a> {
+    poll: &'a Poll,
+    standings: &'a [Standing],
+    voting_method: Method,
+    flash_messages_view: FlashMessagesView<'a>,
+}
+
+impl<'a> LeaderboardView<'a> {
+    pub fn new(
+        poll: &'a Poll,
+        standings: &'a [Standing],
+        voting_method: Method,
+        flashes: &'a IncomingFlashMessages,
+    ) -> Self {
+        Self {
+            poll,
+            standings,
+            voting_method,
+            flash_messages_view: FlashMessagesView::new(flashes),
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "admin_login.stpl")]
+pub struct AdminLoginView<'a> {
+    flash_messages_view: FlashMessagesView<'a>,
+}
+
+impl<'a> AdminLoginView<'a> {
+    pub fn new(flashes: &'a IncomingFlashMessages) -> Self {
+        Self {
+            flash_messages_view: FlashMessagesView::new(flashes),
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "admin_index.stpl")]
+pub struct AdminIndexView<'a> {
+    polls: &'a [Poll],
+    flash_messages_view: FlashMessagesView<'a>,
+}
+
+impl<'a> AdminIndexView<'a> {
+    pub fn new(polls: &'a [Poll], flashes: &'a IncomingFlashMessages) -> Self {
+        Self {
+            polls,
+            flash_messages_view: FlashMessagesView::new(flashes),
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "admin_items.stpl")]
+pub struct AdminItemsView<'a> {
+    poll: &'a Poll,
+    status: PollStatus,
+    items: &'a [Item],
+    flash_messages_view: FlashMessagesView<'a>,
+}
+
+impl<'a> AdminItemsView<'a> {
+    pub fn new(poll: &'a Poll, items: &'a [Item], flashes: &'a IncomingFlashMessages) -> Self {
+        Self {
+            poll,
+            status: poll.current_status(),
+            items,
+            flash_messages_view: FlashMessagesView::new(flashes),
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "admin_tokens.stpl")]
+pub struct AdminTokensView<'a> {
+    poll: &'a Poll,
+    registration_mode: RegistrationMode,
+    roll: VoterRoll,
+    /// Tokens that were just issued, which are only shown once.
+    tokens: &'a [String],
+    flash_messages_view: FlashMessagesView<'a>,
+}
+
+impl<'a> AdminTokensView<'a> {
+    pub fn new(
+        poll: &'a Poll,
+        registration_mode: RegistrationMode,
+        roll: VoterRoll,
+        tokens: &'a [String],
+        flashes: &'a IncomingFlashMessages,
+    ) -> Self {
+        Self {
+            poll,
+            registration_mode,
+            roll,
+            tokens,
+            flash_messages_view: FlashMessagesView::new(flashes),
+        }
+    }
+}
+
+#[derive(Serialize, TemplateOnce)]
+#[template(path = "error.stpl")]
+pub struct ErrorView<'a> {
+    status: u16,
+    reason: &'a str,
+    message: &'a str,
+    /// The id of the request, for finding its logs.
+    request_id: Option<String>,
+}
+
+impl<'a> ErrorView<'a> {
+    pub fn new(status: u16, reason: &'a str, message: &'a str, request_id: Option<String>) -> Self {
+        Self {
+            status,
+            reason,
+            message,
+            request_id,
         }
     }
 }
@@ -52,12 +320,20 @@ impl<'a> BallotView<'a> {
 #[derive(Serialize, TemplateOnce)]
 #[template(path = "best_item.stpl")]
 pub struct BestItemView<'a> {
-    best_item: &'a Option<Item>,
+    poll_id: i32,
+    seats: usize,
+    best_items: &'a [Item],
+    method_name: &'static str,
 }
 
 impl<'a> BestItemView<'a> {
-    pub fn new(best_item: &'a Option<Item>) -> Self {
-        Self { best_item }
+    pub fn new(poll: &Poll, best_items: &'a [Item], voting_method: Method) -> Self {
+        Self {
+            poll_id: poll.id,
+            seats: poll.elected_seats(),
+            best_items,
+            method_name: poll.method(voting_method).0,
+        }
     }
 }
 
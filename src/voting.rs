@@ -0,0 +1,332 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    hash::Hash,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::irv::{instant_runoff_vote, InstantRunoffVotingResult};
+
+use self::tie_break::TieBreaker;
+
+pub mod borda;
+pub mod ranked_pairs;
+pub mod schulze;
+pub mod tie_break;
+
+/// Value of a full ballot. Votes are counted in fractions of a ballot so that ballots can be split
+/// between items that share a rank, and surpluses can be transferred, without rounding errors
+/// depending on the platform. The value splits evenly between up to 16 items.
+pub const BALLOT_VALUE: u64 = 720_720;
+
+/// Result of tallying a poll with any voting method
+pub enum VotingResult<'a, T> {
+    /// Could not finish the poll
+    NoWinner,
+
+    /// The poll results in multiple winners
+    Tied(Vec<&'a T>),
+
+    /// The poll results in one winner
+    Winner(&'a T),
+}
+
+impl<'a, T> From<InstantRunoffVotingResult<'a, T>> for VotingResult<'a, T> {
+    fn from(result: InstantRunoffVotingResult<'a, T>) -> Self {
+        match result {
+            InstantRunoffVotingResult::NoWinner => VotingResult::NoWinner,
+            InstantRunoffVotingResult::Tied(items) => VotingResult::Tied(items),
+            InstantRunoffVotingResult::Winner(item) => VotingResult::Winner(item),
+        }
+    }
+}
+
+impl<'a, T> VotingResult<'a, T>
+where
+    T: Ord + Hash,
+{
+    /// Turn a set of best items into a result. Ties among the best items are broken by comparing
+    /// their first preferences when the tie breaker requires it.
+    fn from_best(
+        mut best_items: Vec<&'a T>,
+        ballots: &'a [&'a [Vec<T>]],
+        tie_breaker: &mut TieBreaker,
+    ) -> Self {
+        best_items.sort();
+        match best_items.len() {
+            0 => VotingResult::NoWinner,
+            1 => VotingResult::Winner(best_items.remove(0)),
+            _ => {
+                let history = [first_preferences(ballots)];
+                match tie_breaker.strongest(&best_items, &history, ballots) {
+                    Some((item, _)) => VotingResult::Winner(item),
+                    None => VotingResult::Tied(best_items),
+                }
+            }
+        }
+    }
+}
+
+/// Count the value of the ballots ranking each item first, a ballot ranking several items first
+/// is split between them.
+fn first_preferences<'a, T>(ballots: &'a [&'a [Vec<T>]]) -> Vec<(&'a T, u64)>
+where
+    T: Ord + Hash,
+{
+    let mut counts: HashMap<&T, u64> = HashMap::new();
+    for &ballot in ballots {
+        for (item, value) in shares(ballot, &HashSet::new(), BALLOT_VALUE) {
+            *counts.entry(item).or_insert(0) += value;
+        }
+    }
+    counts.into_iter().collect()
+}
+
+/// Split the value of a ballot between its most preferred items that are not removed. Items that
+/// share a rank get equal parts, the remainder of an uneven split goes to the first of them in
+/// order. The list is empty when the ballot is exhausted.
+pub(crate) fn shares<'a, T>(
+    ballot: &'a [Vec<T>],
+    removed: &HashSet<&T>,
+    value: u64,
+) -> Vec<(&'a T, u64)>
+where
+    T: Ord + Hash,
+{
+    let mut items: Vec<&T> = ballot
+        .iter()
+        .map(|rank| {
+            rank.iter()
+                .filter(|item| !removed.contains(item))
+                .collect::<Vec<_>>()
+        })
+        .find(|rank| !rank.is_empty())
+        .unwrap_or_default();
+    items.sort();
+    items.dedup();
+    let n = items.len() as u64;
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| (item, value / n + u64::from((i as u64) < value % n)))
+        .collect()
+}
+
+/// Work out where the value that a ballot gave to removed items went, from the shares of the
+/// ballot before and after the items were removed. Each removed item passes its share on in
+/// proportion to what the remaining items gained, or to `None` when the ballot is exhausted and
+/// its remaining value is lost.
+pub(crate) fn transfers<'a, T>(
+    before: &[(&'a T, u64)],
+    after: &[(&'a T, u64)],
+    removed: &HashSet<&T>,
+    value_after: u64,
+) -> Vec<(&'a T, Option<&'a T>, u64)>
+where
+    T: Eq + Hash,
+{
+    let from: Vec<_> = before
+        .iter()
+        .filter(|(item, _)| removed.contains(*item))
+        .copied()
+        .collect();
+    let total: u64 = from.iter().map(|(_, share)| share).sum();
+    if total == 0 {
+        return Vec::new();
+    }
+    let gains: Vec<(Option<&T>, u64)> = if after.is_empty() {
+        vec![(None, value_after)]
+    } else {
+        after
+            .iter()
+            .map(|&(item, share)| {
+                let previous = before
+                    .iter()
+                    .find(|(other, _)| *other == item)
+                    .map_or(0, |&(_, share)| share);
+                (Some(item), share.saturating_sub(previous))
+            })
+            .collect()
+    };
+    from.iter()
+        .flat_map(|&(from, share)| {
+            gains
+                .iter()
+                .filter(|(_, gain)| *gain > 0)
+                .map(move |&(to, gain)| {
+                    let value = share as u128 * gain as u128 / total as u128;
+                    (from, to, value as u64)
+                })
+        })
+        .collect()
+}
+
+/// A method for determining the best item from a set of ranked ballots. Each ballot lists its
+/// ranks in the order of preference, and each rank holds the items that are equally preferred.
+/// Ballots can be truncated: items that are not listed are ranked below every listed item and
+/// are equally preferred among themselves.
+pub trait VotingMethod {
+    /// Name of the voting method that is shown to the users.
+    fn name(&self) -> &'static str;
+
+    /// Determine the best item(s) from the given ballots, using the tie breaker to choose between
+    /// items that can not be separated.
+    fn tally<'a, T>(
+        &self,
+        ballots: &'a [&'a [Vec<T>]],
+        tie_breaker: &mut TieBreaker,
+    ) -> VotingResult<'a, T>
+    where
+        T: 'a + Ord + Hash;
+}
+
+/// The instant-runoff voting system.
+pub struct InstantRunoff;
+
+impl VotingMethod for InstantRunoff {
+    fn name(&self) -> &'static str {
+        "Instant-runoff voting"
+    }
+
+    fn tally<'a, T>(
+        &self,
+        ballots: &'a [&'a [Vec<T>]],
+        tie_breaker: &mut TieBreaker,
+    ) -> VotingResult<'a, T>
+    where
+        T: 'a + Ord + Hash,
+    {
+        instant_runoff_vote(ballots, tie_breaker).into()
+    }
+}
+
+/// Order every item that appears on the ballots by tallying the poll again and again, each time
+/// without the items that won before. Each place holds the winner of a tally, or every item that
+/// was tied in it.
+pub fn complete_ranking<M, T>(
+    method: &M,
+    ballots: &[&[Vec<T>]],
+    tie_breaker: &mut TieBreaker,
+) -> Vec<Vec<T>>
+where
+    M: VotingMethod,
+    T: Copy + Ord + Hash,
+{
+    let mut remaining: Vec<Vec<Vec<T>>> = ballots.iter().map(|ballot| ballot.to_vec()).collect();
+    let mut places = Vec::new();
+    loop {
+        remaining.retain(|ballot| !ballot.is_empty());
+        let views: Vec<&[Vec<T>]> = remaining.iter().map(Vec::as_slice).collect();
+        let place: Vec<T> = match method.tally(&views, tie_breaker) {
+            VotingResult::NoWinner => break,
+            VotingResult::Tied(items) => items.into_iter().copied().collect(),
+            VotingResult::Winner(item) => vec![*item],
+        };
+        for ballot in remaining.iter_mut() {
+            for rank in ballot.iter_mut() {
+                rank.retain(|item| !place.contains(item));
+            }
+            ballot.retain(|rank| !rank.is_empty());
+        }
+        places.push(place);
+    }
+    places
+}
+
+/// All supported voting methods that can be chosen through configuration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum Method {
+    #[default]
+    InstantRunoff,
+    Schulze,
+    RankedPairs,
+    Borda,
+}
+
+impl Method {
+    /// The name of the method in configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::InstantRunoff => "instant_runoff",
+            Method::Schulze => "schulze",
+            Method::RankedPairs => "ranked_pairs",
+            Method::Borda => "borda",
+        }
+    }
+}
+
+impl VotingMethod for Method {
+    fn name(&self) -> &'static str {
+        match self {
+            Method::InstantRunoff => InstantRunoff.name(),
+            Method::Schulze => schulze::Schulze.name(),
+            Method::RankedPairs => ranked_pairs::RankedPairs.name(),
+            Method::Borda => borda::Borda.name(),
+        }
+    }
+
+    fn tally<'a, T>(
+        &self,
+        ballots: &'a [&'a [Vec<T>]],
+        tie_breaker: &mut TieBreaker,
+    ) -> VotingResult<'a, T>
+    where
+        T: 'a + Ord + Hash,
+    {
+        match self {
+            Method::InstantRunoff => InstantRunoff.tally(ballots, tie_breaker),
+            Method::Schulze => schulze::Schulze.tally(ballots, tie_breaker),
+            Method::RankedPairs => ranked_pairs::RankedPairs.tally(ballots, tie_breaker),
+            Method::Borda => borda::Borda.tally(ballots, tie_breaker),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A ballot ranking every item on its own rank, in the given order.
+#[cfg(test)]
+pub(crate) fn strict<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    items.iter().map(|item| vec![item.clone()]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn complete_ranking_orders_every_item() {
+        let vote_a = strict(&["bob", "sue", "bill"]);
+        let vote_b = strict(&["sue", "bob", "bill"]);
+        let vote_c = strict(&["bob", "bill"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice(), vote_c.as_slice()];
+        for method in [
+            Method::InstantRunoff,
+            Method::Schulze,
+            Method::RankedPairs,
+            Method::Borda,
+        ] {
+            let places = complete_ranking(&method, &votes, &mut TieBreaker::default());
+            assert_eq!(places, vec![vec!["bob"], vec!["sue"], vec!["bill"]]);
+        }
+    }
+
+    #[test]
+    fn complete_ranking_keeps_ties_together() {
+        let vote_a = strict(&["bob", "sue"]);
+        let vote_b = strict(&["bob", "bill"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        let places = complete_ranking(&Method::InstantRunoff, &votes, &mut TieBreaker::default());
+        assert_eq!(places, vec![vec!["bob"], vec!["bill", "sue"]]);
+    }
+}
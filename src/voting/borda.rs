@@ -0,0 +1,127 @@
+use std::hash::Hash;
+
+use crate::pairwise::Candidates;
+
+use super::{tie_break::TieBreaker, VotingMethod, VotingResult};
+
+/// The Borda count, where an item ranked at position `k` on a ballot with `n` candidates gets
+/// `n - 1 - k` points. More generally, an item gets a point for every candidate that the ballot
+/// ranks below it, so items sharing a rank do not get points from each other and unranked items
+/// get no point.
+pub struct Borda;
+
+impl VotingMethod for Borda {
+    fn name(&self) -> &'static str {
+        "Borda count"
+    }
+
+    fn tally<'a, T>(
+        &self,
+        ballots: &'a [&'a [Vec<T>]],
+        tie_breaker: &mut TieBreaker,
+    ) -> VotingResult<'a, T>
+    where
+        T: 'a + Ord + Hash,
+    {
+        let candidates = Candidates::new(ballots);
+        let scores = scores(&candidates, ballots);
+
+        let max_score = scores.iter().copied().max().unwrap_or_default();
+        let best_items = scores
+            .iter()
+            .enumerate()
+            .filter(|(_, &score)| score == max_score)
+            .map(|(i, _)| candidates.get(i))
+            .collect();
+        VotingResult::from_best(best_items, ballots, tie_breaker)
+    }
+}
+
+/// Compute the Borda score of every candidate.
+pub(crate) fn scores<T>(candidates: &Candidates<'_, T>, ballots: &[&[Vec<T>]]) -> Vec<u64>
+where
+    T: Eq + Hash,
+{
+    let n = candidates.len();
+    let mut scores = vec![0; n];
+    for &ballot in ballots {
+        // Number of items ranked on or above the current rank
+        let mut ranked = 0;
+        for rank in ballot {
+            ranked += rank.len();
+            for item in rank {
+                scores[candidates.index_of(item)] += n.saturating_sub(ranked) as u64;
+            }
+        }
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::strict;
+
+    #[test]
+    fn borda_basic_poll() {
+        let vote_a = strict(&["bob", "bill", "sue"]);
+        let vote_b = strict(&["sue", "bill", "bob"]);
+        let vote_c = strict(&["bill", "sue", "bob"]);
+
+        // bob = 2, bill = 4, sue = 3
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice(), vote_c.as_slice()];
+        match Borda.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(winner) => assert_eq!(winner, &"bill"),
+        };
+    }
+
+    #[test]
+    fn borda_tied() {
+        let vote_a = strict(&["bob", "sue"]);
+        let vote_b = strict(&["sue", "bob"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        match Borda.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Winner(_) => unreachable!(),
+            VotingResult::Tied(options) => {
+                assert!(options.contains(&&"sue"));
+                assert!(options.contains(&&"bob"));
+            }
+        };
+    }
+
+    #[test]
+    fn borda_equal_ranks_share_points() {
+        // bob = 1 + 2, sue = 1 + 0, bill = 0 + 0
+        let vote_a = vec![vec!["bob", "sue"], vec!["bill"]];
+        let vote_b = vec![vec!["bob"], vec!["sue", "bill"]];
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        match Borda.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(winner) => assert_eq!(winner, &"bob"),
+        };
+
+        let vote_c = vec![vec!["bob", "sue"]];
+        let votes = vec![vote_c.as_slice()];
+        match Borda.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Winner(_) => unreachable!(),
+            VotingResult::Tied(options) => assert_eq!(options.len(), 2),
+        };
+    }
+
+    #[test]
+    fn borda_no_vote() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        match Borda.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(_) => unreachable!(),
+            VotingResult::NoWinner => {}
+        };
+    }
+}
@@ -0,0 +1,138 @@
+use std::hash::Hash;
+
+use crate::pairwise::PairwiseMatrix;
+
+use super::{tie_break::TieBreaker, VotingMethod, VotingResult};
+
+/// The ranked pairs method (Tideman), which locks in pairwise victories from the strongest to the
+/// weakest while skipping those that would create a cycle.
+pub struct RankedPairs;
+
+impl VotingMethod for RankedPairs {
+    fn name(&self) -> &'static str {
+        "Ranked pairs"
+    }
+
+    fn tally<'a, T>(
+        &self,
+        ballots: &'a [&'a [Vec<T>]],
+        tie_breaker: &mut TieBreaker,
+    ) -> VotingResult<'a, T>
+    where
+        T: 'a + Ord + Hash,
+    {
+        let matrix = PairwiseMatrix::new(ballots);
+        let candidates = matrix.candidates();
+        let locked = lock_pairs(matrix.preferences());
+
+        let n = candidates.len();
+        let best_items = (0..n)
+            .filter(|&j| (0..n).all(|i| !locked[i][j]))
+            .map(|i| candidates.get(i))
+            .collect();
+        VotingResult::from_best(best_items, ballots, tie_breaker)
+    }
+}
+
+/// Lock the pairwise victories into a graph, going from the largest winning votes to the
+/// smallest. Victories with the same strength are ordered by the smaller opposition first.
+fn lock_pairs(preferences: &[Vec<u32>]) -> Vec<Vec<bool>> {
+    let n = preferences.len();
+    let mut pairs: Vec<_> = (0..n)
+        .flat_map(|i| (0..n).map(move |j| (i, j)))
+        .filter(|&(i, j)| preferences[i][j] > preferences[j][i])
+        .collect();
+    pairs.sort_by(|&(a, b), &(c, d)| {
+        preferences[c][d]
+            .cmp(&preferences[a][b])
+            .then(preferences[b][a].cmp(&preferences[d][c]))
+    });
+
+    let mut locked = vec![vec![false; n]; n];
+    for (winner, loser) in pairs {
+        if !reachable(&locked, loser, winner) {
+            locked[winner][loser] = true;
+        }
+    }
+    locked
+}
+
+/// Check if there is a path from `from` to `to` in the graph of locked pairs.
+fn reachable(locked: &[Vec<bool>], from: usize, to: usize) -> bool {
+    let mut visited = vec![false; locked.len()];
+    let mut stack = vec![from];
+    while let Some(node) = stack.pop() {
+        if node == to {
+            return true;
+        }
+        if visited[node] {
+            continue;
+        }
+        visited[node] = true;
+        for (next, &edge) in locked[node].iter().enumerate() {
+            if edge && !visited[next] {
+                stack.push(next);
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::strict;
+
+    #[test]
+    fn ranked_pairs_tennessee() {
+        // Example from https://en.wikipedia.org/wiki/Ranked_pairs
+        let mut votes: Vec<&[Vec<&str>]> = Vec::new();
+        let memphis = strict(&["memphis", "nashville", "chattanooga", "knoxville"]);
+        let nashville = strict(&["nashville", "chattanooga", "knoxville", "memphis"]);
+        let chattanooga = strict(&["chattanooga", "knoxville", "nashville", "memphis"]);
+        let knoxville = strict(&["knoxville", "chattanooga", "nashville", "memphis"]);
+        votes.extend(std::iter::repeat_n(&memphis[..], 42));
+        votes.extend(std::iter::repeat_n(&nashville[..], 26));
+        votes.extend(std::iter::repeat_n(&chattanooga[..], 15));
+        votes.extend(std::iter::repeat_n(&knoxville[..], 17));
+
+        match RankedPairs.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(winner) => assert_eq!(winner, &"nashville"),
+        };
+    }
+
+    #[test]
+    fn ranked_pairs_cycle() {
+        let vote_a = strict(&["a", "b", "c"]);
+        let vote_b = strict(&["b", "c", "a"]);
+        let vote_c = strict(&["c", "a", "b"]);
+
+        // a > b (5-2), b > c (5-2), c > a (4-3): the weakest victory is not locked
+        let votes = vec![
+            vote_a.as_slice(),
+            vote_a.as_slice(),
+            vote_a.as_slice(),
+            vote_b.as_slice(),
+            vote_b.as_slice(),
+            vote_c.as_slice(),
+            vote_c.as_slice(),
+        ];
+        match RankedPairs.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(winner) => assert_eq!(winner, &"a"),
+        };
+    }
+
+    #[test]
+    fn ranked_pairs_no_vote() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        match RankedPairs.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(_) => unreachable!(),
+            VotingResult::NoWinner => {}
+        };
+    }
+}
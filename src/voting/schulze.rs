@@ -0,0 +1,123 @@
+use std::hash::Hash;
+
+use crate::pairwise::PairwiseMatrix;
+
+use super::{tie_break::TieBreaker, VotingMethod, VotingResult};
+
+/// The Schulze method, which finds the items that are not beaten by any other item through the
+/// strongest path of pairwise defeats.
+pub struct Schulze;
+
+impl VotingMethod for Schulze {
+    fn name(&self) -> &'static str {
+        "Schulze method"
+    }
+
+    fn tally<'a, T>(
+        &self,
+        ballots: &'a [&'a [Vec<T>]],
+        tie_breaker: &mut TieBreaker,
+    ) -> VotingResult<'a, T>
+    where
+        T: 'a + Ord + Hash,
+    {
+        let matrix = PairwiseMatrix::new(ballots);
+        let candidates = matrix.candidates();
+        let strengths = strongest_paths(matrix.preferences());
+
+        let n = candidates.len();
+        let best_items = (0..n)
+            .filter(|&i| (0..n).all(|j| i == j || strengths[i][j] >= strengths[j][i]))
+            .map(|i| candidates.get(i))
+            .collect();
+        VotingResult::from_best(best_items, ballots, tie_breaker)
+    }
+}
+
+/// Compute the strength of the strongest path between every pair of candidates using the
+/// winning votes as the strength of a link.
+fn strongest_paths(preferences: &[Vec<u32>]) -> Vec<Vec<u32>> {
+    let n = preferences.len();
+    let mut strengths = vec![vec![0; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            if i != j && preferences[i][j] > preferences[j][i] {
+                strengths[i][j] = preferences[i][j];
+            }
+        }
+    }
+    for k in 0..n {
+        for i in 0..n {
+            if i == k {
+                continue;
+            }
+            for j in 0..n {
+                if j == i || j == k {
+                    continue;
+                }
+                let through_k = strengths[i][k].min(strengths[k][j]);
+                if through_k > strengths[i][j] {
+                    strengths[i][j] = through_k;
+                }
+            }
+        }
+    }
+    strengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::strict;
+
+    #[test]
+    fn schulze_condorcet_cycle() {
+        // Example from https://en.wikipedia.org/wiki/Schulze_method
+        let groups = [
+            (strict(&["a", "c", "b", "e", "d"]), 5),
+            (strict(&["a", "d", "e", "c", "b"]), 5),
+            (strict(&["b", "e", "d", "a", "c"]), 8),
+            (strict(&["c", "a", "b", "e", "d"]), 3),
+            (strict(&["c", "a", "e", "b", "d"]), 7),
+            (strict(&["c", "b", "a", "d", "e"]), 2),
+            (strict(&["d", "c", "e", "b", "a"]), 7),
+            (strict(&["e", "b", "a", "d", "c"]), 8),
+        ];
+        let votes: Vec<&[Vec<&str>]> = groups
+            .iter()
+            .flat_map(|(vote, count)| std::iter::repeat_n(vote.as_slice(), *count))
+            .collect();
+
+        match Schulze.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(winner) => assert_eq!(winner, &"e"),
+        };
+    }
+
+    #[test]
+    fn schulze_tied() {
+        let vote_a = strict(&["bob", "sue"]);
+        let vote_b = strict(&["sue", "bob"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice()];
+        match Schulze.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::NoWinner => unreachable!(),
+            VotingResult::Winner(_) => unreachable!(),
+            VotingResult::Tied(options) => {
+                assert!(options.contains(&&"sue"));
+                assert!(options.contains(&&"bob"));
+            }
+        };
+    }
+
+    #[test]
+    fn schulze_no_vote() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        match Schulze.tally(&votes, &mut TieBreaker::default()) {
+            VotingResult::Tied(_) => unreachable!(),
+            VotingResult::Winner(_) => unreachable!(),
+            VotingResult::NoWinner => {}
+        };
+    }
+}
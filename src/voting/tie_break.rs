@@ -0,0 +1,274 @@
+use std::{collections::HashMap, fmt, hash::Hash};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::pairwise::Candidates;
+
+use super::borda;
+
+/// Rules for choosing a single item among items that are tied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// Do not break ties, every tied item is eliminated at once and ties among the best items
+    /// result in no winner.
+    None,
+
+    /// Compare the counts of the tied items in the previous rounds, starting from the most recent
+    /// round.
+    #[default]
+    Backward,
+
+    /// Compare the counts of the tied items in the previous rounds, starting from the first round.
+    Forward,
+
+    /// Compare the Borda scores of the tied items.
+    Borda,
+
+    /// Draw one of the tied items using a seeded random number generator.
+    Random,
+}
+
+impl fmt::Display for TieBreak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TieBreak::None => "no tie-break",
+            TieBreak::Backward => "backward tie-break",
+            TieBreak::Forward => "forward tie-break",
+            TieBreak::Borda => "Borda score",
+            TieBreak::Random => "random draw",
+        })
+    }
+}
+
+/// Breaks ties using a rule and falls back to a seeded random draw when the rule can not
+/// distinguish between the tied items. The same rule, seed and ballots always give the same
+/// results.
+pub struct TieBreaker {
+    rule: TieBreak,
+    seed: u64,
+    state: u64,
+}
+
+impl Default for TieBreaker {
+    fn default() -> Self {
+        Self::new(TieBreak::None, 0)
+    }
+}
+
+impl TieBreaker {
+    pub fn new(rule: TieBreak, seed: u64) -> Self {
+        Self {
+            rule,
+            seed,
+            state: seed,
+        }
+    }
+
+    pub fn rule(&self) -> TieBreak {
+        self.rule
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Choose the weakest item among the tied items. Returns `None` if ties are not broken,
+    /// otherwise returns the chosen item along with the rule that decided it.
+    ///
+    /// `history` contains the counts of every item in each of the previous rounds.
+    pub fn weakest<'a, T, C>(
+        &mut self,
+        tied: &[&'a T],
+        history: &[Vec<(&'a T, C)>],
+        ballots: &'a [&'a [Vec<T>]],
+    ) -> Option<(&'a T, TieBreak)>
+    where
+        T: Ord + Hash,
+        C: Copy + Default + Ord,
+    {
+        self.choose(tied, history, ballots, Extreme::Lowest)
+    }
+
+    /// Choose the strongest item among the tied items. Returns `None` if ties are not broken,
+    /// otherwise returns the chosen item along with the rule that decided it.
+    ///
+    /// `history` contains the counts of every item in each of the previous rounds.
+    pub fn strongest<'a, T, C>(
+        &mut self,
+        tied: &[&'a T],
+        history: &[Vec<(&'a T, C)>],
+        ballots: &'a [&'a [Vec<T>]],
+    ) -> Option<(&'a T, TieBreak)>
+    where
+        T: Ord + Hash,
+        C: Copy + Default + Ord,
+    {
+        self.choose(tied, history, ballots, Extreme::Highest)
+    }
+
+    fn choose<'a, T, C>(
+        &mut self,
+        tied: &[&'a T],
+        history: &[Vec<(&'a T, C)>],
+        ballots: &'a [&'a [Vec<T>]],
+        extreme: Extreme,
+    ) -> Option<(&'a T, TieBreak)>
+    where
+        T: Ord + Hash,
+        C: Copy + Default + Ord,
+    {
+        // Sorting makes the result independent of the order the items were given in
+        let mut tied = tied.to_vec();
+        tied.sort();
+        tied.dedup();
+        match tied.len() {
+            0 => return None,
+            1 => return Some((tied[0], self.rule)),
+            _ => {}
+        }
+
+        match self.rule {
+            TieBreak::None => return None,
+            TieBreak::Backward => {
+                for counts in history.iter().rev() {
+                    tied = extreme.filter(tied, counts.iter().copied().collect());
+                    if tied.len() == 1 {
+                        return Some((tied[0], TieBreak::Backward));
+                    }
+                }
+            }
+            TieBreak::Forward => {
+                for counts in history.iter() {
+                    tied = extreme.filter(tied, counts.iter().copied().collect());
+                    if tied.len() == 1 {
+                        return Some((tied[0], TieBreak::Forward));
+                    }
+                }
+            }
+            TieBreak::Borda => {
+                // Items that are not on any ranked ballot, e.g. when scored ballots are counted,
+                // have no points and are left to the random draw.
+                let candidates = Candidates::new(ballots);
+                let scores = borda::scores(&candidates, ballots);
+                let scores = tied
+                    .iter()
+                    .filter_map(|&item| Some((item, scores[candidates.position(item)?])))
+                    .collect();
+                tied = extreme.filter(tied, scores);
+                if tied.len() == 1 {
+                    return Some((tied[0], TieBreak::Borda));
+                }
+            }
+            TieBreak::Random => {}
+        }
+
+        let index = (self.next_random() % tied.len() as u64) as usize;
+        Some((tied[index], TieBreak::Random))
+    }
+
+    /// Generate the next number using SplitMix64, which is stable across platforms and releases
+    /// so that stored seeds can always be replayed.
+    fn next_random(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Extreme {
+    Lowest,
+    Highest,
+}
+
+impl Extreme {
+    /// Keep only the items having the extreme score, items without a score are counted as zero.
+    fn filter<'a, T, S>(self, items: Vec<&'a T>, scores: HashMap<&'a T, S>) -> Vec<&'a T>
+    where
+        T: Eq + Hash,
+        S: Copy + Default + Ord,
+    {
+        let score = |item: &&'a T| scores.get(item).copied().unwrap_or_default();
+        let target = match self {
+            Extreme::Lowest => items.iter().map(score).min(),
+            Extreme::Highest => items.iter().map(score).max(),
+        };
+        items
+            .into_iter()
+            .filter(|item| Some(score(item)) == target)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voting::strict;
+
+    #[test]
+    fn tie_break_backward() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        let history = vec![
+            vec![(&"bob", 1), (&"sue", 2)],
+            vec![(&"bob", 3), (&"sue", 3)],
+        ];
+
+        let mut tie_breaker = TieBreaker::new(TieBreak::Backward, 0);
+        let chosen = tie_breaker.weakest(&[&"sue", &"bob"], &history, &votes);
+        assert_eq!(chosen, Some((&"bob", TieBreak::Backward)));
+    }
+
+    #[test]
+    fn tie_break_forward() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        let history = vec![
+            vec![(&"bob", 2), (&"sue", 2), (&"bill", 1)],
+            vec![(&"bob", 2), (&"sue", 3)],
+            vec![(&"bob", 4), (&"sue", 4)],
+        ];
+
+        let mut tie_breaker = TieBreaker::new(TieBreak::Forward, 0);
+        let chosen = tie_breaker.weakest(&[&"sue", &"bob"], &history, &votes);
+        assert_eq!(chosen, Some((&"bob", TieBreak::Forward)));
+    }
+
+    #[test]
+    fn tie_break_borda() {
+        let vote_a = strict(&["bob", "sue", "bill"]);
+        let vote_b = strict(&["sue", "bill", "bob"]);
+        let vote_c = strict(&["bill", "sue", "bob"]);
+
+        let votes = vec![vote_a.as_slice(), vote_b.as_slice(), vote_c.as_slice()];
+        let mut tie_breaker = TieBreaker::new(TieBreak::Borda, 0);
+        let chosen = tie_breaker.strongest::<_, u32>(&[&"bob", &"sue", &"bill"], &[], &votes);
+        assert_eq!(chosen, Some((&"sue", TieBreak::Borda)));
+    }
+
+    #[test]
+    fn tie_break_random_is_deterministic() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        let draw = |order: &[&'static str]| {
+            let mut tie_breaker = TieBreaker::new(TieBreak::Random, 42);
+            let tied: Vec<_> = order.iter().collect();
+            (0..8)
+                .map(|_| *tie_breaker.weakest::<_, u32>(&tied, &[], &votes).unwrap().0)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draw(&["bob", "sue", "bill"]), draw(&["sue", "bill", "bob"]));
+    }
+
+    #[test]
+    fn tie_break_none() {
+        let votes: Vec<&[Vec<&str>]> = vec![];
+        let mut tie_breaker = TieBreaker::new(TieBreak::None, 0);
+        assert_eq!(
+            tie_breaker.weakest::<_, u32>(&[&"bob", &"sue"], &[], &votes),
+            None
+        );
+    }
+}